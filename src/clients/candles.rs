@@ -0,0 +1,419 @@
+use std::collections::{btree_map, BTreeMap, HashSet};
+use std::time::Duration;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use maybe_async::maybe_async;
+use rust_decimal::Decimal;
+
+use super::errors::{APIError, CandleError, PagerError};
+use super::indexer_client::MarketsClient;
+use super::indexer_client_types::{CandleResponseStruct, CandlesRequest, TradeResponseStruct};
+use super::pagination::{HistoryPager, PageSource};
+
+/// An OHLCV bar of any resolution, not only the ones the indexer serves.
+///
+/// Bars start at a multiple of their resolution since the Unix epoch, so a
+/// `4h` bar starts at 00:00, 04:00, ... UTC.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Candle {
+    pub started_at: DateTime<Utc>,
+    pub resolution: Duration,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    pub base_token_volume: Decimal,
+    pub usd_volume: Decimal,
+    pub trades: u32,
+}
+
+impl Candle {
+    /// Start of the next bar, the first instant this one does not cover.
+    pub fn ended_at(&self) -> DateTime<Utc> {
+        self.started_at + to_time_delta(self.resolution)
+    }
+}
+
+impl From<&CandleResponseStruct> for Candle {
+    fn from(candle: &CandleResponseStruct) -> Self {
+        Candle {
+            started_at: candle.started_at,
+            resolution: candle.resolution.duration(),
+            open: candle.open,
+            high: candle.high,
+            low: candle.low,
+            close: candle.close,
+            base_token_volume: candle.base_token_volume,
+            usd_volume: candle.usd_volume,
+            trades: candle.trades,
+        }
+    }
+}
+
+impl From<&Candle> for Candle {
+    fn from(candle: &Candle) -> Self {
+        candle.clone()
+    }
+}
+
+// ========================================
+// Building from trades
+// ========================================
+
+struct Bucket {
+    candle: Candle,
+    opened_at: DateTime<Utc>,
+    closed_at: DateTime<Utc>,
+    trade_ids: HashSet<String>,
+}
+
+/// Aggregates trades, from the REST API or the `v4_trades` channel, into
+/// candles of an arbitrary resolution.
+///
+/// Trades may arrive in any order: the open and close of a bar are its
+/// earliest and latest trade. A trade id seen twice is only counted once, so
+/// a REST backfill may overlap with live trades. Intervals without trades
+/// produce no bar.
+pub struct CandleBuilder {
+    resolution: Duration,
+    buckets: BTreeMap<DateTime<Utc>, Bucket>,
+}
+
+impl CandleBuilder {
+    /// `resolution` must be a non-zero whole number of milliseconds, e.g.
+    /// `Duration::from_secs(3 * 60)`.
+    pub fn new(resolution: Duration) -> Result<Self, CandleError> {
+        validate_resolution(resolution)?;
+        Ok(CandleBuilder {
+            resolution,
+            buckets: BTreeMap::new(),
+        })
+    }
+
+    pub fn resolution(&self) -> Duration {
+        self.resolution
+    }
+
+    /// Adds a trade to its bar. Returns `false` if the trade was already
+    /// counted.
+    pub fn add_trade(&mut self, trade: &TradeResponseStruct) -> bool {
+        let started_at = bucket_start(trade.created_at, self.resolution);
+        let usd_volume = trade.price * trade.size;
+
+        let bucket = match self.buckets.entry(started_at) {
+            btree_map::Entry::Vacant(entry) => {
+                entry.insert(Bucket {
+                    candle: Candle {
+                        started_at,
+                        resolution: self.resolution,
+                        open: trade.price,
+                        high: trade.price,
+                        low: trade.price,
+                        close: trade.price,
+                        base_token_volume: trade.size,
+                        usd_volume,
+                        trades: 1,
+                    },
+                    opened_at: trade.created_at,
+                    closed_at: trade.created_at,
+                    trade_ids: HashSet::from([trade.id.clone()]),
+                });
+                return true;
+            }
+            btree_map::Entry::Occupied(entry) => entry.into_mut(),
+        };
+
+        if !bucket.trade_ids.insert(trade.id.clone()) {
+            return false;
+        }
+
+        let candle = &mut bucket.candle;
+        candle.high = candle.high.max(trade.price);
+        candle.low = candle.low.min(trade.price);
+        candle.base_token_volume += trade.size;
+        candle.usd_volume += usd_volume;
+        candle.trades += 1;
+        if trade.created_at < bucket.opened_at {
+            candle.open = trade.price;
+            bucket.opened_at = trade.created_at;
+        }
+        if trade.created_at >= bucket.closed_at {
+            candle.close = trade.price;
+            bucket.closed_at = trade.created_at;
+        }
+        true
+    }
+
+    pub fn extend<'a>(&mut self, trades: impl IntoIterator<Item = &'a TradeResponseStruct>) {
+        for trade in trades {
+            self.add_trade(trade);
+        }
+    }
+
+    /// Adds every trade of `pager`, e.g.
+    /// `HistoryPager::trades(&client, request).stop_at(StopAt::Time(since))`.
+    #[maybe_async]
+    pub async fn backfill<S>(&mut self, mut pager: HistoryPager<S>) -> Result<(), PagerError>
+    where
+        S: PageSource<Record = TradeResponseStruct>,
+    {
+        while let Some(trade) = pager.next_record().await {
+            self.add_trade(&trade?);
+        }
+        Ok(())
+    }
+
+    /// All bars so far, oldest first. The last one may still be open.
+    pub fn candles(&self) -> Vec<Candle> {
+        self.buckets
+            .values()
+            .map(|bucket| bucket.candle.clone())
+            .collect()
+    }
+
+    /// Removes and returns the bars that ended at or before `now`, oldest
+    /// first.
+    pub fn take_closed(&mut self, now: DateTime<Utc>) -> Vec<Candle> {
+        let open_from = bucket_start(now, self.resolution);
+        let open = self.buckets.split_off(&open_from);
+        let closed = std::mem::replace(&mut self.buckets, open);
+        closed.into_values().map(|bucket| bucket.candle).collect()
+    }
+}
+
+// ========================================
+// Resampling
+// ========================================
+
+/// Merges candles into bars of a coarser `resolution`, oldest first.
+///
+/// Accepts indexer candles and [`Candle`]s in any order. `resolution` must
+/// be a multiple of the resolution of every input candle; duplicates of the
+/// same start time are counted once.
+pub fn resample<'a, C>(
+    candles: impl IntoIterator<Item = &'a C>,
+    resolution: Duration,
+) -> Result<Vec<Candle>, CandleError>
+where
+    C: 'a,
+    Candle: From<&'a C>,
+{
+    validate_resolution(resolution)?;
+
+    let mut sorted = BTreeMap::new();
+    for candle in candles {
+        let candle = Candle::from(candle);
+        if candle.resolution.is_zero()
+            || candle.resolution > resolution
+            || !resolution
+                .as_millis()
+                .is_multiple_of(candle.resolution.as_millis())
+        {
+            return Err(CandleError::IncompatibleResolution {
+                from: candle.resolution,
+                to: resolution,
+            });
+        }
+        sorted.entry(candle.started_at).or_insert(candle);
+    }
+
+    let mut resampled: Vec<Candle> = Vec::new();
+    for candle in sorted.into_values() {
+        let started_at = bucket_start(candle.started_at, resolution);
+        match resampled.last_mut() {
+            Some(bar) if bar.started_at == started_at => {
+                bar.high = bar.high.max(candle.high);
+                bar.low = bar.low.min(candle.low);
+                bar.close = candle.close;
+                bar.base_token_volume += candle.base_token_volume;
+                bar.usd_volume += candle.usd_volume;
+                bar.trades += candle.trades;
+            }
+            _ => resampled.push(Candle {
+                started_at,
+                resolution,
+                ..candle
+            }),
+        }
+    }
+    Ok(resampled)
+}
+
+// ========================================
+// Backfill
+// ========================================
+
+/// Fetches every candle of `request` started at or after `since`, walking
+/// back from the request's `to_iso` (or now) `page_size` candles at a time.
+/// Returns them oldest first.
+#[maybe_async]
+pub async fn backfill_candles<C: MarketsClient + Sync + ?Sized>(
+    client: &C,
+    request: CandlesRequest,
+    since: DateTime<Utc>,
+    page_size: u32,
+) -> Result<Vec<CandleResponseStruct>, APIError> {
+    let page_size = page_size.max(1);
+    let request = request.from_iso(since).limit(page_size);
+
+    let mut candles = BTreeMap::new();
+    let mut page_request = request.clone();
+    loop {
+        let page = client.get_perpetual_market_candles(page_request).await?;
+        let full = page.len() >= page_size as usize;
+        let oldest = page.iter().map(|candle| candle.started_at).min();
+        for candle in page {
+            candles.entry(candle.started_at).or_insert(candle);
+        }
+
+        match oldest {
+            Some(oldest) if full && oldest > since => {
+                let before = oldest - TimeDelta::milliseconds(1);
+                page_request = request.clone().to_iso(before);
+            }
+            _ => break,
+        }
+    }
+    Ok(candles.into_values().collect())
+}
+
+fn validate_resolution(resolution: Duration) -> Result<(), CandleError> {
+    if resolution.is_zero() || !resolution.subsec_nanos().is_multiple_of(1_000_000) {
+        return Err(CandleError::InvalidResolution(resolution));
+    }
+    Ok(())
+}
+
+fn bucket_start(time: DateTime<Utc>, resolution: Duration) -> DateTime<Utc> {
+    let resolution = resolution.as_millis() as i64;
+    let start = time.timestamp_millis().div_euclid(resolution) * resolution;
+    DateTime::from_timestamp_millis(start).unwrap_or(time)
+}
+
+fn to_time_delta(duration: Duration) -> TimeDelta {
+    TimeDelta::from_std(duration).unwrap_or(TimeDelta::MAX)
+}
+
+// ========================================
+// Tests
+// ========================================
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chrono::TimeZone;
+
+    use super::super::indexer_client::IndexerClient;
+    use super::super::indexer_client_types::CandleResolution;
+    use super::super::indexer_fixtures::IndexerFixtures;
+    use super::*;
+    use crate::constants::OrderSide;
+
+    fn at(minute: u32, second: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, 0, minute, second).unwrap()
+    }
+
+    fn trade(id: &str, created_at: DateTime<Utc>, price: &str, size: &str) -> TradeResponseStruct {
+        TradeResponseStruct {
+            id: id.to_string(),
+            side: OrderSide::BUY,
+            size: Decimal::from_str(size).unwrap(),
+            price: Decimal::from_str(price).unwrap(),
+            created_at,
+            created_at_height: 1,
+        }
+    }
+
+    fn candle_json(minute: u32) -> String {
+        format!(
+            r#"{{"startedAt":"2024-01-01T00:{minute:02}:00.000Z","ticker":"BTC-USD","resolution":"1MIN","low":"{minute}","high":"{minute}","open":"{minute}","close":"{minute}","baseTokenVolume":"1","usdVolume":"{minute}","trades":1,"startingOpenInterest":"0","id":"{minute}"}}"#
+        )
+    }
+
+    #[test]
+    fn test_builds_candles_from_unordered_trades() {
+        let mut builder = CandleBuilder::new(Duration::from_secs(3 * 60)).unwrap();
+        let newest_first = [
+            trade("4", at(4, 0), "120", "1"),
+            trade("3", at(2, 0), "110", "2"),
+            trade("2", at(1, 0), "90", "1"),
+            trade("1", at(0, 30), "100", "1"),
+        ];
+        builder.extend(&newest_first);
+        assert!(!builder.add_trade(&newest_first[1]));
+
+        let closed = builder.take_closed(at(3, 0));
+        assert_eq!(closed.len(), 1);
+        let bar = &closed[0];
+        assert_eq!(bar.started_at, at(0, 0));
+        assert_eq!(bar.ended_at(), at(3, 0));
+        assert_eq!(bar.open, Decimal::from(100));
+        assert_eq!(bar.high, Decimal::from(110));
+        assert_eq!(bar.low, Decimal::from(90));
+        assert_eq!(bar.close, Decimal::from(110));
+        assert_eq!(bar.base_token_volume, Decimal::from(4));
+        assert_eq!(bar.usd_volume, Decimal::from(410));
+        assert_eq!(bar.trades, 3);
+
+        let open = builder.candles();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].started_at, at(3, 0));
+    }
+
+    #[test]
+    fn test_resamples_indexer_candles() {
+        let candles: Vec<CandleResponseStruct> = [2, 1, 0]
+            .iter()
+            .map(|minute| serde_json::from_str(&candle_json(*minute)).unwrap())
+            .collect();
+
+        let bars = resample(&candles, Duration::from_secs(2 * 60)).unwrap();
+        assert_eq!(bars.len(), 2);
+        assert_eq!(bars[0].started_at, at(0, 0));
+        assert_eq!(bars[0].open, Decimal::from(0));
+        assert_eq!(bars[0].close, Decimal::from(1));
+        assert_eq!(bars[0].high, Decimal::from(1));
+        assert_eq!(bars[0].trades, 2);
+        assert_eq!(bars[1].started_at, at(2, 0));
+
+        assert_eq!(
+            resample(&candles, Duration::from_secs(90)).unwrap_err(),
+            CandleError::IncompatibleResolution {
+                from: Duration::from_secs(60),
+                to: Duration::from_secs(90),
+            }
+        );
+        assert!(matches!(
+            resample(&bars, CandleResolution::FiveMin.duration()),
+            Err(CandleError::IncompatibleResolution { .. })
+        ));
+        assert!(matches!(
+            CandleBuilder::new(Duration::from_micros(1500)),
+            Err(CandleError::InvalidResolution(_))
+        ));
+    }
+
+    #[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+    async fn test_backfills_candles_across_pages() {
+        let path = "/v4/candles/perpetualMarket/BTC-USD?resolution=1MIN&fromISO=2024-01-01T00%3A00%3A00.000Z";
+        let fixtures = IndexerFixtures::new()
+            .with_response(
+                &format!("{path}&limit=2"),
+                &format!(r#"{{"candles":[{},{}]}}"#, candle_json(3), candle_json(2)),
+            )
+            .with_response(
+                &format!("{path}&toISO=2024-01-01T00%3A01%3A59.999Z&limit=2"),
+                &format!(r#"{{"candles":[{},{}]}}"#, candle_json(1), candle_json(0)),
+            );
+        let client = IndexerClient::from_fixtures(fixtures.clone());
+
+        let request = CandlesRequest::new("BTC-USD".to_string(), CandleResolution::OneMin);
+        let candles = backfill_candles(&client, request, at(0, 0), 2)
+            .await
+            .unwrap();
+        let starts: Vec<_> = candles.iter().map(|candle| candle.started_at).collect();
+        assert_eq!(starts, vec![at(0, 0), at(1, 0), at(2, 0), at(3, 0)]);
+        assert_eq!(fixtures.requests().len(), 2);
+    }
+}
@@ -0,0 +1,398 @@
+use dydx_proto::cosmos_sdk_proto::cosmos::base::abci::v1beta1::TxResponse;
+use dydx_proto::dydxprotocol::{
+    clob::{
+        msg_cancel_order,
+        order::{self, ConditionType, Side, TimeInForce},
+        Order, OrderId,
+    },
+    subaccounts::SubaccountId,
+};
+
+use maybe_async::maybe_async;
+use rust_decimal::Decimal;
+
+use crate::constants::{OrderFlags, OrderSide, OrderTimeInForce, OrderType};
+
+use super::errors::CompositeError;
+use super::indexer_client::{IndexerClient, MarketsClient};
+use super::indexer_client_types::PerpetualMarketsRequest;
+use super::market_params::{MarketParams, Rounding};
+use super::subaccount::Subaccount;
+use super::validator_client::ValidatorClient;
+
+/// Expiry of an order: a block height for short-term orders, or a unix
+/// timestamp in seconds for stateful (long-term and conditional) orders.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GoodTil {
+    Block(u32),
+    Time(u32),
+}
+
+pub struct CompositeClient {
+    indexer_client: IndexerClient,
+    validator_client: ValidatorClient,
+}
+
+#[maybe_async]
+impl CompositeClient {
+    pub fn new(indexer_client: IndexerClient, validator_client: ValidatorClient) -> Self {
+        CompositeClient {
+            indexer_client,
+            validator_client,
+        }
+    }
+
+    pub fn indexer_client(&self) -> &IndexerClient {
+        &self.indexer_client
+    }
+
+    pub fn validator_client(&self) -> &ValidatorClient {
+        &self.validator_client
+    }
+
+    /// Places an order using human readable price and size, see
+    /// [`get_market_params`](Self::get_market_params) for `market`.
+    ///
+    /// The price is rounded to the tick size away from the other side of the
+    /// book, down for buys and up for sells, so the order never trades at a
+    /// worse price than asked. The size is rounded down to the step size and
+    /// the trigger price to the nearest tick.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn place_order(
+        &self,
+        subaccount: &Subaccount,
+        market: &MarketParams,
+        order_type: OrderType,
+        side: OrderSide,
+        price: Decimal,
+        size: Decimal,
+        client_id: u32,
+        time_in_force: OrderTimeInForce,
+        good_til: GoodTil,
+        post_only: bool,
+        reduce_only: bool,
+        trigger_price: Option<Decimal>,
+    ) -> Result<TxResponse, CompositeError> {
+        let order = build_order(
+            subaccount,
+            market,
+            order_type,
+            side,
+            price,
+            size,
+            client_id,
+            time_in_force,
+            good_til,
+            post_only,
+            reduce_only,
+            trigger_price,
+        )?;
+        Ok(self.validator_client.place_order(subaccount, order).await?)
+    }
+
+    pub async fn cancel_order(
+        &self,
+        subaccount: &Subaccount,
+        market: &MarketParams,
+        client_id: u32,
+        order_flags: OrderFlags,
+        good_til: GoodTil,
+    ) -> Result<TxResponse, CompositeError> {
+        let order_id = OrderId {
+            subaccount_id: Some(subaccount_id(subaccount)),
+            client_id,
+            order_flags: order_flags.into(),
+            clob_pair_id: market.clob_pair_id(),
+        };
+        let good_til = match good_til {
+            GoodTil::Block(block) => msg_cancel_order::GoodTilOneof::GoodTilBlock(block),
+            GoodTil::Time(time) => msg_cancel_order::GoodTilOneof::GoodTilBlockTime(time),
+        };
+        Ok(self
+            .validator_client
+            .cancel_order(subaccount, order_id, good_til)
+            .await?)
+    }
+
+    /// Fetches the parameters of a market from the indexer. They only change
+    /// with governance, so fetch them once and reuse them for every order
+    /// placed or cancelled in the market.
+    pub async fn get_market_params(&self, market: String) -> Result<MarketParams, CompositeError> {
+        let response = self
+            .indexer_client
+            .get_perpetual_markets(PerpetualMarketsRequest::new().ticker(market.clone()))
+            .await?;
+        match response.markets.get(&market) {
+            Some(market) => Ok(MarketParams::try_from(market)?),
+            None => Err(CompositeError::new(format!("Market not found: {market}"))),
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_order(
+    subaccount: &Subaccount,
+    market: &MarketParams,
+    order_type: OrderType,
+    side: OrderSide,
+    price: Decimal,
+    size: Decimal,
+    client_id: u32,
+    time_in_force: OrderTimeInForce,
+    good_til: GoodTil,
+    post_only: bool,
+    reduce_only: bool,
+    trigger_price: Option<Decimal>,
+) -> Result<Order, CompositeError> {
+    let condition_type = match order_type {
+        OrderType::StopLimit | OrderType::StopMarket => ConditionType::StopLoss,
+        OrderType::TakeProfitLimit | OrderType::TakeProfitMarket => ConditionType::TakeProfit,
+        OrderType::Limit | OrderType::Market => ConditionType::Unspecified,
+    };
+
+    let order_flags = match (condition_type, good_til) {
+        (ConditionType::Unspecified, GoodTil::Block(_)) => OrderFlags::ShortTerm,
+        (ConditionType::Unspecified, GoodTil::Time(_)) => OrderFlags::LongTerm,
+        (_, GoodTil::Time(_)) => OrderFlags::Conditional,
+        (_, GoodTil::Block(_)) => {
+            return Err(CompositeError::new(
+                "Conditional orders must expire at a block time".to_string(),
+            ))
+        }
+    };
+
+    if order_type == OrderType::Market && order_flags != OrderFlags::ShortTerm {
+        return Err(CompositeError::new(
+            "Market orders must expire at a block height".to_string(),
+        ));
+    }
+
+    let time_in_force = match (order_type, time_in_force, post_only) {
+        (OrderType::Market, _, false) => TimeInForce::Ioc,
+        (_, OrderTimeInForce::GTT, true) => TimeInForce::PostOnly,
+        (_, OrderTimeInForce::GTT, false) => TimeInForce::Unspecified,
+        (_, OrderTimeInForce::IOC, false) => TimeInForce::Ioc,
+        (_, OrderTimeInForce::FOK, false) => TimeInForce::FillOrKill,
+        (_, _, true) => {
+            return Err(CompositeError::new(
+                "Post only orders must be good til time and not market orders".to_string(),
+            ))
+        }
+    };
+
+    let conditional_order_trigger_subticks = match (condition_type, trigger_price) {
+        (ConditionType::Unspecified, _) => 0,
+        (_, Some(trigger_price)) => market.price_to_subticks(trigger_price, Rounding::Nearest)?,
+        (_, None) => {
+            return Err(CompositeError::new(
+                "Conditional orders require a trigger price".to_string(),
+            ))
+        }
+    };
+
+    Ok(Order {
+        order_id: Some(OrderId {
+            subaccount_id: Some(subaccount_id(subaccount)),
+            client_id,
+            order_flags: order_flags.into(),
+            clob_pair_id: market.clob_pair_id(),
+        }),
+        side: match side {
+            OrderSide::BUY => Side::Buy,
+            OrderSide::SELL => Side::Sell,
+        }
+        .into(),
+        quantums: market.size_to_quantums(size, Rounding::Down)?,
+        subticks: market.price_to_subticks(price, price_rounding(side))?,
+        time_in_force: time_in_force.into(),
+        reduce_only,
+        client_metadata: 0,
+        condition_type: condition_type.into(),
+        conditional_order_trigger_subticks,
+        good_til_oneof: Some(match good_til {
+            GoodTil::Block(block) => order::GoodTilOneof::GoodTilBlock(block),
+            GoodTil::Time(time) => order::GoodTilOneof::GoodTilBlockTime(time),
+        }),
+        ..Default::default()
+    })
+}
+
+fn subaccount_id(subaccount: &Subaccount) -> SubaccountId {
+    SubaccountId {
+        owner: subaccount.address().to_string(),
+        number: subaccount.subaccount_number(),
+    }
+}
+
+/// Rounds a limit price to the passive side, so a buy never pays more and a
+/// sell never receives less than asked.
+fn price_rounding(side: OrderSide) -> Rounding {
+    match side {
+        OrderSide::BUY => Rounding::Down,
+        OrderSide::SELL => Rounding::Up,
+    }
+}
+
+// ========================================
+// Tests
+// ========================================
+
+#[cfg(test)]
+mod tests {
+    use cosmrs::crypto::secp256k1::SigningKey;
+
+    use super::super::indexer_client_types::PerpetualMarketResponseStruct;
+    use super::super::wallet::LocalWallet;
+    use super::*;
+
+    fn market(
+        ticker: &str,
+        atomic_resolution: i32,
+        tick_size: &str,
+        step_size: &str,
+    ) -> MarketParams {
+        let market: PerpetualMarketResponseStruct = serde_json::from_value(serde_json::json!({
+            "clobPairId": "0",
+            "ticker": ticker,
+            "status": "ACTIVE",
+            "lastPrice": "0",
+            "oraclePrice": "0",
+            "priceChange24H": "0",
+            "volume24H": "0",
+            "trades24H": 0,
+            "nextFundingRate": "0",
+            "initialMarginFraction": "0.05",
+            "maintenanceMarginFraction": "0.03",
+            "basePositionNotional": "0",
+            "openInterest": "0",
+            "atomicResolution": atomic_resolution,
+            "quantumConversionExponent": -9,
+            "tickSize": tick_size,
+            "stepSize": step_size,
+            "stepBaseQuantums": 1000000,
+            "subticksPerTick": 100000
+        }))
+        .unwrap();
+        MarketParams::try_from(&market).unwrap()
+    }
+
+    fn decimal(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn limit_order(
+        market: &MarketParams,
+        side: OrderSide,
+        price: &str,
+        size: &str,
+    ) -> Result<Order, CompositeError> {
+        build_order(
+            &subaccount(),
+            market,
+            OrderType::Limit,
+            side,
+            decimal(price),
+            decimal(size),
+            1,
+            OrderTimeInForce::GTT,
+            GoodTil::Block(1_000),
+            false,
+            false,
+            None,
+        )
+    }
+
+    fn subaccount() -> Subaccount {
+        let signing_key = SigningKey::from_slice(&[7u8; 32]).unwrap();
+        LocalWallet::from_signing_key(signing_key)
+            .unwrap()
+            .subaccount(0)
+    }
+
+    #[test]
+    fn test_limit_prices_round_to_the_passive_side() {
+        let btc = market("BTC-USD", -10, "1", "0.0001");
+        let buy = limit_order(&btc, OrderSide::BUY, "50000.6", "0.01004").unwrap();
+        assert_eq!(buy.subticks, 5_000_000_000);
+        assert_eq!(buy.quantums, 100_000_000);
+        let sell = limit_order(&btc, OrderSide::SELL, "50000.4", "0.01006").unwrap();
+        assert_eq!(sell.subticks, 5_000_100_000);
+        assert_eq!(sell.quantums, 100_000_000);
+
+        let eth = market("ETH-USD", -9, "0.1", "0.001");
+        let order = limit_order(&eth, OrderSide::BUY, "2345.6", "1.5").unwrap();
+        assert_eq!(order.subticks, 2_345_600_000);
+        assert_eq!(order.quantums, 1_500_000_000);
+
+        assert!(limit_order(&btc, OrderSide::BUY, "50000", "0.00001").is_err());
+        assert!(limit_order(&btc, OrderSide::BUY, "0.1", "0.01").is_err());
+    }
+
+    #[test]
+    fn test_build_short_term_and_conditional_orders() {
+        let subaccount = subaccount();
+        let btc = market("BTC-USD", -10, "1", "0.0001");
+
+        let order = build_order(
+            &subaccount,
+            &btc,
+            OrderType::Limit,
+            OrderSide::BUY,
+            decimal("50000"),
+            decimal("0.01"),
+            42,
+            OrderTimeInForce::GTT,
+            GoodTil::Block(1_000),
+            true,
+            false,
+            None,
+        )
+        .unwrap();
+        let order_id = order.order_id.clone().unwrap();
+        assert_eq!(order_id.order_flags, 0);
+        assert_eq!(order_id.client_id, 42);
+        assert_eq!(order_id.subaccount_id.unwrap().owner, subaccount.address());
+        assert_eq!(order.side, Side::Buy as i32);
+        assert_eq!(order.time_in_force, TimeInForce::PostOnly as i32);
+        assert_eq!(
+            order.good_til_oneof,
+            Some(order::GoodTilOneof::GoodTilBlock(1_000))
+        );
+
+        let order = build_order(
+            &subaccount,
+            &btc,
+            OrderType::StopLimit,
+            OrderSide::SELL,
+            decimal("48000"),
+            decimal("0.01"),
+            43,
+            OrderTimeInForce::IOC,
+            GoodTil::Time(1_700_000_000),
+            false,
+            true,
+            Some(decimal("49000")),
+        )
+        .unwrap();
+        assert_eq!(order.order_id.unwrap().order_flags, 32);
+        assert_eq!(order.condition_type, ConditionType::StopLoss as i32);
+        assert_eq!(order.conditional_order_trigger_subticks, 4_900_000_000);
+        assert!(order.reduce_only);
+
+        assert!(build_order(
+            &subaccount,
+            &btc,
+            OrderType::StopMarket,
+            OrderSide::SELL,
+            decimal("48000"),
+            decimal("0.01"),
+            44,
+            OrderTimeInForce::IOC,
+            GoodTil::Time(1_700_000_000),
+            false,
+            false,
+            None,
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,168 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+use super::indexer_client_types::PnLTicksResponseStruct;
+
+/// One PnL tick of an [`EquityCurve`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct EquityPoint {
+    pub time: DateTime<Utc>,
+    pub block_height: u64,
+    pub equity: Decimal,
+    /// Deposits minus withdrawals since the subaccount was created.
+    pub net_transfers: Decimal,
+}
+
+/// The equity of a subaccount over time, built from the PnL ticks of
+/// `get_sub_account_historical_pnls`, with performance measured net of
+/// deposits and withdrawals.
+///
+/// The return of a period between two ticks is its change in equity minus
+/// the transfers made during it, over the equity at its start. Periods that
+/// start without positive equity have no return and are skipped.
+#[derive(Clone, PartialEq, Debug)]
+pub struct EquityCurve {
+    points: Vec<EquityPoint>,
+}
+
+impl EquityCurve {
+    /// Accepts ticks in any order, e.g. newest first as the indexer returns
+    /// them. Ticks of the same block are counted once.
+    pub fn new<'a>(ticks: impl IntoIterator<Item = &'a PnLTicksResponseStruct>) -> Self {
+        let mut points: Vec<EquityPoint> = ticks
+            .into_iter()
+            .map(|tick| EquityPoint {
+                time: tick.block_time,
+                block_height: tick.block_height,
+                equity: tick.equity,
+                net_transfers: tick.net_transfers,
+            })
+            .collect();
+        points.sort_by_key(|point| point.block_height);
+        points.dedup_by_key(|point| point.block_height);
+        EquityCurve { points }
+    }
+
+    /// Oldest first.
+    pub fn points(&self) -> &[EquityPoint] {
+        &self.points
+    }
+
+    /// Return of every period between two ticks, net of transfers.
+    pub fn returns(&self) -> Vec<Decimal> {
+        self.points
+            .windows(2)
+            .filter(|pair| pair[0].equity > Decimal::ZERO)
+            .map(|pair| {
+                let transfers = pair[1].net_transfers - pair[0].net_transfers;
+                (pair[1].equity - pair[0].equity - transfers) / pair[0].equity
+            })
+            .collect()
+    }
+
+    /// Compounded return of all periods, unaffected by the size and timing
+    /// of transfers. `None` with fewer than two ticks.
+    pub fn time_weighted_return(&self) -> Option<Decimal> {
+        let returns = self.returns();
+        if returns.is_empty() {
+            return None;
+        }
+        let growth = returns.iter().fold(Decimal::ONE, |growth, period| {
+            growth * (Decimal::ONE + period)
+        });
+        Some(growth - Decimal::ONE)
+    }
+
+    /// Largest fall from a peak of the compounded returns, as a fraction of
+    /// the peak, so that transfers do not count as drawdowns.
+    pub fn max_drawdown(&self) -> Decimal {
+        let mut index = Decimal::ONE;
+        let mut peak = Decimal::ONE;
+        let mut max_drawdown = Decimal::ZERO;
+        for period in self.returns() {
+            index *= Decimal::ONE + period;
+            peak = peak.max(index);
+            if peak > Decimal::ZERO {
+                max_drawdown = max_drawdown.max((peak - index) / peak);
+            }
+        }
+        max_drawdown
+    }
+
+    /// Mean over sample standard deviation of the period returns, with a zero
+    /// risk free rate, scaled by `periods_per_year`, e.g. `365.0` for daily
+    /// ticks or `24.0 * 365.0` for hourly ones. `None` with fewer than two
+    /// periods or returns that never change.
+    pub fn sharpe_ratio(&self, periods_per_year: f64) -> Option<f64> {
+        let returns: Vec<f64> = self
+            .returns()
+            .iter()
+            .filter_map(|period| period.to_f64())
+            .collect();
+        if returns.len() < 2 {
+            return None;
+        }
+        let count = returns.len() as f64;
+        let mean = returns.iter().sum::<f64>() / count;
+        let variance = returns
+            .iter()
+            .map(|period| (period - mean).powi(2))
+            .sum::<f64>()
+            / (count - 1.0);
+        if variance == 0.0 {
+            return None;
+        }
+        Some(mean / variance.sqrt() * periods_per_year.sqrt())
+    }
+}
+
+// ========================================
+// Tests
+// ========================================
+
+#[cfg(test)]
+mod tests {
+    use super::super::indexer_client_types::HistoricalPnLResponse;
+    use super::*;
+
+    fn tick(height: u64, equity: &str, net_transfers: &str) -> String {
+        format!(
+            r#"{{"id":"{height}","subaccountId":"s","equity":"{equity}","totalPnl":"0","netTransfers":"{net_transfers}","createdAt":"2024-01-0{height}T00:00:00.000Z","blockHeight":"{height}","blockTime":"2024-01-0{height}T00:00:00.000Z"}}"#
+        )
+    }
+
+    #[test]
+    fn test_performance_is_net_of_transfers() {
+        let response: HistoricalPnLResponse = serde_json::from_str(&format!(
+            r#"{{"historicalPnl":[{},{},{},{},{}]}}"#,
+            tick(5, "1584", "1500"),
+            tick(4, "1440", "1500"),
+            tick(3, "1600", "1500"),
+            tick(2, "1100", "1000"),
+            tick(1, "1000", "1000"),
+        ))
+        .unwrap();
+        let decimal = |value: &str| value.parse::<Decimal>().unwrap();
+
+        let curve = EquityCurve::new(&response);
+        assert_eq!(curve.points()[0].block_height, 1);
+        assert_eq!(
+            curve.returns(),
+            vec![
+                decimal("0.1"),
+                decimal("0"),
+                decimal("-0.1"),
+                decimal("0.1")
+            ]
+        );
+        assert_eq!(curve.time_weighted_return(), Some(decimal("0.089")));
+        assert_eq!(curve.max_drawdown(), decimal("0.1"));
+
+        let sharpe = curve.sharpe_ratio(1.0).unwrap();
+        assert!((sharpe - 0.2611).abs() < 1e-3);
+        assert!(EquityCurve::new(&response.historical_pnl[..1])
+            .sharpe_ratio(365.0)
+            .is_none());
+    }
+}
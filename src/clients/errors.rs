@@ -1,12 +1,15 @@
-use std::{error::Error, fmt};
+use std::{error::Error, fmt, io, num::ParseIntError, path::PathBuf, time::Duration};
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug)]
-pub(crate) struct ConstructorError {
+pub struct ConstructorError {
     message: String,
 }
 
 impl ConstructorError {
-    pub fn new(message: String) -> Self {
+    pub(crate) fn new(message: String) -> Self {
         ConstructorError { message }
     }
 }
@@ -19,21 +22,514 @@ impl fmt::Display for ConstructorError {
 
 impl Error for ConstructorError {}
 
+/// Failure of a request to the indexer REST API.
 #[derive(Debug)]
-pub struct APIError {
-    message: String,
+pub enum APIError {
+    /// The request could not be built, e.g. the url is invalid.
+    InvalidRequest(String),
+    /// The request did not complete: connection failure, timeout or an
+    /// interrupted response body.
+    Transport(String),
+    /// The indexer answered with a non-success status other than 429.
+    Status {
+        status: u16,
+        errors: Vec<IndexerErrorMessage>,
+        body: String,
+    },
+    /// The indexer answered with 429 Too Many Requests.
+    RateLimited {
+        retry_after: Option<Duration>,
+        errors: Vec<IndexerErrorMessage>,
+    },
+    /// The response body did not match the expected type.
+    Decode { message: String, body: String },
+    /// The client side rate limiter is out of tokens and fails fast, see
+    /// [`RateLimitMode::FailFast`](super::rate_limiter::RateLimitMode::FailFast).
+    /// The request was not sent.
+    Throttled { retry_after: Duration },
+    /// The indexer answered, but the response could not be saved by the
+    /// client's [`FixtureRecorder`](super::indexer_fixtures::FixtureRecorder).
+    Record(FixtureError),
+}
+
+/// An entry of the `errors` array returned by the indexer alongside a
+/// non-success status.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct IndexerErrorMessage {
+    pub msg: String,
+    pub param: Option<String>,
+    pub location: Option<String>,
+    pub value: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct IndexerErrorBody {
+    errors: Vec<IndexerErrorMessage>,
 }
 
 impl APIError {
-    pub(crate) fn new(message: String) -> Self {
-        APIError { message }
+    /// Builds the error for a non-success response, parsing the indexer's
+    /// `errors` body when there is one.
+    pub(crate) fn from_response(status: u16, retry_after: Option<Duration>, body: String) -> Self {
+        let errors = match serde_json::from_str::<IndexerErrorBody>(&body) {
+            Ok(error_body) => error_body.errors,
+            Err(_) => vec![],
+        };
+
+        if status == 429 {
+            APIError::RateLimited {
+                retry_after,
+                errors,
+            }
+        } else {
+            APIError::Status {
+                status,
+                errors,
+                body,
+            }
+        }
+    }
+
+    /// Whether the same request may succeed if sent again: transport
+    /// failures, rate limiting, request timeouts and server errors.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            APIError::Transport(_) | APIError::RateLimited { .. } => true,
+            APIError::Status { status, .. } => *status == 408 || *status >= 500,
+            APIError::InvalidRequest(_)
+            | APIError::Decode { .. }
+            | APIError::Throttled { .. }
+            | APIError::Record(_) => false,
+        }
+    }
+
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            APIError::Status { status, .. } => Some(*status),
+            APIError::RateLimited { .. } => Some(429),
+            _ => None,
+        }
+    }
+
+    /// How long the indexer asked to wait before retrying, if it did.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            APIError::RateLimited { retry_after, .. } => *retry_after,
+            APIError::Throttled { retry_after } => Some(*retry_after),
+            _ => None,
+        }
     }
 }
 
 impl fmt::Display for APIError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            APIError::InvalidRequest(message) => write!(f, "Invalid request: {}", message),
+            APIError::Transport(message) => write!(f, "Transport error: {}", message),
+            APIError::Status {
+                status,
+                errors,
+                body,
+            } => {
+                if errors.is_empty() {
+                    write!(f, "HTTP {}: {}", status, body)
+                } else {
+                    write!(f, "HTTP {}: {}", status, error_messages(errors))
+                }
+            }
+            APIError::RateLimited { errors, .. } => {
+                write!(f, "Rate limited: {}", error_messages(errors))
+            }
+            APIError::Decode { message, .. } => write!(f, "Decode error: {}", message),
+            APIError::Throttled { retry_after } => write!(
+                f,
+                "Throttled by the client rate limiter, retry after {:?}",
+                retry_after
+            ),
+            APIError::Record(error) => write!(f, "Failed to record response: {}", error),
+        }
+    }
+}
+
+fn error_messages(errors: &[IndexerErrorMessage]) -> String {
+    errors
+        .iter()
+        .map(|error| match &error.param {
+            Some(param) => format!("{}: {}", param, error.msg),
+            None => error.msg.clone(),
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+impl Error for APIError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            APIError::Record(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Failure of a [`HistoryPager`](super::pagination::HistoryPager).
+#[derive(Debug)]
+pub enum PagerError {
+    /// A page request failed.
+    Api(APIError),
+    /// More records share one height than the pager can request at once, so
+    /// it cannot page past that height. No request failed.
+    PageOverflow { height: u64, limit: u32 },
+}
+
+impl fmt::Display for PagerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PagerError::Api(error) => write!(f, "{}", error),
+            PagerError::PageOverflow { height, limit } => write!(
+                f,
+                "More than {} records at height {}, which cannot be paged through",
+                limit, height
+            ),
+        }
+    }
+}
+
+impl Error for PagerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PagerError::Api(error) => Some(error),
+            PagerError::PageOverflow { .. } => None,
+        }
+    }
+}
+
+impl From<APIError> for PagerError {
+    fn from(error: APIError) -> Self {
+        PagerError::Api(error)
+    }
+}
+
+#[derive(Debug)]
+pub struct SocketError {
+    message: String,
+}
+
+impl SocketError {
+    pub(crate) fn new(message: String) -> Self {
+        SocketError { message }
+    }
+}
+
+impl fmt::Display for SocketError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.message)
     }
 }
 
-impl Error for APIError {}
+impl Error for SocketError {}
+
+#[derive(Debug)]
+pub struct ValidatorError {
+    message: String,
+}
+
+impl ValidatorError {
+    pub(crate) fn new(message: String) -> Self {
+        ValidatorError { message }
+    }
+}
+
+impl fmt::Display for ValidatorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for ValidatorError {}
+
+#[derive(Debug)]
+pub struct CompositeError {
+    message: String,
+}
+
+impl CompositeError {
+    pub(crate) fn new(message: String) -> Self {
+        CompositeError { message }
+    }
+}
+
+impl fmt::Display for CompositeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for CompositeError {}
+
+impl From<APIError> for CompositeError {
+    fn from(error: APIError) -> Self {
+        CompositeError::new(error.to_string())
+    }
+}
+
+impl From<ValidatorError> for CompositeError {
+    fn from(error: ValidatorError) -> Self {
+        CompositeError::new(error.to_string())
+    }
+}
+
+impl From<MarketParamsError> for CompositeError {
+    fn from(error: MarketParamsError) -> Self {
+        CompositeError::new(error.to_string())
+    }
+}
+
+/// Why a [`MarketParams`](super::market_params::MarketParams) could not be
+/// built or a price or size could not be converted.
+#[derive(Clone, PartialEq, Debug)]
+pub enum MarketParamsError {
+    /// The indexer's clob pair id is not a number.
+    InvalidClobPairId {
+        ticker: String,
+        source: ParseIntError,
+    },
+    /// The step base quantums or subticks per tick are zero.
+    ZeroIncrement { ticker: String },
+    /// The size is smaller than one step, whatever the rounding.
+    BelowStepSize {
+        ticker: String,
+        size: Decimal,
+        step_size: Decimal,
+    },
+    /// The price rounds to zero subticks.
+    BelowTickSize {
+        ticker: String,
+        price: Decimal,
+        tick_size: Decimal,
+    },
+    /// The market's exponents scale by more than `Decimal` can express.
+    ExponentOutOfRange(i32),
+    /// The value, once scaled or rounded, does not fit.
+    OutOfRange(Decimal),
+}
+
+impl fmt::Display for MarketParamsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MarketParamsError::InvalidClobPairId { ticker, source } => {
+                write!(f, "Invalid clob pair id for {}: {}", ticker, source)
+            }
+            MarketParamsError::ZeroIncrement { ticker } => write!(
+                f,
+                "Step base quantums and subticks per tick of {} must not be zero",
+                ticker
+            ),
+            MarketParamsError::BelowStepSize {
+                ticker,
+                size,
+                step_size,
+            } => write!(
+                f,
+                "Size {} is below the step size {} of {}",
+                size, step_size, ticker
+            ),
+            MarketParamsError::BelowTickSize {
+                ticker,
+                price,
+                tick_size,
+            } => write!(
+                f,
+                "Price {} is below the tick size {} of {}",
+                price, tick_size, ticker
+            ),
+            MarketParamsError::ExponentOutOfRange(exponent) => {
+                write!(f, "Exponent {} is out of range", exponent)
+            }
+            MarketParamsError::OutOfRange(value) => write!(f, "{} is out of range", value),
+        }
+    }
+}
+
+impl Error for MarketParamsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MarketParamsError::InvalidClobPairId { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct WalletError {
+    message: String,
+}
+
+impl WalletError {
+    pub(crate) fn new(message: String) -> Self {
+        WalletError { message }
+    }
+}
+
+impl fmt::Display for WalletError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for WalletError {}
+
+impl From<WalletError> for ValidatorError {
+    fn from(error: WalletError) -> Self {
+        ValidatorError::new(error.to_string())
+    }
+}
+
+/// Why an [`Orderbook`](super::orderbook::Orderbook) could not apply a
+/// message.
+#[derive(Clone, PartialEq, Debug)]
+pub enum OrderbookError {
+    /// Messages between the last one seen and this one were missed. The book
+    /// is out of sync until it is seeded again.
+    Gap { expected: u64, received: u64 },
+    /// An update arrived while the book was waiting for a new snapshot.
+    OutOfSync,
+}
+
+impl fmt::Display for OrderbookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderbookError::Gap { expected, received } => write!(
+                f,
+                "Orderbook gap: expected message {} but received {}",
+                expected, received
+            ),
+            OrderbookError::OutOfSync => write!(f, "Orderbook is out of sync"),
+        }
+    }
+}
+
+impl Error for OrderbookError {}
+
+/// Why candles could not be built or resampled.
+#[derive(Clone, PartialEq, Debug)]
+pub enum CandleError {
+    /// The resolution is zero or not a whole number of milliseconds.
+    InvalidResolution(Duration),
+    /// Candles of resolution `from` do not divide evenly into `to`.
+    IncompatibleResolution { from: Duration, to: Duration },
+}
+
+impl fmt::Display for CandleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CandleError::InvalidResolution(resolution) => write!(
+                f,
+                "Candle resolution must be a whole number of milliseconds, got {:?}",
+                resolution
+            ),
+            CandleError::IncompatibleResolution { from, to } => write!(
+                f,
+                "Cannot resample {:?} candles into {:?} candles",
+                from, to
+            ),
+        }
+    }
+}
+
+impl Error for CandleError {}
+
+/// Why a [`Portfolio`](super::risk::Portfolio) margin could not be computed.
+#[derive(Clone, PartialEq, Debug)]
+pub enum RiskError {
+    /// A position is held in a market missing from the markets given.
+    UnknownMarket(String),
+}
+
+impl fmt::Display for RiskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RiskError::UnknownMarket(market) => write!(f, "Unknown market: {}", market),
+        }
+    }
+}
+
+impl Error for RiskError {}
+
+/// Why indexer fixtures could not be loaded or recorded.
+#[derive(Debug)]
+pub enum FixtureError {
+    /// Reading or writing a fixture file or directory failed.
+    Io { path: PathBuf, source: io::Error },
+    /// A fixture file is not a recorded response.
+    Parse { path: PathBuf, message: String },
+    /// A response to be recorded is not JSON.
+    InvalidResponse { request: String, message: String },
+}
+
+impl fmt::Display for FixtureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FixtureError::Io { path, source } => {
+                write!(f, "Fixture I/O error on {}: {}", path.display(), source)
+            }
+            FixtureError::Parse { path, message } => {
+                write!(f, "Invalid fixture {}: {}", path.display(), message)
+            }
+            FixtureError::InvalidResponse { request, message } => {
+                write!(f, "Response to {} is not JSON: {}", request, message)
+            }
+        }
+    }
+}
+
+impl Error for FixtureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FixtureError::Io { source, .. } => Some(source),
+            FixtureError::Parse { .. } | FixtureError::InvalidResponse { .. } => None,
+        }
+    }
+}
+
+// ========================================
+// Tests
+// ========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_error_from_response() {
+        let body = r#"{"errors":[{"value":"x","msg":"address must be a valid dydx address","param":"address","location":"query"}]}"#;
+        let error = APIError::from_response(400, None, body.to_string());
+        assert_eq!(error.status(), Some(400));
+        assert!(!error.is_retryable());
+        match &error {
+            APIError::Status { errors, .. } => {
+                assert_eq!(errors.len(), 1);
+                assert_eq!(errors[0].param.as_deref(), Some("address"));
+            }
+            _ => panic!("unexpected error: {error:?}"),
+        }
+        assert_eq!(
+            error.to_string(),
+            "HTTP 400: address: address must be a valid dydx address"
+        );
+
+        let error = APIError::from_response(429, Some(Duration::from_secs(2)), "".to_string());
+        assert!(error.is_retryable());
+        assert_eq!(error.retry_after(), Some(Duration::from_secs(2)));
+
+        let error = APIError::from_response(503, None, "<html>".to_string());
+        assert!(error.is_retryable());
+        assert_eq!(error.to_string(), "HTTP 503: <html>");
+
+        assert!(APIError::Transport("timed out".to_string()).is_retryable());
+        assert!(!APIError::Decode {
+            message: "missing field".to_string(),
+            body: "{}".to_string()
+        }
+        .is_retryable());
+    }
+}
@@ -1,13 +1,7 @@
-use crate::arg_to_tuple;
-use crate::clients::CamelCaseify;
-use crate::constants::TimePeriod;
-use crate::option_t_to_string_option;
-use crate::option_to_tuple;
+use crate::constants::PnlTickInterval;
 use serde::{Deserialize, Serialize};
 
-use std::{borrow::Borrow, collections::HashMap, time::Duration};
-
-use crate::constants::{OrderSide, OrderStatus, OrderType, TickerType};
+use std::{sync::Arc, time::Duration};
 
 use super::indexer_client_types::CandleResponse;
 use super::indexer_client_types::FillResponse;
@@ -17,35 +11,121 @@ use super::indexer_client_types::OrderbookResponse;
 use super::indexer_client_types::PerpetualMarketsResponse;
 use super::indexer_client_types::SparklineResponse;
 use super::indexer_client_types::TradeResponse;
+use super::indexer_client_types::{
+    AffiliateAddressResponse, AffiliateMetadataResponse, AffiliateSnapshotRequest,
+    AffiliateSnapshotResponse, AffiliateTotalVolumeResponse, ComplianceResponse, HeightResponse,
+    HistoricalBlockTradingRewardsRequest, HistoricalBlockTradingRewardsResponse,
+    HistoricalTradingRewardAggregationsRequest, HistoricalTradingRewardAggregationsResponse,
+    MegavaultHistoricalPnLResponse, MegavaultPositionResponse, ParentSubaccountFillsRequest,
+    ParentSubaccountOrdersRequest, ParentSubaccountResponse, ParentSubaccountTransfersRequest,
+    TimeResponse, TransferBetweenResponse, TransfersBetweenRequest, VaultsHistoricalPnLResponse,
+};
+use super::indexer_client_types::{
+    CandlesRequest, FillsRequest, HistoricalFundingRequest, HistoricalPnLRequest, OrdersRequest,
+    PerpetualMarketsRequest, SparklinesRequest, SubaccountsRequest, TradesRequest,
+    TransfersRequest,
+};
+use super::indexer_fixtures::{request_key, FixtureRecorder, IndexerFixtures};
+use super::rate_limiter::{EndpointGroup, RateLimiter};
+use super::response_cache::{CacheLookup, ResponseCache};
+use super::retry::{parse_retry_after, RetryPolicy};
 use super::{
     errors::{APIError, ConstructorError},
     indexer_client_types::{
         AssetPositionResponse, OrderResponseStruct, PerpetualPositionResponse,
-        PositionDetailsRequest, SubAccountResponseObject, TransferResponse,
+        PositionDetailsRequest, SubaccountResponse, SubaccountsResponse, TransferResponse,
     },
 };
-use is_url::is_url;
-use reqwest::blocking::{self, Client as BlockingClient};
+use chrono::Utc;
+use maybe_async::maybe_async;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+#[cfg(feature = "blocking")]
+use reqwest::blocking::{Client, ClientBuilder};
+use reqwest::{
+    header::{ETAG, IF_NONE_MATCH, RETRY_AFTER},
+    StatusCode, Url,
+};
+#[cfg(not(feature = "blocking"))]
+use reqwest::{Client, ClientBuilder};
 
 pub struct IndexerClient {
     indexer_config: IndexerConfig,
-    api_timeout: u32,
     req_handler: RestHandler,
 }
 
 impl IndexerClient {
+    /// Requests time out after `api_timeout` milliseconds, 3000 by default.
     pub fn new(
         indexer_config: IndexerConfig,
         api_timeout: Option<u32>,
     ) -> Result<Self, ConstructorError> {
-        let req_handler = RestHandler::new(indexer_config.rest_endpoint.clone(), None)?;
+        let req_handler = RestHandler::new(indexer_config.rest_endpoint.clone(), api_timeout)?;
         Ok(IndexerClient {
             indexer_config,
-            api_timeout: api_timeout.unwrap_or(3000),
-            // utiltiy: UtilityClient::new(indexer_config.clone(), req_handler.clone()),
             req_handler,
         })
     }
+
+    /// Creates a client that answers every request from `fixtures` instead
+    /// of the network, for testing code written against [`AccountsClient`]
+    /// and [`MarketsClient`].
+    pub fn from_fixtures(fixtures: IndexerFixtures) -> Self {
+        let indexer_config =
+            IndexerConfig::new("http://localhost".to_string(), "ws://localhost".to_string());
+        let req_handler =
+            RestHandler::from_fixtures(indexer_config.rest_endpoint.clone(), fixtures);
+        IndexerClient {
+            indexer_config,
+            req_handler,
+        }
+    }
+
+    /// Replaces the default [`RetryPolicy`] applied to every request.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.req_handler.retry_policy = retry_policy;
+        self
+    }
+
+    /// Throttles requests through `rate_limiter`, which may be shared with
+    /// other clients.
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.req_handler.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    pub fn rate_limiter(&self) -> Option<&Arc<RateLimiter>> {
+        self.req_handler.rate_limiter.as_ref()
+    }
+
+    /// Serves [`MarketsClient`] requests from `cache` while they are fresh.
+    /// The cache may be shared with other clients.
+    pub fn with_cache(mut self, cache: Arc<ResponseCache>) -> Self {
+        self.req_handler.cache = Some(cache);
+        self
+    }
+
+    pub fn cache(&self) -> Option<&Arc<ResponseCache>> {
+        self.req_handler.cache.as_ref()
+    }
+
+    /// Saves every response received from the indexer with `recorder`, to
+    /// replay them offline with [`IndexerFixtures::load`].
+    pub fn with_recorder(mut self, recorder: FixtureRecorder) -> Self {
+        self.req_handler.recorder = Some(recorder);
+        self
+    }
+
+    pub fn recorder(&self) -> Option<&FixtureRecorder> {
+        self.req_handler.recorder.as_ref()
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.req_handler.retry_policy
+    }
+
+    pub fn indexer_config(&self) -> &IndexerConfig {
+        &self.indexer_config
+    }
 }
 
 #[derive(Clone)]
@@ -61,13 +141,36 @@ impl IndexerConfig {
             websocket_endpoint,
         }
     }
+
+    pub fn rest_endpoint(&self) -> &str {
+        &self.rest_endpoint
+    }
+
+    pub fn websocket_endpoint(&self) -> &str {
+        &self.websocket_endpoint
+    }
 }
 
 #[derive(Clone)]
 pub(crate) struct RestHandler {
     host: String,
-    timeout: u32,
-    req_client: BlockingClient,
+    backend: Backend,
+    retry_policy: RetryPolicy,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    cache: Option<Arc<ResponseCache>>,
+    recorder: Option<FixtureRecorder>,
+}
+
+#[derive(Clone)]
+enum Backend {
+    Http(Client),
+    Fixtures(IndexerFixtures),
+}
+
+/// A successful response, or confirmation that a cached one is unchanged.
+enum Fetched {
+    Body { body: String, etag: Option<String> },
+    NotModified,
 }
 
 impl RestHandler {
@@ -87,48 +190,63 @@ impl RestHandler {
                 host
             )));
         }
+        let req_client = match ClientBuilder::new()
+            .timeout(Duration::from_millis(api_timeout.unwrap_or(3000).into()))
+            .build()
+        {
+            Ok(client) => client,
+            Err(e) => return Err(ConstructorError::new(e.to_string())),
+        };
+
         Ok(RestHandler {
             host,
-            timeout: api_timeout.unwrap_or(3000),
-            req_client: match blocking::ClientBuilder::new()
-                .timeout(Duration::from_secs(api_timeout.unwrap_or(3).into()))
-                .build()
-            {
-                Ok(client) => client,
-                Err(e) => return Err(ConstructorError::new(e.to_string())),
-            },
+            backend: Backend::Http(req_client),
+            retry_policy: RetryPolicy::default(),
+            rate_limiter: None,
+            cache: None,
+            recorder: None,
         })
     }
 
-    pub fn get<T>(
+    fn from_fixtures(host: String, fixtures: IndexerFixtures) -> Self {
+        RestHandler {
+            host,
+            backend: Backend::Fixtures(fixtures),
+            retry_policy: RetryPolicy::no_retry(),
+            rate_limiter: None,
+            cache: None,
+            recorder: None,
+        }
+    }
+
+    /// Sends a GET request for `path`, with `query` serialized into a
+    /// percent-encoded query string. `None` fields are left out.
+    #[maybe_async]
+    pub async fn get<T, Q>(
         &self,
+        group: EndpointGroup,
         path: String,
-        query_params: Option<Vec<(String, Option<String>)>>,
+        query: &Q,
     ) -> Result<T, APIError>
     where
         T: for<'a> Deserialize<'a>,
+        Q: Serialize + Sync + ?Sized,
     {
-        let query_string = match query_params {
-            Some(query_map) => query_map
-                .into_iter()
-                .map(|(key, value)| {
-                    if let Some(value) = value {
-                        format!("{}={}", key, value)
-                    } else {
-                        "".to_string()
-                    }
-                })
-                .filter(|v| !v.is_empty())
-                .collect::<Vec<String>>()
-                .join("&"),
-            None => "".to_string(),
+        let query_string = match serde_urlencoded::to_string(query) {
+            Ok(query_string) => query_string,
+            Err(e) => return Err(APIError::InvalidRequest(e.to_string())),
+        };
+
+        let req_client = match &self.backend {
+            Backend::Http(req_client) => req_client,
+            Backend::Fixtures(fixtures) => return fixtures.get(&path, &query_string),
         };
 
         let url = format!(
             "{}{}{}",
             self.host,
             path,
-            if query_string.len() != 0 {
+            if !query_string.is_empty() {
                 format!("?{}", query_string)
             } else {
                 "".to_string()
@@ -136,259 +254,592 @@ impl RestHandler {
         );
 
         if !is_url(url.as_str()) {
-            return Err(APIError::new(format!("String is not URL: {}", url)));
+            return Err(APIError::InvalidRequest(format!(
+                "String is not URL: {}",
+                url
+            )));
+        }
+
+        let etag = match self.cache.as_ref().map(|cache| cache.lookup(&path, &url)) {
+            Some(CacheLookup::Fresh(body)) => return decode_body(body),
+            Some(CacheLookup::Stale { etag }) => etag,
+            Some(CacheLookup::Uncached) | None => None,
+        };
+
+        let mut fetched = self.fetch(req_client, group, &url, etag.as_deref()).await?;
+        if let Fetched::NotModified = fetched {
+            match self
+                .cache
+                .as_ref()
+                .and_then(|cache| cache.revalidated(&url))
+            {
+                Some(body) => return decode_body(body),
+                // Evicted or invalidated since the lookup, possibly by another
+                // client sharing the cache: ask for the full response.
+                None => fetched = self.fetch(req_client, group, &url, None).await?,
+            }
         }
+        let (body, etag) = match fetched {
+            Fetched::Body { body, etag } => (body, etag),
+            Fetched::NotModified => {
+                return Err(APIError::Decode {
+                    message: "304 Not Modified without If-None-Match".to_string(),
+                    body: String::new(),
+                })
+            }
+        };
 
-        match self.req_client.get(url).send() {
-            Ok(result) => Ok(match result.json::<T>() {
-                Ok(json) => json,
-                Err(e) => return Err(APIError::new(e.to_string())),
-            }),
-            Err(e) => Err(APIError::new(e.to_string())),
+        if let Some(recorder) = &self.recorder {
+            recorder
+                .record(&request_key(&path, &query_string), &body)
+                .map_err(APIError::Record)?;
         }
+        if let Some(cache) = &self.cache {
+            cache.store(&path, &url, &body, etag);
+        }
+        decode_body(body)
     }
 
-    pub fn post<T, B>(&self, path: String, body_args: HashMap<String, B>) -> Result<T, APIError>
-    where
-        T: for<'a> Deserialize<'a>,
-        B: for<'a> Serialize,
-    {
-        let body = match serde_json::to_string(body_args.borrow()) {
-            Ok(str) => str,
-            Err(e) => return Err(APIError::new(e.to_string())),
+    /// Sends the request, retrying as the retry policy allows.
+    #[maybe_async]
+    async fn fetch(
+        &self,
+        req_client: &Client,
+        group: EndpointGroup,
+        url: &str,
+        etag: Option<&str>,
+    ) -> Result<Fetched, APIError> {
+        let mut attempt = 1;
+        loop {
+            if let Some(rate_limiter) = &self.rate_limiter {
+                let delay = rate_limiter.acquire(group)?;
+                if !delay.is_zero() {
+                    sleep(delay).await;
+                }
+            }
+
+            match self.send(req_client, url, etag).await {
+                Err(error)
+                    if error.is_retryable() && attempt < self.retry_policy.max_attempts() =>
+                {
+                    sleep(self.retry_policy.delay(attempt, error.retry_after())).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    #[maybe_async]
+    async fn send(
+        &self,
+        req_client: &Client,
+        url: &str,
+        etag: Option<&str>,
+    ) -> Result<Fetched, APIError> {
+        let mut request = req_client.get(url);
+        if let Some(etag) = etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(e) => return Err(APIError::Transport(e.to_string())),
         };
-        let url = format!("{}{}", self.host, path);
 
-        if !is_url(url.as_str()) {
-            return Err(APIError::new(format!("String is not URL: {}", url)));
+        let status = response.status();
+        if status == StatusCode::NOT_MODIFIED {
+            return Ok(Fetched::NotModified);
         }
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| parse_retry_after(value, Utc::now()));
 
-        match self.req_client.post(url).body(body).send() {
-            Ok(result) => Ok(match result.json::<T>() {
-                Ok(json) => json,
-                Err(e) => return Err(APIError::new(e.to_string())),
-            }),
-            Err(e) => Err(APIError::new(e.to_string())),
+        let body = match response.text().await {
+            Ok(body) => body,
+            Err(e) => return Err(APIError::Transport(e.to_string())),
+        };
+
+        if !status.is_success() {
+            return Err(APIError::from_response(status.as_u16(), retry_after, body));
         }
+
+        Ok(Fetched::Body { body, etag })
     }
 }
 
+/// Characters kept as is in a path segment. Everything else is
+/// percent-encoded, including `/`, `?` and `#`.
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'_');
+
+/// Joins the segments of a request path, percent-encoding each one so that
+/// caller supplied ids, addresses and tickers stay within their segment.
+/// Empty, `.` and `..` segments are rejected: urls drop them even encoded.
+fn encoded_path(segments: &[&str]) -> Result<String, APIError> {
+    let mut path = String::new();
+    for segment in segments {
+        if matches!(*segment, "" | "." | "..") {
+            return Err(APIError::InvalidRequest(format!(
+                "Invalid path segment: {:?}",
+                segment
+            )));
+        }
+        path.push('/');
+        path.extend(utf8_percent_encode(segment, PATH_SEGMENT));
+    }
+    Ok(path)
+}
+
+/// Whether `url` is an absolute http(s) url. Hosts without a top level
+/// domain, such as `localhost` or an IP address, are accepted.
+fn is_url(url: &str) -> bool {
+    match Url::parse(url) {
+        Ok(url) => matches!(url.scheme(), "http" | "https") && url.has_host(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(feature = "blocking")]
+fn sleep(duration: Duration) {
+    std::thread::sleep(duration)
+}
+
+#[cfg(not(feature = "blocking"))]
+async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await
+}
+
+pub(crate) fn decode_body<T>(body: String) -> Result<T, APIError>
+where
+    T: for<'a> Deserialize<'a>,
+{
+    match serde_json::from_str(&body) {
+        Ok(json) => Ok(json),
+        Err(e) => Err(APIError::Decode {
+            message: e.to_string(),
+            body,
+        }),
+    }
+}
+
+#[maybe_async]
 impl AccountsClient for IndexerClient {
-    fn get_sub_accounts(
+    async fn get_sub_accounts(
         &self,
-        address: String,
-        limit: Option<u32>,
-    ) -> Result<Vec<SubAccountResponseObject>, APIError> {
-        self.req_handler.get(
-            format!("/v4/addresses/{address}"),
-            Some(vec![option_to_tuple!(limit)]),
-        )
+        request: SubaccountsRequest,
+    ) -> Result<SubaccountsResponse, APIError> {
+        self.req_handler
+            .get(
+                EndpointGroup::Accounts,
+                encoded_path(&["v4", "addresses", request.address()])?,
+                &request,
+            )
+            .await
     }
 
-    fn get_sub_account(
+    async fn get_sub_account(
         &self,
         address: String,
         sub_account_number: u32,
-    ) -> Result<SubAccountResponseObject, APIError> {
-        self.req_handler.get(
-            format!("/v4/addresses/{address}/subaccountNumber/{sub_account_number}"),
-            None,
-        )
+    ) -> Result<SubaccountResponse, APIError> {
+        self.req_handler
+            .get(
+                EndpointGroup::Accounts,
+                encoded_path(&[
+                    "v4",
+                    "addresses",
+                    &address,
+                    "subaccountNumber",
+                    &sub_account_number.to_string(),
+                ])?,
+                &(),
+            )
+            .await
     }
 
-    fn get_sub_account_perpetual_positions(
+    async fn get_sub_account_perpetual_positions(
         &self,
         request: PositionDetailsRequest,
     ) -> Result<PerpetualPositionResponse, APIError> {
         self.req_handler
-            .get("/v4/perpetualPositions".to_string(), Some(request.into()))
+            .get(
+                EndpointGroup::Accounts,
+                "/v4/perpetualPositions".to_string(),
+                &request,
+            )
+            .await
     }
 
-    fn get_sub_account_asset_positions(
+    async fn get_sub_account_asset_positions(
         &self,
         request: PositionDetailsRequest,
     ) -> Result<AssetPositionResponse, APIError> {
         self.req_handler
-            .get("/v4/assetPositions".to_string(), Some(request.into()))
+            .get(
+                EndpointGroup::Accounts,
+                "/v4/assetPositions".to_string(),
+                &request,
+            )
+            .await
     }
 
-    fn get_sub_account_transfers(
+    async fn get_sub_account_transfers(
         &self,
-        address: String,
-        sub_account_number: u32,
-        limit: Option<u32>,
-        created_before_or_at_height: Option<u32>,
-        created_before_or_at: Option<String>,
+        request: TransfersRequest,
     ) -> Result<TransferResponse, APIError> {
-        self.req_handler.get(
-            "/v4/transfers".to_string(),
-            Some(vec![
-                arg_to_tuple!(address),
-                arg_to_tuple!(sub_account_number),
-                option_to_tuple!(limit),
-                option_to_tuple!(created_before_or_at_height),
-                option_to_tuple!(created_before_or_at),
-            ]),
-        )
+        self.req_handler
+            .get(
+                EndpointGroup::Accounts,
+                "/v4/transfers".to_string(),
+                &request,
+            )
+            .await
     }
 
-    fn get_sub_account_orders(
+    async fn get_sub_account_orders(
         &self,
-        address: String,
-        sub_account_number: u32,
-        ticker: Option<String>,
-        ticker_type: TickerType,
-        side: Option<OrderSide>,
-        status: Option<OrderStatus>,
-        order_type: Option<OrderType>,
-        limit: Option<u32>,
-        good_til_block_before_or_at: Option<u64>,
-        good_til_block_time_before_or_at: Option<String>,
-        return_latest_orders: Option<bool>,
+        request: OrdersRequest,
     ) -> Result<Vec<OrderResponseStruct>, APIError> {
-        self.req_handler.get(
-            "/v4/orders".to_string(),
-            Some(vec![
-                arg_to_tuple!(address),
-                arg_to_tuple!(sub_account_number),
-                option_to_tuple!(ticker),
-                arg_to_tuple!(ticker_type),
-                option_to_tuple!(side),
-                option_to_tuple!(status),
-                option_to_tuple!(order_type),
-                option_to_tuple!(limit),
-                option_to_tuple!(good_til_block_before_or_at),
-                option_to_tuple!(good_til_block_time_before_or_at),
-                option_to_tuple!(return_latest_orders),
-            ]),
-        )
+        self.req_handler
+            .get(EndpointGroup::Accounts, "/v4/orders".to_string(), &request)
+            .await
     }
 
-    fn get_order(&self, order_id: String) -> Result<OrderResponseStruct, APIError> {
-        self.req_handler.get(format!("/v4/orders/{order_id}"), None)
+    async fn get_order(&self, order_id: String) -> Result<OrderResponseStruct, APIError> {
+        self.req_handler
+            .get(
+                EndpointGroup::Accounts,
+                encoded_path(&["v4", "orders", &order_id])?,
+                &(),
+            )
+            .await
     }
 
-    fn get_sub_account_fills(
+    async fn get_sub_account_fills(&self, request: FillsRequest) -> Result<FillResponse, APIError> {
+        self.req_handler
+            .get(EndpointGroup::Accounts, "/v4/fills".to_string(), &request)
+            .await
+    }
+
+    async fn get_sub_account_historical_pnls(
+        &self,
+        request: HistoricalPnLRequest,
+    ) -> Result<HistoricalPnLResponse, APIError> {
+        self.req_handler
+            .get(
+                EndpointGroup::Accounts,
+                "/v4/historical-pnl".to_string(),
+                &request,
+            )
+            .await
+    }
+
+    async fn get_parent_sub_account(
         &self,
         address: String,
-        sub_account_number: u32,
-        ticker: Option<String>,
-        ticker_type: TickerType,
-        limit: Option<u32>,
-        created_before_or_at_height: Option<u32>,
-        created_before_or_at: Option<String>,
+        parent_sub_account_number: u32,
+    ) -> Result<ParentSubaccountResponse, APIError> {
+        self.req_handler
+            .get(
+                EndpointGroup::Accounts,
+                encoded_path(&[
+                    "v4",
+                    "addresses",
+                    &address,
+                    "parentSubaccountNumber",
+                    &parent_sub_account_number.to_string(),
+                ])?,
+                &(),
+            )
+            .await
+    }
+
+    async fn get_parent_sub_account_transfers(
+        &self,
+        request: ParentSubaccountTransfersRequest,
+    ) -> Result<TransferResponse, APIError> {
+        self.req_handler
+            .get(
+                EndpointGroup::Accounts,
+                "/v4/transfers/parentSubaccountNumber".to_string(),
+                &request,
+            )
+            .await
+    }
+
+    async fn get_parent_sub_account_orders(
+        &self,
+        request: ParentSubaccountOrdersRequest,
+    ) -> Result<Vec<OrderResponseStruct>, APIError> {
+        self.req_handler
+            .get(
+                EndpointGroup::Accounts,
+                "/v4/orders/parentSubaccountNumber".to_string(),
+                &request,
+            )
+            .await
+    }
+
+    async fn get_parent_sub_account_fills(
+        &self,
+        request: ParentSubaccountFillsRequest,
     ) -> Result<FillResponse, APIError> {
-        self.req_handler.get(
-            "/v4/fills".to_string(),
-            Some(vec![
-                arg_to_tuple!(address),
-                arg_to_tuple!(sub_account_number),
-                option_to_tuple!(ticker),
-                arg_to_tuple!(ticker_type),
-                option_to_tuple!(limit),
-                option_to_tuple!(created_before_or_at_height),
-                option_to_tuple!(created_before_or_at),
-            ]),
-        )
+        self.req_handler
+            .get(
+                EndpointGroup::Accounts,
+                "/v4/fills/parentSubaccountNumber".to_string(),
+                &request,
+            )
+            .await
     }
 
-    fn get_sub_account_historical_pnls(
+    async fn get_transfers_between(
         &self,
-        address: String,
-        sub_account_number: u32,
-        effective_before_or_at: Option<String>,
-        effective_at_or_after: Option<String>,
-    ) -> Result<HistoricalPnLResponse, APIError> {
-        self.req_handler.get(
-            "/v4/historical-pnl".to_string(),
-            Some(vec![
-                arg_to_tuple!(address),
-                arg_to_tuple!(sub_account_number),
-                option_to_tuple!(effective_before_or_at),
-                option_to_tuple!(effective_at_or_after),
-            ]),
-        )
+        request: TransfersBetweenRequest,
+    ) -> Result<TransferBetweenResponse, APIError> {
+        self.req_handler
+            .get(
+                EndpointGroup::Accounts,
+                "/v4/transfers/between".to_string(),
+                &request,
+            )
+            .await
+    }
+
+    async fn get_historical_block_trading_rewards(
+        &self,
+        request: HistoricalBlockTradingRewardsRequest,
+    ) -> Result<HistoricalBlockTradingRewardsResponse, APIError> {
+        self.req_handler
+            .get(
+                EndpointGroup::Accounts,
+                encoded_path(&["v4", "historicalBlockTradingRewards", request.address()])?,
+                &request,
+            )
+            .await
+    }
+
+    async fn get_historical_trading_reward_aggregations(
+        &self,
+        request: HistoricalTradingRewardAggregationsRequest,
+    ) -> Result<HistoricalTradingRewardAggregationsResponse, APIError> {
+        self.req_handler
+            .get(
+                EndpointGroup::Accounts,
+                encoded_path(&[
+                    "v4",
+                    "historicalTradingRewardAggregations",
+                    request.address(),
+                ])?,
+                &request,
+            )
+            .await
     }
 }
 
+#[maybe_async]
 impl MarketsClient for IndexerClient {
-    fn get_perpetual_markets(
+    async fn get_perpetual_markets(
         &self,
-        market: Option<String>,
+        request: PerpetualMarketsRequest,
     ) -> Result<PerpetualMarketsResponse, APIError> {
-        self.req_handler.get(
-            "/v4/perpetualMarkets".to_string(),
-            Some(vec![option_to_tuple!(market)]),
-        )
+        self.req_handler
+            .get(
+                EndpointGroup::Markets,
+                "/v4/perpetualMarkets".to_string(),
+                &request,
+            )
+            .await
     }
 
-    fn get_perpetual_market_orderbook(
+    async fn get_perpetual_market_orderbook(
         &self,
         market: String,
     ) -> Result<OrderbookResponse, APIError> {
         self.req_handler
-            .get(format!("/v4/orderbooks/perpetualMarket/{market}"), None)
+            .get(
+                EndpointGroup::Markets,
+                encoded_path(&["v4", "orderbooks", "perpetualMarket", &market])?,
+                &(),
+            )
+            .await
     }
 
-    fn get_perpetual_market_trades(
+    async fn get_perpetual_market_trades(
         &self,
-        market: String,
-        created_before_or_at_height: Option<u32>,
-        limit: Option<u32>,
+        request: TradesRequest,
     ) -> Result<TradeResponse, APIError> {
-        self.req_handler.get(
-            format!("/v4/trades/perpetualMarket/{market}"),
-            Some(vec![
-                option_to_tuple!(created_before_or_at_height),
-                option_to_tuple!(limit),
-            ]),
-        )
+        self.req_handler
+            .get(
+                EndpointGroup::Markets,
+                encoded_path(&["v4", "trades", "perpetualMarket", request.market()])?,
+                &request,
+            )
+            .await
     }
 
-    fn get_perpetual_market_candles(
+    async fn get_perpetual_market_candles(
         &self,
-        market: String,
-        resolution: String,
-        from_iso: Option<String>,
-        to_iso: Option<String>,
-        limit: Option<u32>,
+        request: CandlesRequest,
     ) -> Result<CandleResponse, APIError> {
-        self.req_handler.get(
-            format!("/v4/candles/perpetualMarket/{market}"),
-            Some(vec![
-                arg_to_tuple!(resolution),
-                ("fromISO".to_string(), from_iso),
-                ("toISO".to_string(), to_iso),
-                option_to_tuple!(limit),
-            ]),
-        )
+        self.req_handler
+            .get(
+                EndpointGroup::Markets,
+                encoded_path(&["v4", "candles", "perpetualMarket", request.market()])?,
+                &request,
+            )
+            .await
     }
 
-    fn get_perpetual_market_historical_funding(
+    async fn get_perpetual_market_historical_funding(
         &self,
-        market: String,
-        effective_before_or_at: Option<String>,
-        effective_before_or_at_height: Option<u32>,
-        limit: Option<u32>,
+        request: HistoricalFundingRequest,
     ) -> Result<HistoricalFundingResponse, APIError> {
-        self.req_handler.get(
-            format!("/v4/historicalFunding/{market}"),
-            Some(vec![
-                option_to_tuple!(effective_before_or_at),
-                option_to_tuple!(effective_before_or_at_height),
-                option_to_tuple!(limit),
-            ]),
-        )
+        self.req_handler
+            .get(
+                EndpointGroup::Markets,
+                encoded_path(&["v4", "historicalFunding", request.market()])?,
+                &request,
+            )
+            .await
     }
 
-    fn get_perpetual_market_sparklines(
+    async fn get_perpetual_market_sparklines(
         &self,
-        time_period: TimePeriod,
+        request: SparklinesRequest,
     ) -> Result<SparklineResponse, APIError> {
-        self.req_handler.get(
-            "/v4/sparklines".to_string(),
-            Some(vec![arg_to_tuple!(time_period)]),
-        )
+        self.req_handler
+            .get(
+                EndpointGroup::Markets,
+                "/v4/sparklines".to_string(),
+                &request,
+            )
+            .await
+    }
+}
+
+#[maybe_async]
+impl UtilityClient for IndexerClient {
+    async fn get_height(&self) -> Result<HeightResponse, APIError> {
+        self.req_handler
+            .get(EndpointGroup::Utility, "/v4/height".to_string(), &())
+            .await
+    }
+
+    async fn get_time(&self) -> Result<TimeResponse, APIError> {
+        self.req_handler
+            .get(EndpointGroup::Utility, "/v4/time".to_string(), &())
+            .await
+    }
+
+    async fn screen(&self, address: String) -> Result<ComplianceResponse, APIError> {
+        self.req_handler
+            .get(
+                EndpointGroup::Utility,
+                "/v4/screen".to_string(),
+                &[("address", address)],
+            )
+            .await
+    }
+}
+
+#[maybe_async]
+impl AffiliatesClient for IndexerClient {
+    async fn get_affiliate_metadata(
+        &self,
+        address: String,
+    ) -> Result<AffiliateMetadataResponse, APIError> {
+        self.req_handler
+            .get(
+                EndpointGroup::Affiliates,
+                "/v4/affiliates/metadata".to_string(),
+                &[("address", address)],
+            )
+            .await
+    }
+
+    async fn get_affiliate_address(
+        &self,
+        referral_code: String,
+    ) -> Result<AffiliateAddressResponse, APIError> {
+        self.req_handler
+            .get(
+                EndpointGroup::Affiliates,
+                "/v4/affiliates/address".to_string(),
+                &[("referralCode", referral_code)],
+            )
+            .await
+    }
+
+    async fn get_affiliate_snapshot(
+        &self,
+        request: AffiliateSnapshotRequest,
+    ) -> Result<AffiliateSnapshotResponse, APIError> {
+        self.req_handler
+            .get(
+                EndpointGroup::Affiliates,
+                "/v4/affiliates/snapshot".to_string(),
+                &request,
+            )
+            .await
+    }
+
+    async fn get_affiliate_total_volume(
+        &self,
+        address: String,
+    ) -> Result<AffiliateTotalVolumeResponse, APIError> {
+        self.req_handler
+            .get(
+                EndpointGroup::Affiliates,
+                "/v4/affiliates/total_volume".to_string(),
+                &[("address", address)],
+            )
+            .await
+    }
+}
+
+#[maybe_async]
+impl VaultsClient for IndexerClient {
+    async fn get_megavault_historical_pnl(
+        &self,
+        resolution: PnlTickInterval,
+    ) -> Result<MegavaultHistoricalPnLResponse, APIError> {
+        self.req_handler
+            .get(
+                EndpointGroup::Vaults,
+                "/v4/vault/v1/megavault/historicalPnl".to_string(),
+                &[("resolution", resolution)],
+            )
+            .await
+    }
+
+    async fn get_vaults_historical_pnl(
+        &self,
+        resolution: PnlTickInterval,
+    ) -> Result<VaultsHistoricalPnLResponse, APIError> {
+        self.req_handler
+            .get(
+                EndpointGroup::Vaults,
+                "/v4/vault/v1/vaults/historicalPnl".to_string(),
+                &[("resolution", resolution)],
+            )
+            .await
+    }
+
+    async fn get_megavault_positions(&self) -> Result<MegavaultPositionResponse, APIError> {
+        self.req_handler
+            .get(
+                EndpointGroup::Vaults,
+                "/v4/vault/v1/megavault/positions".to_string(),
+                &(),
+            )
+            .await
     }
 }
 
@@ -396,110 +847,694 @@ impl MarketsClient for IndexerClient {
 // Client traits
 // ========================================================
 
-trait AccountsClient {
-    fn get_sub_accounts(
+#[maybe_async]
+pub trait AccountsClient {
+    async fn get_sub_accounts(
         &self,
-        address: String,
-        limit: Option<u32>,
-    ) -> Result<Vec<SubAccountResponseObject>, APIError>;
+        request: SubaccountsRequest,
+    ) -> Result<SubaccountsResponse, APIError>;
 
-    fn get_sub_account(
+    async fn get_sub_account(
         &self,
         address: String,
         sub_account_number: u32,
-    ) -> Result<SubAccountResponseObject, APIError>;
+    ) -> Result<SubaccountResponse, APIError>;
 
-    fn get_sub_account_perpetual_positions(
+    async fn get_sub_account_perpetual_positions(
         &self,
         request: PositionDetailsRequest,
     ) -> Result<PerpetualPositionResponse, APIError>;
 
-    fn get_sub_account_asset_positions(
+    async fn get_sub_account_asset_positions(
         &self,
         request: PositionDetailsRequest,
     ) -> Result<AssetPositionResponse, APIError>;
 
-    fn get_sub_account_transfers(
+    async fn get_sub_account_transfers(
         &self,
-        address: String,
-        sub_account_number: u32,
-        limit: Option<u32>,
-        created_before_or_at_height: Option<u32>,
-        created_before_or_at: Option<String>,
+        request: TransfersRequest,
     ) -> Result<TransferResponse, APIError>;
 
-    fn get_sub_account_orders(
+    async fn get_sub_account_orders(
         &self,
-        address: String,
-        sub_account_number: u32,
-        ticker: Option<String>,
-        ticker_type: TickerType,
-        side: Option<OrderSide>,
-        status: Option<OrderStatus>,
-        order_type: Option<OrderType>,
-        limit: Option<u32>,
-        good_til_block_before_or_at: Option<u64>,
-        good_til_block_time_before_or_at: Option<String>,
-        return_latest_orders: Option<bool>,
+        request: OrdersRequest,
     ) -> Result<Vec<OrderResponseStruct>, APIError>;
 
-    fn get_order(&self, order_id: String) -> Result<OrderResponseStruct, APIError>;
+    async fn get_order(&self, order_id: String) -> Result<OrderResponseStruct, APIError>;
+
+    async fn get_sub_account_fills(&self, request: FillsRequest) -> Result<FillResponse, APIError>;
+
+    async fn get_sub_account_historical_pnls(
+        &self,
+        request: HistoricalPnLRequest,
+    ) -> Result<HistoricalPnLResponse, APIError>;
 
-    fn get_sub_account_fills(
+    async fn get_parent_sub_account(
         &self,
         address: String,
-        sub_account_number: u32,
-        ticker: Option<String>,
-        ticker_type: TickerType,
-        limit: Option<u32>,
-        created_before_or_at_height: Option<u32>,
-        created_before_or_at: Option<String>,
+        parent_sub_account_number: u32,
+    ) -> Result<ParentSubaccountResponse, APIError>;
+
+    async fn get_parent_sub_account_transfers(
+        &self,
+        request: ParentSubaccountTransfersRequest,
+    ) -> Result<TransferResponse, APIError>;
+
+    async fn get_parent_sub_account_orders(
+        &self,
+        request: ParentSubaccountOrdersRequest,
+    ) -> Result<Vec<OrderResponseStruct>, APIError>;
+
+    async fn get_parent_sub_account_fills(
+        &self,
+        request: ParentSubaccountFillsRequest,
     ) -> Result<FillResponse, APIError>;
 
-    fn get_sub_account_historical_pnls(
+    async fn get_transfers_between(
         &self,
-        address: String,
-        sub_account_number: u32,
-        effective_before_or_at: Option<String>,
-        effective_at_or_after: Option<String>,
-    ) -> Result<HistoricalPnLResponse, APIError>;
+        request: TransfersBetweenRequest,
+    ) -> Result<TransferBetweenResponse, APIError>;
+
+    async fn get_historical_block_trading_rewards(
+        &self,
+        request: HistoricalBlockTradingRewardsRequest,
+    ) -> Result<HistoricalBlockTradingRewardsResponse, APIError>;
+
+    async fn get_historical_trading_reward_aggregations(
+        &self,
+        request: HistoricalTradingRewardAggregationsRequest,
+    ) -> Result<HistoricalTradingRewardAggregationsResponse, APIError>;
 }
 
-trait MarketsClient {
-    fn get_perpetual_markets(
+#[maybe_async]
+pub trait MarketsClient {
+    async fn get_perpetual_markets(
         &self,
-        market: Option<String>,
+        request: PerpetualMarketsRequest,
     ) -> Result<PerpetualMarketsResponse, APIError>;
 
-    fn get_perpetual_market_orderbook(&self, market: String)
-        -> Result<OrderbookResponse, APIError>;
-
-    fn get_perpetual_market_trades(
+    async fn get_perpetual_market_orderbook(
         &self,
         market: String,
-        starting_before_or_at_height: Option<u32>,
-        limit: Option<u32>,
+    ) -> Result<OrderbookResponse, APIError>;
+
+    async fn get_perpetual_market_trades(
+        &self,
+        request: TradesRequest,
     ) -> Result<TradeResponse, APIError>;
 
-    fn get_perpetual_market_candles(
+    async fn get_perpetual_market_candles(
         &self,
-        market: String,
-        resolution: String,
-        from_iso: Option<String>,
-        to_iso: Option<String>,
-        limit: Option<u32>,
+        request: CandlesRequest,
     ) -> Result<CandleResponse, APIError>;
 
-    fn get_perpetual_market_historical_funding(
+    async fn get_perpetual_market_historical_funding(
         &self,
-        market: String,
-        effective_before_or_at: Option<String>,
-        effective_before_or_at_height: Option<u32>,
-        limit: Option<u32>,
+        request: HistoricalFundingRequest,
     ) -> Result<HistoricalFundingResponse, APIError>;
 
-    fn get_perpetual_market_sparklines(
+    async fn get_perpetual_market_sparklines(
         &self,
-        time_period: TimePeriod,
+        request: SparklinesRequest,
     ) -> Result<SparklineResponse, APIError>;
 }
+
+#[maybe_async]
+pub trait UtilityClient {
+    /// Latest block height processed by the indexer.
+    async fn get_height(&self) -> Result<HeightResponse, APIError>;
+
+    async fn get_time(&self) -> Result<TimeResponse, APIError>;
+
+    /// Whether `address` is blocked from trading by compliance screening.
+    async fn screen(&self, address: String) -> Result<ComplianceResponse, APIError>;
+}
+
+#[maybe_async]
+pub trait AffiliatesClient {
+    async fn get_affiliate_metadata(
+        &self,
+        address: String,
+    ) -> Result<AffiliateMetadataResponse, APIError>;
+
+    /// Address of the affiliate owning `referral_code`.
+    async fn get_affiliate_address(
+        &self,
+        referral_code: String,
+    ) -> Result<AffiliateAddressResponse, APIError>;
+
+    async fn get_affiliate_snapshot(
+        &self,
+        request: AffiliateSnapshotRequest,
+    ) -> Result<AffiliateSnapshotResponse, APIError>;
+
+    async fn get_affiliate_total_volume(
+        &self,
+        address: String,
+    ) -> Result<AffiliateTotalVolumeResponse, APIError>;
+}
+
+#[maybe_async]
+pub trait VaultsClient {
+    async fn get_megavault_historical_pnl(
+        &self,
+        resolution: PnlTickInterval,
+    ) -> Result<MegavaultHistoricalPnLResponse, APIError>;
+
+    /// Historical PnL of each market making vault.
+    async fn get_vaults_historical_pnl(
+        &self,
+        resolution: PnlTickInterval,
+    ) -> Result<VaultsHistoricalPnLResponse, APIError>;
+
+    async fn get_megavault_positions(&self) -> Result<MegavaultPositionResponse, APIError>;
+}
+
+// ========================================
+// Tests
+// ========================================
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        thread,
+    };
+
+    use super::super::errors::FixtureError;
+    use super::super::indexer_client_types::CandleResolution;
+    use super::super::rate_limiter::{RateLimit, RateLimitMode};
+    use super::super::response_cache::{CacheStats, CachedEndpoint};
+    use super::*;
+    use crate::constants::TimePeriod;
+    use crate::constants::{
+        OrderSide, OrderStatus, OrderType, PerpetualPositionStatus, TradingRewardAggregationPeriod,
+    };
+
+    /// Answers one connection per scripted response, in order, and counts the
+    /// requests it has served.
+    fn mock_server(responses: Vec<String>) -> (IndexerConfig, Arc<AtomicUsize>) {
+        mock_server_with_hook(responses, |_| {})
+    }
+
+    /// Like [`mock_server`], calling `hook` with the index of each request
+    /// before answering it.
+    fn mock_server_with_hook(
+        responses: Vec<String>,
+        hook: impl Fn(usize) + Send + 'static,
+    ) -> (IndexerConfig, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let rest_endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let served = Arc::new(AtomicUsize::new(0));
+
+        let counter = served.clone();
+        thread::spawn(move || {
+            for (response, stream) in responses.into_iter().zip(listener.incoming()) {
+                let mut stream = stream.unwrap();
+                read_request(&mut stream);
+                hook(counter.fetch_add(1, Ordering::SeqCst));
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        let config = IndexerConfig::new(rest_endpoint, "ws://127.0.0.1:1".to_string());
+        (config, served)
+    }
+
+    fn read_request(stream: &mut TcpStream) {
+        let mut request = Vec::new();
+        let mut buffer = [0u8; 1024];
+        while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+            match stream.read(&mut buffer) {
+                Ok(0) | Err(_) => return,
+                Ok(read) => request.extend_from_slice(&buffer[..read]),
+            }
+        }
+    }
+
+    fn response(status: &str, headers: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n{headers}\r\n{body}",
+            body.len()
+        )
+    }
+
+    fn client(config: IndexerConfig, max_attempts: u32) -> IndexerClient {
+        IndexerClient::new(config, None)
+            .unwrap()
+            .with_retry_policy(RetryPolicy::new(
+                max_attempts,
+                Duration::from_millis(1),
+                Duration::from_millis(5),
+            ))
+    }
+
+    #[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+    async fn test_retries_rate_limits_and_server_errors() {
+        let (config, served) = mock_server(vec![
+            response("429 Too Many Requests", "Retry-After: 0\r\n", "{}"),
+            response("503 Service Unavailable", "", "upstream unavailable"),
+            response("200 OK", "", r#"{"BTC-USD":["50000","50001"]}"#),
+        ]);
+
+        let sparklines = client(config, 3)
+            .get_perpetual_market_sparklines(SparklinesRequest::new(TimePeriod::OneDay))
+            .await
+            .unwrap();
+        assert_eq!(sparklines["BTC-USD"].len(), 2);
+        assert_eq!(served.load(Ordering::SeqCst), 3);
+    }
+
+    #[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+    async fn test_gives_up_after_max_attempts() {
+        let (config, served) = mock_server(vec![
+            response("500 Internal Server Error", "", "boom"),
+            response("500 Internal Server Error", "", "boom"),
+            response("200 OK", "", "{}"),
+        ]);
+
+        let result = client(config, 2)
+            .get_perpetual_market_sparklines(SparklinesRequest::new(TimePeriod::OneDay))
+            .await;
+        assert_eq!(result.unwrap_err().status(), Some(500));
+        assert_eq!(served.load(Ordering::SeqCst), 2);
+    }
+
+    #[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+    async fn test_does_not_retry_client_errors() {
+        let body =
+            r#"{"errors":[{"msg":"Invalid value","param":"timePeriod","location":"query"}]}"#;
+        let (config, served) = mock_server(vec![
+            response("400 Bad Request", "", body),
+            response("200 OK", "", "{}"),
+        ]);
+
+        let result = client(config, 3)
+            .get_perpetual_market_sparklines(SparklinesRequest::new(TimePeriod::OneDay))
+            .await;
+        match result {
+            Err(APIError::Status { status, errors, .. }) => {
+                assert_eq!(status, 400);
+                assert_eq!(errors[0].param.as_deref(), Some("timePeriod"));
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+        assert_eq!(served.load(Ordering::SeqCst), 1);
+    }
+
+    #[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+    async fn test_rate_limiter_is_shared_between_clients() {
+        let (config, served) = mock_server(vec![
+            response("200 OK", "", r#"{"BTC-USD":[]}"#),
+            response("200 OK", "", r#"{"BTC-USD":[]}"#),
+        ]);
+        let rate_limiter = Arc::new(RateLimiter::new(RateLimitMode::FailFast).with_limit(
+            EndpointGroup::Markets,
+            RateLimit::new(1, Duration::from_secs(60)),
+        ));
+        let first = client(config.clone(), 3).with_rate_limiter(rate_limiter.clone());
+        let second = client(config, 3).with_rate_limiter(rate_limiter);
+
+        let result = first
+            .get_perpetual_market_sparklines(SparklinesRequest::new(TimePeriod::OneDay))
+            .await;
+        assert!(result.is_ok());
+
+        let result = second
+            .get_perpetual_market_sparklines(SparklinesRequest::new(TimePeriod::OneDay))
+            .await;
+        match result {
+            Err(APIError::Throttled { retry_after }) => {
+                assert!(retry_after > Duration::from_secs(59))
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+        assert_eq!(served.load(Ordering::SeqCst), 1);
+    }
+
+    #[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+    async fn test_cache_serves_and_revalidates_responses() {
+        let (config, served) = mock_server(vec![
+            response("200 OK", "ETag: \"abc\"\r\n", r#"{"BTC-USD":["50000"]}"#),
+            response("200 OK", "", r#"{"markets":{}}"#),
+            response("304 Not Modified", "", ""),
+        ]);
+        let cache = Arc::new(
+            ResponseCache::new()
+                .with_ttl(CachedEndpoint::Sparklines, Duration::ZERO)
+                .with_ttl(CachedEndpoint::PerpetualMarkets, Duration::from_secs(60)),
+        );
+        let client = client(config, 1).with_cache(cache.clone());
+
+        for _ in 0..2 {
+            let sparklines = client
+                .get_perpetual_market_sparklines(SparklinesRequest::new(TimePeriod::OneDay))
+                .await
+                .unwrap();
+            assert_eq!(sparklines["BTC-USD"].len(), 1);
+            let markets = client
+                .get_perpetual_markets(PerpetualMarketsRequest::new())
+                .await
+                .unwrap();
+            assert!(markets.markets.is_empty());
+        }
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 1,
+                misses: 2,
+                revalidated: 1,
+            }
+        );
+        assert_eq!(served.load(Ordering::SeqCst), 3);
+
+        cache.invalidate(CachedEndpoint::PerpetualMarkets);
+        let result = client
+            .get_perpetual_markets(PerpetualMarketsRequest::new())
+            .await;
+        assert!(matches!(result, Err(APIError::Transport(_))));
+    }
+
+    #[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+    async fn test_not_modified_after_invalidation_refetches() {
+        let cache =
+            Arc::new(ResponseCache::new().with_ttl(CachedEndpoint::Sparklines, Duration::ZERO));
+        let shared = cache.clone();
+        let (config, served) = mock_server_with_hook(
+            vec![
+                response("200 OK", "ETag: \"abc\"\r\n", r#"{"BTC-USD":["50000"]}"#),
+                response("304 Not Modified", "", ""),
+                response("200 OK", "", r#"{"BTC-USD":["50001","50002"]}"#),
+            ],
+            move |request| {
+                if request == 1 {
+                    shared.invalidate(CachedEndpoint::Sparklines);
+                }
+            },
+        );
+        let client = client(config, 1).with_cache(cache);
+
+        let first = client
+            .get_perpetual_market_sparklines(SparklinesRequest::new(TimePeriod::OneDay))
+            .await
+            .unwrap();
+        assert_eq!(first["BTC-USD"].len(), 1);
+        let second = client
+            .get_perpetual_market_sparklines(SparklinesRequest::new(TimePeriod::OneDay))
+            .await
+            .unwrap();
+        assert_eq!(second["BTC-USD"].len(), 2);
+        assert_eq!(served.load(Ordering::SeqCst), 3);
+    }
+
+    #[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+    async fn test_recorded_responses_replay_offline() {
+        let (config, served) = mock_server(vec![
+            response("200 OK", "", r#"{"BTC-USD":["50000","50001"]}"#),
+            response(
+                "200 OK",
+                "",
+                r#"{"height":"100","time":"2024-01-01T00:00:00.000Z"}"#,
+            ),
+        ]);
+        let dir = std::env::temp_dir().join(format!("dydx-fixtures-{}", std::process::id()));
+        let recorder = FixtureRecorder::new(&dir).unwrap();
+        let client = client(config, 1).with_recorder(recorder);
+        client
+            .get_perpetual_market_sparklines(SparklinesRequest::new(TimePeriod::OneDay))
+            .await
+            .unwrap();
+        client.get_height().await.unwrap();
+        assert_eq!(served.load(Ordering::SeqCst), 2);
+
+        let fixtures = IndexerFixtures::load(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let replay = IndexerClient::from_fixtures(fixtures.clone());
+        let sparklines = replay
+            .get_perpetual_market_sparklines(SparklinesRequest::new(TimePeriod::OneDay))
+            .await
+            .unwrap();
+        assert_eq!(sparklines["BTC-USD"].len(), 2);
+        let height = replay.get_height().await.unwrap();
+        assert_eq!(height.height, 100);
+        let missing = replay
+            .get_perpetual_market_sparklines(SparklinesRequest::new(TimePeriod::SevenDays))
+            .await;
+        assert!(missing.is_err());
+        assert_eq!(
+            fixtures.requests(),
+            vec!["/v4/sparklines?timePeriod=ONE_DAY", "/v4/height"]
+        );
+    }
+
+    #[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+    async fn test_failed_recording_is_not_retried() {
+        let (config, served) = mock_server(vec![
+            response(
+                "200 OK",
+                "",
+                r#"{"height":"100","time":"2024-01-01T00:00:00.000Z"}"#,
+            ),
+            response(
+                "200 OK",
+                "",
+                r#"{"height":"101","time":"2024-01-01T00:00:01.000Z"}"#,
+            ),
+        ]);
+        let dir = std::env::temp_dir().join(format!("dydx-unwritable-{}", std::process::id()));
+        let recorder = FixtureRecorder::new(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let client = client(config, 3).with_recorder(recorder);
+
+        let result = client.get_height().await;
+        assert!(matches!(
+            result,
+            Err(APIError::Record(FixtureError::Io { .. }))
+        ));
+        assert_eq!(served.load(Ordering::SeqCst), 1);
+    }
+
+    #[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+    async fn test_request_urls() {
+        let address = "dydx1address".to_string();
+        let market = "BTC-USD".to_string();
+        let mut fixtures = IndexerFixtures::new();
+        for path in [
+            "/v4/addresses/dydx1address",
+            "/v4/addresses/dydx1address/subaccountNumber/1",
+            "/v4/perpetualPositions",
+            "/v4/assetPositions",
+            "/v4/transfers",
+            "/v4/orders",
+            "/v4/orders/order-id",
+            "/v4/orders/%2E%2E%2Fid%3Fa%3Db%20c%23d",
+            "/v4/fills",
+            "/v4/historical-pnl",
+            "/v4/perpetualMarkets",
+            "/v4/orderbooks/perpetualMarket/BTC-USD",
+            "/v4/trades/perpetualMarket/BTC-USD",
+            "/v4/candles/perpetualMarket/BTC-USD",
+            "/v4/historicalFunding/BTC-USD",
+            "/v4/sparklines",
+            "/v4/addresses/dydx1address/parentSubaccountNumber/0",
+            "/v4/transfers/parentSubaccountNumber",
+            "/v4/orders/parentSubaccountNumber",
+            "/v4/fills/parentSubaccountNumber",
+            "/v4/transfers/between",
+            "/v4/historicalBlockTradingRewards/dydx1address",
+            "/v4/historicalTradingRewardAggregations/dydx1address",
+            "/v4/height",
+            "/v4/time",
+            "/v4/screen",
+            "/v4/affiliates/metadata",
+            "/v4/affiliates/address",
+            "/v4/affiliates/snapshot",
+            "/v4/affiliates/total_volume",
+            "/v4/vault/v1/megavault/historicalPnl",
+            "/v4/vault/v1/vaults/historicalPnl",
+            "/v4/vault/v1/megavault/positions",
+        ] {
+            fixtures.insert_response(path, "null");
+        }
+        let client = IndexerClient::from_fixtures(fixtures.clone());
+
+        // Only the requests are checked, the responses fail to decode.
+        let _ = client
+            .get_sub_accounts(SubaccountsRequest::new(address.clone()).limit(2))
+            .await;
+        let _ = client.get_sub_account(address.clone(), 1).await;
+        let _ = client
+            .get_sub_account_perpetual_positions(
+                PositionDetailsRequest::new(address.clone(), 0)
+                    .status(PerpetualPositionStatus::OPEN)
+                    .limit(5),
+            )
+            .await;
+        let _ = client
+            .get_sub_account_asset_positions(PositionDetailsRequest::new(address.clone(), 0))
+            .await;
+        let _ = client
+            .get_sub_account_transfers(
+                TransfersRequest::new(address.clone(), 0)
+                    .created_before_or_at("2024-01-01T00:00:00+01:00".parse().unwrap()),
+            )
+            .await;
+        let _ = client
+            .get_sub_account_orders(
+                OrdersRequest::new(address.clone(), 0)
+                    .ticker(market.clone())
+                    .side(OrderSide::BUY)
+                    .status(OrderStatus::Open)
+                    .order_type(OrderType::Limit)
+                    .limit(10)
+                    .good_til_block_before_or_at(100)
+                    .return_latest_orders(true),
+            )
+            .await;
+        let _ = client.get_order("order-id".to_string()).await;
+        let _ = client.get_order("../id?a=b c#d".to_string()).await;
+        let dots = client.get_order("..".to_string()).await;
+        assert!(matches!(dots, Err(APIError::InvalidRequest(_))));
+        let _ = client
+            .get_sub_account_fills(
+                FillsRequest::new(address.clone(), 0)
+                    .market(market.clone())
+                    .created_before_or_at_height(100),
+            )
+            .await;
+        let _ = client
+            .get_sub_account_historical_pnls(
+                HistoricalPnLRequest::new(address.clone(), 0)
+                    .limit(4)
+                    .created_before_or_at_height(200)
+                    .created_on_or_after("2024-01-01T00:00:00Z".parse().unwrap()),
+            )
+            .await;
+        let _ = client
+            .get_perpetual_markets(PerpetualMarketsRequest::new().ticker(market.clone()))
+            .await;
+        let _ = client.get_perpetual_market_orderbook(market.clone()).await;
+        let _ = client
+            .get_perpetual_market_trades(TradesRequest::new(market.clone()).limit(3))
+            .await;
+        let _ = client
+            .get_perpetual_market_candles(
+                CandlesRequest::new(market.clone(), CandleResolution::OneMin)
+                    .from_iso("2024-01-01T00:00:00Z".parse().unwrap())
+                    .limit(2),
+            )
+            .await;
+        let _ = client
+            .get_perpetual_market_historical_funding(
+                HistoricalFundingRequest::new(market.clone()).effective_before_or_at_height(100),
+            )
+            .await;
+        let _ = client
+            .get_perpetual_market_sparklines(SparklinesRequest::new(TimePeriod::SevenDays))
+            .await;
+        let _ = client.get_parent_sub_account(address.clone(), 0).await;
+        let _ = client
+            .get_parent_sub_account_transfers(
+                ParentSubaccountTransfersRequest::new(address.clone(), 0)
+                    .limit(5)
+                    .page(2),
+            )
+            .await;
+        let _ = client
+            .get_parent_sub_account_orders(
+                ParentSubaccountOrdersRequest::new(address.clone(), 0).status(OrderStatus::Open),
+            )
+            .await;
+        let _ = client
+            .get_parent_sub_account_fills(
+                ParentSubaccountFillsRequest::new(address.clone(), 0).page(3),
+            )
+            .await;
+        let _ = client
+            .get_transfers_between(TransfersBetweenRequest::new(
+                address.clone(),
+                0,
+                "dydx1other".to_string(),
+                1,
+            ))
+            .await;
+        let _ = client
+            .get_historical_block_trading_rewards(
+                HistoricalBlockTradingRewardsRequest::new(address.clone()).limit(10),
+            )
+            .await;
+        let _ = client
+            .get_historical_trading_reward_aggregations(
+                HistoricalTradingRewardAggregationsRequest::new(
+                    address.clone(),
+                    TradingRewardAggregationPeriod::Daily,
+                )
+                .starting_before_or_at_height(100),
+            )
+            .await;
+        let _ = client.get_height().await;
+        let _ = client.get_time().await;
+        let _ = client.screen(address.clone()).await;
+        let _ = client.get_affiliate_metadata(address.clone()).await;
+        let _ = client.get_affiliate_address("CODE".to_string()).await;
+        let _ = client
+            .get_affiliate_snapshot(
+                AffiliateSnapshotRequest::new()
+                    .limit(10)
+                    .sort_by_affiliate_earning(true),
+            )
+            .await;
+        let _ = client.get_affiliate_total_volume(address.clone()).await;
+        let _ = client
+            .get_megavault_historical_pnl(PnlTickInterval::Day)
+            .await;
+        let _ = client
+            .get_vaults_historical_pnl(PnlTickInterval::Hour)
+            .await;
+        let _ = client.get_megavault_positions().await;
+
+        assert_eq!(
+            fixtures.requests(),
+            vec![
+                "/v4/addresses/dydx1address?limit=2",
+                "/v4/addresses/dydx1address/subaccountNumber/1",
+                "/v4/perpetualPositions?address=dydx1address&subaccountNumber=0&status=OPEN&limit=5",
+                "/v4/assetPositions?address=dydx1address&subaccountNumber=0",
+                "/v4/transfers?address=dydx1address&subaccountNumber=0&createdBeforeOrAt=2023-12-31T23%3A00%3A00.000Z",
+                "/v4/orders?address=dydx1address&subaccountNumber=0&ticker=BTC-USD&tickerType=PERPETUAL&side=BUY&status=OPEN&type=LIMIT&limit=10&goodTilBlockBeforeOrAt=100&returnLatestOrders=true",
+                "/v4/orders/order-id",
+                "/v4/orders/%2E%2E%2Fid%3Fa%3Db%20c%23d",
+                "/v4/fills?address=dydx1address&subaccountNumber=0&market=BTC-USD&marketType=PERPETUAL&createdBeforeOrAtHeight=100",
+                "/v4/historical-pnl?address=dydx1address&subaccountNumber=0&limit=4&createdBeforeOrAtHeight=200&createdOnOrAfter=2024-01-01T00%3A00%3A00.000Z",
+                "/v4/perpetualMarkets?ticker=BTC-USD",
+                "/v4/orderbooks/perpetualMarket/BTC-USD",
+                "/v4/trades/perpetualMarket/BTC-USD?limit=3",
+                "/v4/candles/perpetualMarket/BTC-USD?resolution=1MIN&fromISO=2024-01-01T00%3A00%3A00.000Z&limit=2",
+                "/v4/historicalFunding/BTC-USD?effectiveBeforeOrAtHeight=100",
+                "/v4/sparklines?timePeriod=SEVEN_DAYS",
+                "/v4/addresses/dydx1address/parentSubaccountNumber/0",
+                "/v4/transfers/parentSubaccountNumber?address=dydx1address&parentSubaccountNumber=0&limit=5&page=2",
+                "/v4/orders/parentSubaccountNumber?address=dydx1address&parentSubaccountNumber=0&tickerType=PERPETUAL&status=OPEN",
+                "/v4/fills/parentSubaccountNumber?address=dydx1address&parentSubaccountNumber=0&marketType=PERPETUAL&page=3",
+                "/v4/transfers/between?sourceAddress=dydx1address&sourceSubaccountNumber=0&recipientAddress=dydx1other&recipientSubaccountNumber=1",
+                "/v4/historicalBlockTradingRewards/dydx1address?limit=10",
+                "/v4/historicalTradingRewardAggregations/dydx1address?period=DAILY&startingBeforeOrAtHeight=100",
+                "/v4/height",
+                "/v4/time",
+                "/v4/screen?address=dydx1address",
+                "/v4/affiliates/metadata?address=dydx1address",
+                "/v4/affiliates/address?referralCode=CODE",
+                "/v4/affiliates/snapshot?limit=10&sortByAffiliateEarning=true",
+                "/v4/affiliates/total_volume?address=dydx1address",
+                "/v4/vault/v1/megavault/historicalPnl?resolution=day",
+                "/v4/vault/v1/vaults/historicalPnl?resolution=hour",
+                "/v4/vault/v1/megavault/positions",
+            ]
+        );
+    }
+}
@@ -1,50 +1,72 @@
-use std::collections::HashMap;
+use std::{
+    collections::{hash_map, HashMap},
+    fmt,
+    time::Duration,
+};
 
+use chrono::{DateTime, SecondsFormat, Utc};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
 use crate::constants::{
-    OrderSide, OrderStatus, OrderTimeInForce, OrderType, PerpetualPositionStatus,
+    OrderSide, OrderStatus, OrderTimeInForce, OrderType, PerpetualPositionStatus, TickerType,
+    TimePeriod, TradingRewardAggregationPeriod,
 };
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct SubAccountResponseObject {
-    address: String,
-    subaccount_number: u32,
-    equity: String,
-    free_collateral: String,
-    open_perpetual_positions: Option<HashMap<String, PerpetualPositionResponseStruct>>,
-    asset_positions: Option<HashMap<String, AssetPositionResponseStruct>>,
-    margin_enabled: bool,
+    pub address: String,
+    pub subaccount_number: u32,
+    pub equity: Decimal,
+    pub free_collateral: Decimal,
+    pub open_perpetual_positions: Option<HashMap<String, PerpetualPositionResponseStruct>>,
+    pub asset_positions: Option<HashMap<String, AssetPositionResponseStruct>>,
+    pub margin_enabled: bool,
+}
+
+/// Response of `/v4/addresses/{address}`.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SubaccountsResponse {
+    pub subaccounts: Vec<SubAccountResponseObject>,
+    pub total_trading_rewards: Decimal,
+}
+
+/// Response of `/v4/addresses/{address}/subaccountNumber/{n}`.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct SubaccountResponse {
+    pub subaccount: SubAccountResponseObject,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct PerpetualPositionResponseStruct {
-    market: String,
-    status: Option<PerpetualPositionStatus>,
-    side: Option<PositionSide>,
-    size: String,
-    max_size: String,
-    entry_price: String,
-    realized_pnl: String,
-    created_at: String,
-    created_at_height: String,
-    sum_open: String,
-    sum_close: String,
-    net_funding: String,
-    unrealized_pnl: String,
-    closed_at: Option<String>,
-    exit_price: Option<String>,
+    pub market: String,
+    pub status: Option<PerpetualPositionStatus>,
+    pub side: Option<PositionSide>,
+    pub size: Decimal,
+    pub max_size: Decimal,
+    pub entry_price: Decimal,
+    pub realized_pnl: Decimal,
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "height")]
+    pub created_at_height: u64,
+    pub sum_open: Decimal,
+    pub sum_close: Decimal,
+    pub net_funding: Decimal,
+    pub unrealized_pnl: Decimal,
+    pub closed_at: Option<DateTime<Utc>>,
+    pub exit_price: Option<Decimal>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct AssetPositionResponseStruct {
-    symbol: String,
-    side: Option<PositionSide>,
-    size: String,
-    asset_id: Option<String>,
+    pub symbol: String,
+    pub side: Option<PositionSide>,
+    pub size: Decimal,
+    pub asset_id: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
@@ -68,22 +90,26 @@ pub enum TransferType {
 #[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct TransferResponseSenderObject {
-    sub_account_number: u32,
-    address: String,
+    /// Missing when the party is an address rather than a subaccount, e.g.
+    /// for deposits and withdrawals.
+    pub subaccount_number: Option<u32>,
+    pub address: String,
 }
 
 #[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
 pub struct TransferResponseStruct {
-    id: String,
-    sender: TransferResponseSenderObject,
-    recipient: TransferResponseSenderObject,
-    size: String,
-    created_at: String,
-    created_at_height: String,
-    symbol: String,
+    pub id: String,
+    pub sender: TransferResponseSenderObject,
+    pub recipient: TransferResponseSenderObject,
+    pub size: Decimal,
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "height")]
+    pub created_at_height: u64,
+    pub symbol: String,
     #[serde(rename = "type")]
-    transfer_type: TransferType,
-    transaction_hash: String,
+    pub transfer_type: TransferType,
+    pub transaction_hash: String,
 }
 
 // ========================================
@@ -91,28 +117,31 @@ pub struct TransferResponseStruct {
 // ========================================
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
 pub struct OrderResponseStruct {
-    id: String,
-    subaccount_id: Option<String>,
-    client_id: Option<String>,
-    clob_pair_id: Option<String>,
-    side: OrderSide,
-    size: String,
-    total_filled: String,
-    price: String,
+    pub id: String,
+    pub subaccount_id: Option<String>,
+    pub client_id: Option<String>,
+    pub clob_pair_id: Option<String>,
+    pub side: OrderSide,
+    pub size: Decimal,
+    pub total_filled: Decimal,
+    pub price: Decimal,
     #[serde(rename = "type")]
-    order_type: OrderType,
-    reduce_only: bool,
-    order_flags: Option<String>,
-    good_til_block: Option<String>,
-    good_til_block_time: Option<String>,
-    created_at_height: Option<String>,
-    client_metadata: Option<String>,
-    trigger_price: Option<String>,
-    time_in_force: OrderTimeInForce,
-    status: OrderStatus,
-    post_only: bool,
-    ticker: String,
+    pub order_type: OrderType,
+    pub reduce_only: bool,
+    pub order_flags: Option<String>,
+    #[serde(default, with = "option_height")]
+    pub good_til_block: Option<u64>,
+    pub good_til_block_time: Option<DateTime<Utc>>,
+    #[serde(default, with = "option_height")]
+    pub created_at_height: Option<u64>,
+    pub client_metadata: Option<String>,
+    pub trigger_price: Option<Decimal>,
+    pub time_in_force: OrderTimeInForce,
+    pub status: OrderStatus,
+    pub post_only: bool,
+    pub ticker: String,
 }
 
 // ========================================
@@ -120,21 +149,23 @@ pub struct OrderResponseStruct {
 // ========================================
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
 pub struct FillResponseStruct {
-    id: String,
-    side: OrderSide,
-    liquidity: Liquidity,
+    pub id: String,
+    pub side: OrderSide,
+    pub liquidity: Liquidity,
     #[serde(rename = "type")]
-    fill_type: FillType,
-    market: String,
-    market_type: MarketType,
-    price: String,
-    size: String,
-    fee: String,
-    created_at: String,
-    created_at_height: String,
-    order_id: Option<String>,
-    client_metadata: Option<String>,
+    pub fill_type: FillType,
+    pub market: String,
+    pub market_type: MarketType,
+    pub price: Decimal,
+    pub size: Decimal,
+    pub fee: Decimal,
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "height")]
+    pub created_at_height: u64,
+    pub order_id: Option<String>,
+    pub client_metadata: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
@@ -148,9 +179,17 @@ pub enum Liquidity {
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum FillType {
     Maker,
+    Market,
     Limit,
     Liquidated,
     Liquidation,
+    /// Closed by the protocol against an under-collateralized account.
+    Deleveraged,
+    /// The counterparty side of a deleveraging.
+    Offsetting,
+    /// A fill type added to the indexer after this client.
+    #[serde(other)]
+    Unknown,
 }
 
 #[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
@@ -165,15 +204,17 @@ pub enum MarketType {
 // ========================================
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
 pub struct PnLTicksResponseStruct {
-    id: String,
-    subaccount_id: Option<String>,
-    equity: String,
-    total_pnl: String,
-    net_transfers: String,
-    created_at: String,
-    block_height: String,
-    block_time: String,
+    pub id: String,
+    pub subaccount_id: Option<String>,
+    pub equity: Decimal,
+    pub total_pnl: Decimal,
+    pub net_transfers: Decimal,
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "height")]
+    pub block_height: u64,
+    pub block_time: DateTime<Utc>,
 }
 
 // ========================================
@@ -183,30 +224,32 @@ pub struct PnLTicksResponseStruct {
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct PerpetualMarketResponseStruct {
-    clob_pair_id: String,
-    ticker: String,
-    status: PerpetualMarketStatus,
-    last_price: String,
-    oracle_price: String,
-    price_change_24h: String,
-    volume_24h: String,
-    trades_24h: f64,
-    next_funding_rate: String,
-    initial_margin_fraction: String,
-    maintenance_margin_fraction: String,
-    base_position_notional: String,
-    open_interest: String,
-    atomic_resolution: f64,
-    quantum_conversion_exponent: f64,
-    tick_size: String,
-    step_size: String,
-    step_base_quantums: f64,
-    subticks_per_tick: f64,
+    pub clob_pair_id: String,
+    pub ticker: String,
+    pub status: PerpetualMarketStatus,
+    pub last_price: Decimal,
+    pub oracle_price: Decimal,
+    #[serde(rename = "priceChange24H")]
+    pub price_change_24h: Decimal,
+    #[serde(rename = "volume24H")]
+    pub volume_24h: Decimal,
+    #[serde(rename = "trades24H")]
+    pub trades_24h: u64,
+    pub next_funding_rate: Decimal,
+    pub initial_margin_fraction: Decimal,
+    pub maintenance_margin_fraction: Decimal,
+    pub base_position_notional: Decimal,
+    pub open_interest: Decimal,
+    pub atomic_resolution: i32,
+    pub quantum_conversion_exponent: i32,
+    pub tick_size: Decimal,
+    pub step_size: Decimal,
+    pub step_base_quantums: u64,
+    pub subticks_per_tick: u64,
 }
 
 #[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
-
 pub enum PerpetualMarketStatus {
     Active,
     Paused,
@@ -218,40 +261,40 @@ pub enum PerpetualMarketStatus {
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct OrderbookResponsePriceLevel {
-    price: String,
-    size: String,
+    pub price: Decimal,
+    pub size: Decimal,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct TradeResponseStruct {
-    id: String,
-    side: OrderSide,
-    size: String,
-    price: String,
-    create_at: String,
-    create_at_height: String,
+    pub id: String,
+    pub side: OrderSide,
+    pub size: Decimal,
+    pub price: Decimal,
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "height")]
+    pub created_at_height: u64,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct CandleResponseStruct {
-    started_at: String,
-    ticker: String,
-    resolution: CandleResolution,
-    low: String,
-    high: String,
-    open: String,
-    close: String,
-    base_token_volume: String,
-    usd_volume: String,
-    trades: u32,
-    starting_open_interest: String,
-    id: String,
+    pub started_at: DateTime<Utc>,
+    pub ticker: String,
+    pub resolution: CandleResolution,
+    pub low: Decimal,
+    pub high: Decimal,
+    pub open: Decimal,
+    pub close: Decimal,
+    pub base_token_volume: Decimal,
+    pub usd_volume: Decimal,
+    pub trades: u32,
+    pub starting_open_interest: Decimal,
+    pub id: String,
 }
 
-#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
-
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum CandleResolution {
     #[serde(rename = "1MIN")]
     OneMin,
@@ -269,14 +312,189 @@ pub enum CandleResolution {
     OneDay,
 }
 
+impl fmt::Display for CandleResolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CandleResolution::OneMin => write!(f, "1MIN"),
+            CandleResolution::FiveMin => write!(f, "5MINS"),
+            CandleResolution::FifteenMin => write!(f, "15MINS"),
+            CandleResolution::ThirtyMin => write!(f, "30MINS"),
+            CandleResolution::OneHour => write!(f, "1HOUR"),
+            CandleResolution::FourHour => write!(f, "4HOURS"),
+            CandleResolution::OneDay => write!(f, "1DAY"),
+        }
+    }
+}
+
+impl CandleResolution {
+    /// Length of one candle of this resolution.
+    pub fn duration(&self) -> Duration {
+        let seconds = match self {
+            CandleResolution::OneMin => 60,
+            CandleResolution::FiveMin => 5 * 60,
+            CandleResolution::FifteenMin => 15 * 60,
+            CandleResolution::ThirtyMin => 30 * 60,
+            CandleResolution::OneHour => 60 * 60,
+            CandleResolution::FourHour => 4 * 60 * 60,
+            CandleResolution::OneDay => 24 * 60 * 60,
+        };
+        Duration::from_secs(seconds)
+    }
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct HistoricalFundingResponseStruct {
-    ticker: String,
-    rate: String,
-    price: String,
-    effective_at: String,
-    effective_at_height: String,
+    pub ticker: String,
+    pub rate: Decimal,
+    pub price: Decimal,
+    pub effective_at: DateTime<Utc>,
+    #[serde(with = "height")]
+    pub effective_at_height: u64,
+}
+
+// ========================================
+// Parent subaccount structs
+// ========================================
+
+/// A parent subaccount together with the child subaccounts that hold its
+/// isolated positions.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ParentSubaccountResponseObject {
+    pub address: String,
+    pub parent_subaccount_number: u32,
+    pub equity: Decimal,
+    pub free_collateral: Decimal,
+    pub child_subaccounts: Vec<SubAccountResponseObject>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ParentSubaccountResponse {
+    pub subaccount: ParentSubaccountResponseObject,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferBetweenResponse {
+    pub transfers_subset: Vec<TransferResponseStruct>,
+    pub total_net_transfers: Decimal,
+}
+
+// ========================================
+// Trading reward structs
+// ========================================
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoricalBlockTradingReward {
+    pub trading_reward: Decimal,
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "height")]
+    pub created_at_height: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoricalTradingRewardAggregation {
+    pub trading_reward: Decimal,
+    pub started_at: DateTime<Utc>,
+    #[serde(with = "height")]
+    pub started_at_height: u64,
+    /// Missing while the period is still running.
+    pub ended_at: Option<DateTime<Utc>>,
+    #[serde(default, with = "option_height")]
+    pub ended_at_height: Option<u64>,
+    pub period: TradingRewardAggregationPeriod,
+}
+
+// ========================================
+// Utility structs
+// ========================================
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct HeightResponse {
+    #[serde(with = "height")]
+    pub height: u64,
+    pub time: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct TimeResponse {
+    pub iso: DateTime<Utc>,
+    /// Seconds since the unix epoch, with millisecond precision.
+    pub epoch: f64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ComplianceResponse {
+    pub restricted: bool,
+    pub reason: Option<String>,
+}
+
+// ========================================
+// Affiliate structs
+// ========================================
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AffiliateMetadataResponse {
+    pub referral_code: String,
+    pub is_volume_eligible: bool,
+    pub is_affiliate: bool,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct AffiliateAddressResponse {
+    pub address: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AffiliateSnapshotResponseObject {
+    pub affiliate_address: String,
+    pub affiliate_referral_code: String,
+    pub affiliate_earnings: Decimal,
+    pub affiliate_referred_trades: u64,
+    pub affiliate_total_referred_fees: Decimal,
+    pub affiliate_referred_users: u64,
+    pub affiliate_referred_net_protocol_earnings: Decimal,
+    pub affiliate_referred_total_volume: Decimal,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AffiliateSnapshotResponse {
+    pub affiliate_list: Vec<AffiliateSnapshotResponseObject>,
+    pub total: u32,
+    pub current_offset: u32,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AffiliateTotalVolumeResponse {
+    /// Missing for addresses that never traded.
+    pub total_volume: Option<Decimal>,
+}
+
+// ========================================
+// Vault structs
+// ========================================
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultHistoricalPnL {
+    pub ticker: String,
+    pub historical_pnl: Vec<PnLTicksResponseStruct>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultPosition {
+    pub ticker: String,
+    pub asset_position: Option<AssetPositionResponseStruct>,
+    pub perpetual_position: Option<PerpetualPositionResponseStruct>,
+    pub equity: Decimal,
 }
 
 // ========================================
@@ -285,103 +503,990 @@ pub struct HistoricalFundingResponseStruct {
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct PerpetualPositionResponse {
-    positions: Vec<PerpetualPositionResponseStruct>,
+    pub positions: Vec<PerpetualPositionResponseStruct>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct AssetPositionResponse {
-    positions: Vec<AssetPositionResponseStruct>,
+    pub positions: Vec<AssetPositionResponseStruct>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct TransferResponse {
-    transfers: Vec<TransferResponseStruct>,
+    pub transfers: Vec<TransferResponseStruct>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct FillResponse {
-    fills: Vec<FillResponseStruct>,
+    pub fills: Vec<FillResponseStruct>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct HistoricalPnLResponse {
-    historical_pnl: Vec<FillResponseStruct>,
+    pub historical_pnl: Vec<PnLTicksResponseStruct>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct PerpetualMarketsResponse {
-    markets: HashMap<String, PerpetualMarketResponseStruct>,
+    pub markets: HashMap<String, PerpetualMarketResponseStruct>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct OrderbookResponse {
-    bids: Vec<OrderbookResponsePriceLevel>,
-    asks: Vec<OrderbookResponsePriceLevel>,
+    pub bids: Vec<OrderbookResponsePriceLevel>,
+    pub asks: Vec<OrderbookResponsePriceLevel>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct TradeResponse {
-    trades: Vec<TradeResponseStruct>,
+    pub trades: Vec<TradeResponseStruct>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct CandleResponse {
-    candles: Vec<CandleResponseStruct>,
+    pub candles: Vec<CandleResponseStruct>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct HistoricalFundingResponse {
-    historical_funding: Vec<HistoricalFundingResponseStruct>,
+    pub historical_funding: Vec<HistoricalFundingResponseStruct>,
 }
 
-pub type SparklineResponse = HashMap<String, Vec<String>>;
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct HistoricalBlockTradingRewardsResponse {
+    pub rewards: Vec<HistoricalBlockTradingReward>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct HistoricalTradingRewardAggregationsResponse {
+    pub rewards: Vec<HistoricalTradingRewardAggregation>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MegavaultHistoricalPnLResponse {
+    pub megavault_pnl: Vec<PnLTicksResponseStruct>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultsHistoricalPnLResponse {
+    pub vaults_pnl: Vec<VaultHistoricalPnL>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct MegavaultPositionResponse {
+    pub positions: Vec<VaultPosition>,
+}
+
+/// Gives a response that wraps a single list of records slice-like access,
+/// e.g. `for fill in &response { .. }`.
+macro_rules! impl_response_iter {
+    ($response:ty, $field:ident, $item:ty) => {
+        impl $response {
+            pub fn iter(&self) -> std::slice::Iter<'_, $item> {
+                self.$field.iter()
+            }
+
+            pub fn len(&self) -> usize {
+                self.$field.len()
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.$field.is_empty()
+            }
+        }
+
+        impl IntoIterator for $response {
+            type Item = $item;
+            type IntoIter = std::vec::IntoIter<$item>;
+
+            fn into_iter(self) -> Self::IntoIter {
+                self.$field.into_iter()
+            }
+        }
+
+        impl<'a> IntoIterator for &'a $response {
+            type Item = &'a $item;
+            type IntoIter = std::slice::Iter<'a, $item>;
+
+            fn into_iter(self) -> Self::IntoIter {
+                self.$field.iter()
+            }
+        }
+    };
+}
+
+impl_response_iter!(SubaccountsResponse, subaccounts, SubAccountResponseObject);
+impl_response_iter!(
+    PerpetualPositionResponse,
+    positions,
+    PerpetualPositionResponseStruct
+);
+impl_response_iter!(
+    AssetPositionResponse,
+    positions,
+    AssetPositionResponseStruct
+);
+impl_response_iter!(TransferResponse, transfers, TransferResponseStruct);
+impl_response_iter!(FillResponse, fills, FillResponseStruct);
+impl_response_iter!(
+    HistoricalPnLResponse,
+    historical_pnl,
+    PnLTicksResponseStruct
+);
+impl_response_iter!(TradeResponse, trades, TradeResponseStruct);
+impl_response_iter!(CandleResponse, candles, CandleResponseStruct);
+impl_response_iter!(
+    HistoricalFundingResponse,
+    historical_funding,
+    HistoricalFundingResponseStruct
+);
+impl_response_iter!(
+    HistoricalBlockTradingRewardsResponse,
+    rewards,
+    HistoricalBlockTradingReward
+);
+impl_response_iter!(
+    HistoricalTradingRewardAggregationsResponse,
+    rewards,
+    HistoricalTradingRewardAggregation
+);
+
+impl PerpetualMarketsResponse {
+    pub fn get(&self, ticker: &str) -> Option<&PerpetualMarketResponseStruct> {
+        self.markets.get(ticker)
+    }
+
+    pub fn iter(&self) -> hash_map::Iter<'_, String, PerpetualMarketResponseStruct> {
+        self.markets.iter()
+    }
+}
+
+impl IntoIterator for PerpetualMarketsResponse {
+    type Item = (String, PerpetualMarketResponseStruct);
+    type IntoIter = hash_map::IntoIter<String, PerpetualMarketResponseStruct>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.markets.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a PerpetualMarketsResponse {
+    type Item = (&'a String, &'a PerpetualMarketResponseStruct);
+    type IntoIter = hash_map::Iter<'a, String, PerpetualMarketResponseStruct>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.markets.iter()
+    }
+}
+
+pub type SparklineResponse = HashMap<String, Vec<Decimal>>;
 
 // ========================================
 // Request structs
 // ========================================
 
-#[derive(Deserialize, Serialize, Clone, Debug)]
+macro_rules! impl_request_setters {
+    ($request:ty { $($field:ident: $value:ty),* $(,)? }) => {
+        impl $request {
+            $(
+                pub fn $field(mut self, $field: $value) -> Self {
+                    self.$field = Some($field);
+                    self
+                }
+            )*
+        }
+    };
+}
+
+/// Query of `/v4/addresses/{address}`.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SubaccountsRequest {
+    #[serde(skip)]
+    address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<u32>,
+}
+
+impl SubaccountsRequest {
+    pub fn new(address: String) -> Self {
+        SubaccountsRequest {
+            address,
+            limit: None,
+        }
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+}
+
+impl_request_setters!(SubaccountsRequest { limit: u32 });
+
+/// Query of `/v4/perpetualPositions` and `/v4/assetPositions`.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct PositionDetailsRequest {
     address: String,
+    #[serde(rename = "subaccountNumber")]
     sub_account_number: u32,
     #[serde(skip_serializing_if = "Option::is_none")]
     status: Option<PerpetualPositionStatus>,
     #[serde(skip_serializing_if = "Option::is_none")]
     limit: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    create_before_or_at_height: Option<u32>,
+    created_before_or_at_height: Option<u64>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "option_time"
+    )]
+    created_before_or_at: Option<DateTime<Utc>>,
+}
+
+impl PositionDetailsRequest {
+    pub fn new(address: String, sub_account_number: u32) -> Self {
+        PositionDetailsRequest {
+            address,
+            sub_account_number,
+            status: None,
+            limit: None,
+            created_before_or_at_height: None,
+            created_before_or_at: None,
+        }
+    }
+}
+
+impl_request_setters!(PositionDetailsRequest {
+    status: PerpetualPositionStatus,
+    limit: u32,
+    created_before_or_at_height: u64,
+    created_before_or_at: DateTime<Utc>,
+});
+
+/// Query of `/v4/transfers`.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TransfersRequest {
+    address: String,
+    #[serde(rename = "subaccountNumber")]
+    sub_account_number: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created_before_or_at_height: Option<u64>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "option_time"
+    )]
+    created_before_or_at: Option<DateTime<Utc>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    create_before_or_at: Option<String>,
+    page: Option<u32>,
 }
 
-impl Into<Vec<(String, Option<String>)>> for PositionDetailsRequest {
-    fn into(self) -> Vec<(String, Option<String>)> {
-        let mut v = vec![
-            ("address".to_string(), Some(self.address)),
-            (
-                "subAccountNumber".to_string(),
-                Some(format!("{}", self.sub_account_number)),
-            ),
-        ];
-        if let Some(status) = self.status {
-            v.push(("status".to_string(), Some(status.into())));
-        };
-        if let Some(limit) = self.limit {
-            v.push(("limit".to_string(), Some(format!("{limit}"))));
+impl TransfersRequest {
+    pub fn new(address: String, sub_account_number: u32) -> Self {
+        TransfersRequest {
+            address,
+            sub_account_number,
+            limit: None,
+            created_before_or_at_height: None,
+            created_before_or_at: None,
+            page: None,
         }
-        if let Some(cboah) = self.create_before_or_at_height {
-            v.push((
-                "createdBeforeOrAtHeight".to_string(),
-                Some(format!("{cboah}")),
-            ));
+    }
+}
+
+impl_request_setters!(TransfersRequest {
+    limit: u32,
+    created_before_or_at_height: u64,
+    created_before_or_at: DateTime<Utc>,
+    page: u32,
+});
+
+/// Query of `/v4/orders`. Only perpetual orders are listed unless
+/// [`OrdersRequest::ticker_type`] says otherwise.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OrdersRequest {
+    address: String,
+    #[serde(rename = "subaccountNumber")]
+    sub_account_number: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ticker: Option<String>,
+    ticker_type: TickerType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    side: Option<OrderSide>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<OrderStatus>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    order_type: Option<OrderType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    good_til_block_before_or_at: Option<u64>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "option_time"
+    )]
+    good_til_block_time_before_or_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    return_latest_orders: Option<bool>,
+}
+
+impl OrdersRequest {
+    pub fn new(address: String, sub_account_number: u32) -> Self {
+        OrdersRequest {
+            address,
+            sub_account_number,
+            ticker: None,
+            ticker_type: TickerType::PERPETUAL,
+            side: None,
+            status: None,
+            order_type: None,
+            limit: None,
+            good_til_block_before_or_at: None,
+            good_til_block_time_before_or_at: None,
+            return_latest_orders: None,
         }
-        if let Some(cboa) = self.create_before_or_at {
-            v.push(("createdBeforeOrAt".to_string(), Some(cboa)));
+    }
+
+    pub fn ticker_type(mut self, ticker_type: TickerType) -> Self {
+        self.ticker_type = ticker_type;
+        self
+    }
+}
+
+impl_request_setters!(OrdersRequest {
+    ticker: String,
+    side: OrderSide,
+    status: OrderStatus,
+    order_type: OrderType,
+    limit: u32,
+    good_til_block_before_or_at: u64,
+    good_til_block_time_before_or_at: DateTime<Utc>,
+    return_latest_orders: bool,
+});
+
+/// Query of `/v4/fills`.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FillsRequest {
+    address: String,
+    #[serde(rename = "subaccountNumber")]
+    sub_account_number: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    market: Option<String>,
+    market_type: TickerType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created_before_or_at_height: Option<u64>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "option_time"
+    )]
+    created_before_or_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page: Option<u32>,
+}
+
+impl FillsRequest {
+    pub fn new(address: String, sub_account_number: u32) -> Self {
+        FillsRequest {
+            address,
+            sub_account_number,
+            market: None,
+            market_type: TickerType::PERPETUAL,
+            limit: None,
+            created_before_or_at_height: None,
+            created_before_or_at: None,
+            page: None,
+        }
+    }
+
+    pub fn market_type(mut self, market_type: TickerType) -> Self {
+        self.market_type = market_type;
+        self
+    }
+}
+
+impl_request_setters!(FillsRequest {
+    market: String,
+    limit: u32,
+    created_before_or_at_height: u64,
+    created_before_or_at: DateTime<Utc>,
+    page: u32,
+});
+
+/// Query of `/v4/historical-pnl`.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoricalPnLRequest {
+    address: String,
+    #[serde(rename = "subaccountNumber")]
+    sub_account_number: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created_before_or_at_height: Option<u64>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "option_time"
+    )]
+    created_before_or_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created_on_or_after_height: Option<u64>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "option_time"
+    )]
+    created_on_or_after: Option<DateTime<Utc>>,
+}
+
+impl HistoricalPnLRequest {
+    pub fn new(address: String, sub_account_number: u32) -> Self {
+        HistoricalPnLRequest {
+            address,
+            sub_account_number,
+            limit: None,
+            created_before_or_at_height: None,
+            created_before_or_at: None,
+            created_on_or_after_height: None,
+            created_on_or_after: None,
+        }
+    }
+}
+
+impl_request_setters!(HistoricalPnLRequest {
+    limit: u32,
+    created_before_or_at_height: u64,
+    created_before_or_at: DateTime<Utc>,
+    created_on_or_after_height: u64,
+    created_on_or_after: DateTime<Utc>,
+});
+
+/// Query of `/v4/perpetualMarkets`. All markets are listed unless a
+/// ticker is set.
+#[derive(Serialize, Clone, PartialEq, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PerpetualMarketsRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ticker: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<u32>,
+}
+
+impl PerpetualMarketsRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl_request_setters!(PerpetualMarketsRequest {
+    ticker: String,
+    limit: u32,
+});
+
+/// Query of `/v4/sparklines`.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SparklinesRequest {
+    time_period: TimePeriod,
+}
+
+impl SparklinesRequest {
+    pub fn new(time_period: TimePeriod) -> Self {
+        SparklinesRequest { time_period }
+    }
+}
+
+/// Query of `/v4/trades/perpetualMarket/{market}`.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TradesRequest {
+    #[serde(skip)]
+    market: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created_before_or_at_height: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page: Option<u32>,
+}
+
+impl TradesRequest {
+    pub fn new(market: String) -> Self {
+        TradesRequest {
+            market,
+            created_before_or_at_height: None,
+            limit: None,
+            page: None,
+        }
+    }
+
+    pub fn market(&self) -> &str {
+        &self.market
+    }
+}
+
+impl_request_setters!(TradesRequest {
+    created_before_or_at_height: u64,
+    limit: u32,
+    page: u32,
+});
+
+/// Query of `/v4/candles/perpetualMarket/{market}`.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CandlesRequest {
+    #[serde(skip)]
+    market: String,
+    resolution: CandleResolution,
+    #[serde(
+        rename = "fromISO",
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "option_time"
+    )]
+    from_iso: Option<DateTime<Utc>>,
+    #[serde(
+        rename = "toISO",
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "option_time"
+    )]
+    to_iso: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<u32>,
+}
+
+impl CandlesRequest {
+    pub fn new(market: String, resolution: CandleResolution) -> Self {
+        CandlesRequest {
+            market,
+            resolution,
+            from_iso: None,
+            to_iso: None,
+            limit: None,
+        }
+    }
+
+    pub fn market(&self) -> &str {
+        &self.market
+    }
+
+    pub fn resolution(&self) -> CandleResolution {
+        self.resolution
+    }
+}
+
+impl_request_setters!(CandlesRequest {
+    from_iso: DateTime<Utc>,
+    to_iso: DateTime<Utc>,
+    limit: u32,
+});
+
+/// Query of `/v4/historicalFunding/{market}`.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoricalFundingRequest {
+    #[serde(skip)]
+    market: String,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "option_time"
+    )]
+    effective_before_or_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    effective_before_or_at_height: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<u32>,
+}
+
+impl HistoricalFundingRequest {
+    pub fn new(market: String) -> Self {
+        HistoricalFundingRequest {
+            market,
+            effective_before_or_at: None,
+            effective_before_or_at_height: None,
+            limit: None,
+        }
+    }
+
+    pub fn market(&self) -> &str {
+        &self.market
+    }
+}
+
+impl_request_setters!(HistoricalFundingRequest {
+    effective_before_or_at: DateTime<Utc>,
+    effective_before_or_at_height: u64,
+    limit: u32,
+});
+
+/// Query of `/v4/transfers/parentSubaccountNumber`.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ParentSubaccountTransfersRequest {
+    address: String,
+    parent_subaccount_number: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created_before_or_at_height: Option<u64>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "option_time"
+    )]
+    created_before_or_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page: Option<u32>,
+}
+
+impl ParentSubaccountTransfersRequest {
+    pub fn new(address: String, parent_subaccount_number: u32) -> Self {
+        ParentSubaccountTransfersRequest {
+            address,
+            parent_subaccount_number,
+            limit: None,
+            created_before_or_at_height: None,
+            created_before_or_at: None,
+            page: None,
+        }
+    }
+}
+
+impl_request_setters!(ParentSubaccountTransfersRequest {
+    limit: u32,
+    created_before_or_at_height: u64,
+    created_before_or_at: DateTime<Utc>,
+    page: u32,
+});
+
+/// Query of `/v4/orders/parentSubaccountNumber`.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ParentSubaccountOrdersRequest {
+    address: String,
+    parent_subaccount_number: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ticker: Option<String>,
+    ticker_type: TickerType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    side: Option<OrderSide>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<OrderStatus>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    order_type: Option<OrderType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    good_til_block_before_or_at: Option<u64>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "option_time"
+    )]
+    good_til_block_time_before_or_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    return_latest_orders: Option<bool>,
+}
+
+impl ParentSubaccountOrdersRequest {
+    pub fn new(address: String, parent_subaccount_number: u32) -> Self {
+        ParentSubaccountOrdersRequest {
+            address,
+            parent_subaccount_number,
+            ticker: None,
+            ticker_type: TickerType::PERPETUAL,
+            side: None,
+            status: None,
+            order_type: None,
+            limit: None,
+            good_til_block_before_or_at: None,
+            good_til_block_time_before_or_at: None,
+            return_latest_orders: None,
+        }
+    }
+
+    pub fn ticker_type(mut self, ticker_type: TickerType) -> Self {
+        self.ticker_type = ticker_type;
+        self
+    }
+}
+
+impl_request_setters!(ParentSubaccountOrdersRequest {
+    ticker: String,
+    side: OrderSide,
+    status: OrderStatus,
+    order_type: OrderType,
+    limit: u32,
+    good_til_block_before_or_at: u64,
+    good_til_block_time_before_or_at: DateTime<Utc>,
+    return_latest_orders: bool,
+});
+
+/// Query of `/v4/fills/parentSubaccountNumber`.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ParentSubaccountFillsRequest {
+    address: String,
+    parent_subaccount_number: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    market: Option<String>,
+    market_type: TickerType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created_before_or_at_height: Option<u64>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "option_time"
+    )]
+    created_before_or_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page: Option<u32>,
+}
+
+impl ParentSubaccountFillsRequest {
+    pub fn new(address: String, parent_subaccount_number: u32) -> Self {
+        ParentSubaccountFillsRequest {
+            address,
+            parent_subaccount_number,
+            market: None,
+            market_type: TickerType::PERPETUAL,
+            limit: None,
+            created_before_or_at_height: None,
+            created_before_or_at: None,
+            page: None,
+        }
+    }
+
+    pub fn market_type(mut self, market_type: TickerType) -> Self {
+        self.market_type = market_type;
+        self
+    }
+}
+
+impl_request_setters!(ParentSubaccountFillsRequest {
+    market: String,
+    limit: u32,
+    created_before_or_at_height: u64,
+    created_before_or_at: DateTime<Utc>,
+    page: u32,
+});
+
+/// Query of `/v4/transfers/between`, the transfers from one subaccount to
+/// another.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TransfersBetweenRequest {
+    source_address: String,
+    source_subaccount_number: u32,
+    recipient_address: String,
+    recipient_subaccount_number: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created_before_or_at_height: Option<u64>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "option_time"
+    )]
+    created_before_or_at: Option<DateTime<Utc>>,
+}
+
+impl TransfersBetweenRequest {
+    pub fn new(
+        source_address: String,
+        source_subaccount_number: u32,
+        recipient_address: String,
+        recipient_subaccount_number: u32,
+    ) -> Self {
+        TransfersBetweenRequest {
+            source_address,
+            source_subaccount_number,
+            recipient_address,
+            recipient_subaccount_number,
+            created_before_or_at_height: None,
+            created_before_or_at: None,
+        }
+    }
+}
+
+impl_request_setters!(TransfersBetweenRequest {
+    created_before_or_at_height: u64,
+    created_before_or_at: DateTime<Utc>,
+});
+
+/// Query of `/v4/historicalBlockTradingRewards/{address}`.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoricalBlockTradingRewardsRequest {
+    #[serde(skip)]
+    address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    starting_before_or_at_height: Option<u64>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "option_time"
+    )]
+    starting_before_or_at: Option<DateTime<Utc>>,
+}
+
+impl HistoricalBlockTradingRewardsRequest {
+    pub fn new(address: String) -> Self {
+        HistoricalBlockTradingRewardsRequest {
+            address,
+            limit: None,
+            starting_before_or_at_height: None,
+            starting_before_or_at: None,
+        }
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+}
+
+impl_request_setters!(HistoricalBlockTradingRewardsRequest {
+    limit: u32,
+    starting_before_or_at_height: u64,
+    starting_before_or_at: DateTime<Utc>,
+});
+
+/// Query of `/v4/historicalTradingRewardAggregations/{address}`.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoricalTradingRewardAggregationsRequest {
+    #[serde(skip)]
+    address: String,
+    period: TradingRewardAggregationPeriod,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    starting_before_or_at_height: Option<u64>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "option_time"
+    )]
+    starting_before_or_at: Option<DateTime<Utc>>,
+}
+
+impl HistoricalTradingRewardAggregationsRequest {
+    pub fn new(address: String, period: TradingRewardAggregationPeriod) -> Self {
+        HistoricalTradingRewardAggregationsRequest {
+            address,
+            period,
+            limit: None,
+            starting_before_or_at_height: None,
+            starting_before_or_at: None,
+        }
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+}
+
+impl_request_setters!(HistoricalTradingRewardAggregationsRequest {
+    limit: u32,
+    starting_before_or_at_height: u64,
+    starting_before_or_at: DateTime<Utc>,
+});
+
+/// Query of `/v4/affiliates/snapshot`.
+#[derive(Serialize, Clone, PartialEq, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AffiliateSnapshotRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    offset: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sort_by_affiliate_earning: Option<bool>,
+}
+
+impl AffiliateSnapshotRequest {
+    pub fn new() -> Self {
+        AffiliateSnapshotRequest::default()
+    }
+}
+
+impl_request_setters!(AffiliateSnapshotRequest {
+    offset: u32,
+    limit: u32,
+    sort_by_affiliate_earning: bool,
+});
+
+// ========================================
+// Serde helpers
+// ========================================
+
+/// Heights are sent as decimal strings by the indexer, but plain numbers are
+/// accepted as well.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StringOrU64 {
+    String(String),
+    U64(u64),
+}
+
+impl StringOrU64 {
+    fn into_u64<E: serde::de::Error>(self) -> Result<u64, E> {
+        match self {
+            StringOrU64::String(value) => value.parse().map_err(E::custom),
+            StringOrU64::U64(value) => Ok(value),
+        }
+    }
+}
+
+/// Serializes a request time as the indexer's ISO 8601 timestamps: UTC with
+/// milliseconds.
+fn option_time<S: serde::Serializer>(
+    value: &Option<DateTime<Utc>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(time) => serializer.collect_str(&time.to_rfc3339_opts(SecondsFormat::Millis, true)),
+        None => serializer.serialize_none(),
+    }
+}
+
+pub(crate) mod height {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::StringOrU64;
+
+    pub fn serialize<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        StringOrU64::deserialize(deserializer)?.into_u64()
+    }
+}
+
+pub(crate) mod option_height {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::StringOrU64;
+
+    pub fn serialize<S: Serializer>(value: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => serializer.collect_str(value),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<u64>, D::Error> {
+        match Option::<StringOrU64>::deserialize(deserializer)? {
+            Some(value) => value.into_u64().map(Some),
+            None => Ok(None),
         }
-        v.to_owned()
     }
 }
 
@@ -399,55 +1504,55 @@ mod tests {
         {
           "address": "string",
           "subaccountNumber": 0,
-          "equity": "string",
-          "freeCollateral": "string",
+          "equity": "1000.25",
+          "freeCollateral": "900",
           "openPerpetualPositions": {
             "property1": {
               "market": "string",
               "status": "OPEN",
               "side": "LONG",
-              "size": "string",
-              "maxSize": "string",
-              "entryPrice": "string",
-              "realizedPnl": "string",
-              "createdAt": "string",
-              "createdAtHeight": "string",
-              "sumOpen": "string",
-              "sumClose": "string",
-              "netFunding": "string",
-              "unrealizedPnl": "string",
-              "closedAt": "string",
-              "exitPrice": "string"
+              "size": "0.5",
+              "maxSize": "1",
+              "entryPrice": "50000.5",
+              "realizedPnl": "-12.34",
+              "createdAt": "2024-01-01T00:00:00.000Z",
+              "createdAtHeight": "1000",
+              "sumOpen": "1",
+              "sumClose": "0.5",
+              "netFunding": "0.01",
+              "unrealizedPnl": "3.5",
+              "closedAt": "2024-01-02T00:00:00.000Z",
+              "exitPrice": "51000"
             },
             "property2": {
               "market": "string",
               "status": "OPEN",
               "side": "LONG",
-              "size": "string",
-              "maxSize": "string",
-              "entryPrice": "string",
-              "realizedPnl": "string",
-              "createdAt": "string",
-              "createdAtHeight": "string",
-              "sumOpen": "string",
-              "sumClose": "string",
-              "netFunding": "string",
-              "unrealizedPnl": "string",
-              "closedAt": "string",
-              "exitPrice": "string"
+              "size": "0.5",
+              "maxSize": "1",
+              "entryPrice": "50000.5",
+              "realizedPnl": "-12.34",
+              "createdAt": "2024-01-01T00:00:00.000Z",
+              "createdAtHeight": "1000",
+              "sumOpen": "1",
+              "sumClose": "0.5",
+              "netFunding": "0.01",
+              "unrealizedPnl": "3.5",
+              "closedAt": "2024-01-02T00:00:00.000Z",
+              "exitPrice": "51000"
             }
           },
           "assetPositions": {
             "property1": {
               "symbol": "string",
               "side": "LONG",
-              "size": "string",
+              "size": "900",
               "assetId": "string"
             },
             "property2": {
               "symbol": "string",
               "side": "LONG",
-              "size": "string",
+              "size": "900",
               "assetId": "string"
             }
           },
@@ -461,60 +1566,128 @@ mod tests {
 
         let string = "string".to_string();
         let string_option = Some("string".to_string());
+        let decimal = |value: &str| value.parse::<Decimal>().unwrap();
+        let datetime = |value: &str| value.parse::<DateTime<Utc>>().unwrap();
 
         let check_open_perpetual_position = |p: PerpetualPositionResponseStruct| {
             assert_eq!(p.market, string);
             assert_eq!(p.status, Some(PerpetualPositionStatus::OPEN));
             assert_eq!(p.side, Some(PositionSide::LONG));
-            assert_eq!(p.size, string);
-            assert_eq!(p.max_size, string);
-            assert_eq!(p.entry_price, string);
-            assert_eq!(p.realized_pnl, string);
-            assert_eq!(p.created_at, string);
-            assert_eq!(p.created_at_height, string);
-            assert_eq!(p.sum_open, string);
-            assert_eq!(p.sum_close, string);
-            assert_eq!(p.net_funding, string);
-            assert_eq!(p.unrealized_pnl, string);
-            assert_eq!(p.closed_at, string_option);
-            assert_eq!(p.exit_price, string_option);
+            assert_eq!(p.size, decimal("0.5"));
+            assert_eq!(p.max_size, decimal("1"));
+            assert_eq!(p.entry_price, decimal("50000.5"));
+            assert_eq!(p.realized_pnl, decimal("-12.34"));
+            assert_eq!(p.created_at, datetime("2024-01-01T00:00:00Z"));
+            assert_eq!(p.created_at_height, 1000);
+            assert_eq!(p.sum_open, decimal("1"));
+            assert_eq!(p.sum_close, decimal("0.5"));
+            assert_eq!(p.net_funding, decimal("0.01"));
+            assert_eq!(p.unrealized_pnl, decimal("3.5"));
+            assert_eq!(p.closed_at, Some(datetime("2024-01-02T00:00:00Z")));
+            assert_eq!(p.exit_price, Some(decimal("51000")));
         };
 
         let check_asset_position = |a: AssetPositionResponseStruct| {
             assert_eq!(a.symbol, string);
             assert_eq!(a.side, Some(PositionSide::LONG));
-            assert_eq!(a.size, string);
+            assert_eq!(a.size, decimal("900"));
             assert_eq!(a.asset_id, string_option);
         };
 
         let first = parsed.first().unwrap();
-        assert_eq!(first.margin_enabled, true);
+        assert!(first.margin_enabled);
         assert_eq!(first.address, string);
         assert_eq!(first.subaccount_number, 0);
-        assert_eq!(first.equity, string);
+        assert_eq!(first.equity, decimal("1000.25"));
+        assert_eq!(first.free_collateral, decimal("900"));
 
         println!("{:?}", first);
         let open_perpetual_positions_option = first.open_perpetual_positions.clone();
 
-        assert_eq!(open_perpetual_positions_option.is_some(), true);
+        assert!(open_perpetual_positions_option.is_some());
 
         let open_perpetual_position = open_perpetual_positions_option.unwrap();
         assert_eq!(open_perpetual_position.len(), 2);
 
         let first_opp = open_perpetual_position.values().next().unwrap();
-        let second_opp = open_perpetual_position.values().skip(1).next().unwrap();
+        let second_opp = open_perpetual_position.values().nth(1).unwrap();
         check_open_perpetual_position(first_opp.to_owned());
         check_open_perpetual_position(second_opp.to_owned());
 
         let asset_positions_option = first.asset_positions.clone();
-        assert_eq!(asset_positions_option.is_some(), true);
+        assert!(asset_positions_option.is_some());
 
         let asset_positions = asset_positions_option.unwrap();
         assert_eq!(asset_positions.len(), 2);
 
         let first_ap = asset_positions.values().next().unwrap();
-        let second_ap = asset_positions.values().skip(1).next().unwrap();
+        let second_ap = asset_positions.values().nth(1).unwrap();
         check_asset_position(first_ap.to_owned());
         check_asset_position(second_ap.to_owned());
     }
+
+    #[test]
+    fn test_response_iteration() {
+        let json = r#"{"trades":[
+            {"id":"1","side":"BUY","size":"0.1","price":"50000","createdAt":"2024-01-01T00:00:00.000Z","createdAtHeight":"10"},
+            {"id":"2","side":"SELL","size":"0.2","price":"50001","createdAt":"2024-01-01T00:00:01.000Z","createdAtHeight":"11"}
+        ]}"#;
+        let trades: TradeResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(trades.len(), 2);
+        assert_eq!(
+            (&trades)
+                .into_iter()
+                .map(|t| t.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["1", "2"]
+        );
+        let heights: Vec<u64> = trades.iter().map(|t| t.created_at_height).collect();
+        assert_eq!(heights, vec![10, 11]);
+        let sizes: Vec<Decimal> = trades.into_iter().map(|t| t.size).collect();
+        assert_eq!(sizes, vec![Decimal::new(1, 1), Decimal::new(2, 1)]);
+
+        let json =
+            r#"{"bids":[{"price":"49999","size":"1"}],"asks":[{"price":"50001","size":"2"}]}"#;
+        let orderbook: OrderbookResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(orderbook.bids[0].price, Decimal::from(49_999));
+        assert_eq!(
+            orderbook.asks.iter().map(|l| l.size).collect::<Vec<_>>(),
+            vec![Decimal::TWO]
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_time_in_force_accepts_the_former_name() {
+        let gtt: OrderTimeInForce = serde_json::from_str(r#""GIT""#).unwrap();
+        assert_eq!(gtt, OrderTimeInForce::GTT);
+        assert_eq!(OrderTimeInForce::GIT, OrderTimeInForce::GTT);
+        assert_eq!(serde_json::to_string(&gtt).unwrap(), r#""GTT""#);
+    }
+
+    #[test]
+    fn test_utility_and_vault_response_deserialization() {
+        let height: HeightResponse =
+            serde_json::from_str(r#"{"height":"12345","time":"2024-01-01T00:00:00.000Z"}"#)
+                .unwrap();
+        assert_eq!(height.height, 12345);
+
+        let time: TimeResponse =
+            serde_json::from_str(r#"{"iso":"2024-01-01T00:00:00.000Z","epoch":1704067200.5}"#)
+                .unwrap();
+        assert_eq!(time.iso.timestamp(), 1704067200);
+
+        let rewards: HistoricalTradingRewardAggregationsResponse = serde_json::from_str(
+            r#"{"rewards":[{"tradingReward":"1.5","startedAt":"2024-01-01T00:00:00.000Z","startedAtHeight":"100","endedAt":null,"endedAtHeight":null,"period":"DAILY"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(rewards.len(), 1);
+        assert_eq!(rewards.rewards[0].ended_at_height, None);
+
+        let pnl: MegavaultHistoricalPnLResponse = serde_json::from_str(
+            r#"{"megavaultPnl":[{"id":"1","subaccountId":"s","equity":"1000","totalPnl":"10","netTransfers":"990","createdAt":"2024-01-01T00:00:00.000Z","blockHeight":"100","blockTime":"2024-01-01T00:00:00.000Z"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(pnl.megavault_pnl[0].block_height, 100);
+    }
 }
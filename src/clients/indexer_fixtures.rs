@@ -0,0 +1,276 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+
+use super::errors::{APIError, FixtureError};
+use super::indexer_client::decode_body;
+
+/// Canned indexer responses, served by an [`IndexerClient`] created with
+/// [`IndexerClient::from_fixtures`] instead of going over HTTP.
+///
+/// Responses are keyed by request path, e.g. `/v4/perpetualMarkets`. A key
+/// that includes the query string, e.g. `/v4/perpetualMarkets?ticker=BTC-USD`,
+/// only matches that exact request and takes precedence over the bare path.
+///
+/// [`IndexerClient`]: super::indexer_client::IndexerClient
+/// [`IndexerClient::from_fixtures`]: super::indexer_client::IndexerClient::from_fixtures
+#[derive(Clone, Default, Debug)]
+pub struct IndexerFixtures {
+    responses: HashMap<String, String>,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl IndexerFixtures {
+    pub fn new() -> Self {
+        IndexerFixtures::default()
+    }
+
+    /// Adds the JSON body returned for `path`.
+    pub fn with_response(mut self, path: &str, body: &str) -> Self {
+        self.insert_response(path, body);
+        self
+    }
+
+    pub fn insert_response(&mut self, path: &str, body: &str) {
+        self.responses.insert(path.to_string(), body.to_string());
+    }
+
+    /// Loads the responses saved by a [`FixtureRecorder`] in `dir`, from
+    /// every `.json` file it holds.
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self, FixtureError> {
+        let dir = dir.as_ref();
+        let entries = fs::read_dir(dir).map_err(|source| FixtureError::Io {
+            path: dir.to_path_buf(),
+            source,
+        })?;
+
+        let mut fixtures = IndexerFixtures::new();
+        for entry in entries {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(source) => {
+                    return Err(FixtureError::Io {
+                        path: dir.to_path_buf(),
+                        source,
+                    })
+                }
+            };
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+
+            let file = fs::read_to_string(&path).map_err(|source| FixtureError::Io {
+                path: path.clone(),
+                source,
+            })?;
+            let fixture: RecordedResponse =
+                serde_json::from_str(&file).map_err(|e| FixtureError::Parse {
+                    path: path.clone(),
+                    message: e.to_string(),
+                })?;
+            fixtures.insert_response(&fixture.request, fixture.body.get());
+        }
+        Ok(fixtures)
+    }
+
+    /// Requests served so far, including their query string, oldest first.
+    pub fn requests(&self) -> Vec<String> {
+        match self.requests.lock() {
+            Ok(requests) => requests.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    pub(crate) fn get<T>(&self, path: &str, query_string: &str) -> Result<T, APIError>
+    where
+        T: for<'a> Deserialize<'a>,
+    {
+        let request = request_key(path, query_string);
+
+        let body = match self
+            .responses
+            .get(&request)
+            .or_else(|| self.responses.get(path))
+        {
+            Some(body) => body,
+            None => {
+                return Err(APIError::Transport(format!(
+                    "No fixture for request: {request}"
+                )))
+            }
+        };
+
+        match self.requests.lock() {
+            Ok(mut requests) => requests.push(request),
+            Err(poisoned) => poisoned.into_inner().push(request),
+        }
+
+        decode_body(body.clone())
+    }
+}
+
+/// Saves every successful response of an [`IndexerClient`] to a file in a
+/// directory, to be replayed offline with [`IndexerFixtures::load`].
+/// Installed with [`IndexerClient::with_recorder`].
+///
+/// Each request is saved to its own file, named after its path and query
+/// string; recording a request again overwrites it.
+///
+/// [`IndexerClient`]: super::indexer_client::IndexerClient
+/// [`IndexerClient::with_recorder`]: super::indexer_client::IndexerClient::with_recorder
+#[derive(Clone, Debug)]
+pub struct FixtureRecorder {
+    dir: PathBuf,
+}
+
+impl FixtureRecorder {
+    /// Creates `dir` if it does not exist.
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self, FixtureError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).map_err(|source| FixtureError::Io {
+            path: dir.clone(),
+            source,
+        })?;
+        Ok(FixtureRecorder { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub(crate) fn record(&self, request: &str, body: &str) -> Result<(), FixtureError> {
+        let body =
+            RawValue::from_string(body.to_string()).map_err(|e| FixtureError::InvalidResponse {
+                request: request.to_string(),
+                message: e.to_string(),
+            })?;
+        let fixture = RecordedResponse {
+            request: request.to_string(),
+            body,
+        };
+        let file = match serde_json::to_string_pretty(&fixture) {
+            Ok(file) => file,
+            Err(e) => {
+                return Err(FixtureError::InvalidResponse {
+                    request: request.to_string(),
+                    message: e.to_string(),
+                })
+            }
+        };
+
+        let path = self.dir.join(file_name(request));
+        fs::write(&path, file + "\n").map_err(|source| FixtureError::Io { path, source })
+    }
+}
+
+/// The content of a fixture file.
+#[derive(Deserialize, Serialize)]
+struct RecordedResponse {
+    /// Path and query string, e.g. `/v4/perpetualMarkets?ticker=BTC-USD`.
+    request: String,
+    body: Box<RawValue>,
+}
+
+/// The key a request is recorded and served under.
+pub(crate) fn request_key(path: &str, query_string: &str) -> String {
+    if query_string.is_empty() {
+        path.to_string()
+    } else {
+        format!("{path}?{query_string}")
+    }
+}
+
+/// A readable prefix of the request, made unique by its FNV-1a hash.
+fn file_name(request: &str) -> String {
+    let readable: String = request
+        .trim_start_matches('/')
+        .chars()
+        .take(80)
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let hash = request.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    });
+    format!("{readable}-{hash:016x}.json")
+}
+
+// ========================================
+// Tests
+// ========================================
+
+#[cfg(test)]
+mod tests {
+    use maybe_async::maybe_async;
+
+    use super::super::indexer_client::{AccountsClient, IndexerClient, MarketsClient};
+    use super::super::indexer_client_types::{PerpetualMarketsRequest, SparklinesRequest};
+    use super::*;
+    use crate::constants::TimePeriod;
+
+    const MARKETS: &str = r#"{"markets":{"BTC-USD":{"clobPairId":"0","ticker":"BTC-USD","status":"ACTIVE","lastPrice":"50000","oraclePrice":"50001","priceChange24H":"0","volume24H":"0","trades24H":0,"nextFundingRate":"0","initialMarginFraction":"0.05","maintenanceMarginFraction":"0.03","basePositionNotional":"0","openInterest":"0","atomicResolution":-10,"quantumConversionExponent":-9,"tickSize":"1","stepSize":"0.0001","stepBaseQuantums":1000000,"subticksPerTick":100000}}}"#;
+
+    #[maybe_async]
+    async fn market_count(client: &dyn MarketsClient) -> usize {
+        match client
+            .get_perpetual_markets(PerpetualMarketsRequest::new())
+            .await
+        {
+            Ok(response) => response.markets.len(),
+            Err(_) => 0,
+        }
+    }
+
+    #[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+    async fn test_fixtures_serve_trait_objects() {
+        let fixtures = IndexerFixtures::new()
+            .with_response("/v4/perpetualMarkets", MARKETS)
+            .with_response("/v4/perpetualMarkets?ticker=ETH-USD", r#"{"markets":{}}"#)
+            .with_response("/v4/sparklines", r#"{"BTC-USD":[true]}"#);
+        let client = IndexerClient::from_fixtures(fixtures.clone());
+
+        let count = market_count(&client).await;
+        assert_eq!(count, 1);
+
+        let response = client
+            .get_perpetual_markets(PerpetualMarketsRequest::new().ticker("ETH-USD".to_string()))
+            .await
+            .unwrap();
+        assert!(response.markets.is_empty());
+
+        let accounts: &dyn AccountsClient = &client;
+        let missing = accounts.get_order("order-id".to_string()).await;
+        assert!(missing.is_err());
+        let invalid = client
+            .get_perpetual_market_sparklines(SparklinesRequest::new(TimePeriod::OneDay))
+            .await;
+        assert!(invalid.is_err());
+
+        let requests = fixtures.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0], "/v4/perpetualMarkets");
+        assert_eq!(requests[1], "/v4/perpetualMarkets?ticker=ETH-USD");
+        assert!(requests[2].starts_with("/v4/sparklines?"));
+    }
+
+    #[test]
+    fn test_load_errors_tell_io_from_parse() {
+        let dir = std::env::temp_dir().join(format!("dydx-bad-fixtures-{}", std::process::id()));
+        let missing = IndexerFixtures::load(&dir);
+        assert!(matches!(missing, Err(FixtureError::Io { .. })));
+
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("broken.json"), "{").unwrap();
+        let broken = IndexerFixtures::load(&dir);
+        let _ = fs::remove_dir_all(&dir);
+        match broken {
+            Err(FixtureError::Parse { path, .. }) => assert_eq!(path, dir.join("broken.json")),
+            other => panic!("Unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,479 @@
+use std::collections::{BTreeMap, HashSet, VecDeque};
+
+use rust_decimal::Decimal;
+
+use super::indexer_client_types::{
+    FillResponseStruct, HistoricalFundingResponseStruct, PerpetualPositionResponseStruct,
+    PositionSide,
+};
+use crate::constants::OrderSide;
+
+/// How closing fills are matched against the fills that opened a position.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CostBasis {
+    /// Close the oldest open lot first.
+    Fifo,
+    /// Close against the average entry price, as the indexer does.
+    AverageCost,
+}
+
+/// Part of a position opened by one fill, or all of it under average cost.
+#[derive(Clone, PartialEq, Debug)]
+struct Lot {
+    /// Signed, short lots negative.
+    size: Decimal,
+    price: Decimal,
+}
+
+/// The stretch of a market's history from opening a position until it is
+/// closed or flipped to the other side, which the indexer reports as one
+/// perpetual position.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PositionSegment {
+    /// Height of the fill that opened it, the indexer's `created_at_height`.
+    pub opened_at_height: u64,
+    /// Signed, short positions negative, zero once closed.
+    pub size: Decimal,
+    /// Entry price while open, kept as it was when closed.
+    pub entry_price: Decimal,
+    pub realized_pnl: Decimal,
+    pub fees: Decimal,
+    pub net_funding: Decimal,
+}
+
+impl PositionSegment {
+    fn new(opened_at_height: u64) -> Self {
+        PositionSegment {
+            opened_at_height,
+            size: Decimal::ZERO,
+            entry_price: Decimal::ZERO,
+            realized_pnl: Decimal::ZERO,
+            fees: Decimal::ZERO,
+            net_funding: Decimal::ZERO,
+        }
+    }
+}
+
+/// A perpetual position reconstructed from its fills and funding. The
+/// totals cover the market's whole history, see
+/// [`PositionLedger::segments`] for each position in it.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PositionLedger {
+    pub market: String,
+    /// Signed, short positions negative.
+    pub size: Decimal,
+    /// PnL of closed size, fees and funding aside.
+    pub realized_pnl: Decimal,
+    /// Fees paid, maker rebates negative.
+    pub fees: Decimal,
+    /// Funding received, payments negative, as the indexer's `net_funding`.
+    pub net_funding: Decimal,
+    lots: VecDeque<Lot>,
+    segments: Vec<PositionSegment>,
+}
+
+impl PositionLedger {
+    fn new(market: String) -> Self {
+        PositionLedger {
+            market,
+            size: Decimal::ZERO,
+            realized_pnl: Decimal::ZERO,
+            fees: Decimal::ZERO,
+            net_funding: Decimal::ZERO,
+            lots: VecDeque::new(),
+            segments: Vec::new(),
+        }
+    }
+
+    /// Positions held in the market, oldest first.
+    pub fn segments(&self) -> &[PositionSegment] {
+        &self.segments
+    }
+
+    /// The open position, or the last one if the market is flat.
+    pub fn current_segment(&self) -> Option<&PositionSegment> {
+        self.segments.last()
+    }
+
+    /// Average price of the open lots, zero when flat.
+    pub fn entry_price(&self) -> Decimal {
+        if self.size.is_zero() {
+            return Decimal::ZERO;
+        }
+        let cost: Decimal = self.lots.iter().map(|lot| lot.size * lot.price).sum();
+        cost / self.size
+    }
+
+    pub fn funding_paid(&self) -> Decimal {
+        -self.net_funding
+    }
+
+    /// Realized PnL net of fees and funding.
+    pub fn net_realized_pnl(&self) -> Decimal {
+        self.realized_pnl + self.net_funding - self.fees
+    }
+
+    fn fill(
+        &mut self,
+        size: Decimal,
+        price: Decimal,
+        fee: Decimal,
+        height: u64,
+        cost_basis: CostBasis,
+    ) {
+        if self.size.is_zero() {
+            self.segments.push(PositionSegment::new(height));
+        }
+
+        let mut realized_pnl = Decimal::ZERO;
+        let mut remaining = size;
+        // Close open lots of the other side first.
+        while !remaining.is_zero() {
+            let Some(lot) = self.lots.front_mut() else {
+                break;
+            };
+            if lot.size.is_sign_positive() == remaining.is_sign_positive() {
+                break;
+            }
+            let closed = if lot.size.abs() <= remaining.abs() {
+                lot.size
+            } else {
+                -remaining
+            };
+            realized_pnl += closed * (price - lot.price);
+            lot.size -= closed;
+            remaining += closed;
+            if lot.size.is_zero() {
+                self.lots.pop_front();
+            }
+        }
+
+        if !remaining.is_zero() {
+            match (cost_basis, self.lots.front_mut()) {
+                (CostBasis::AverageCost, Some(lot)) => {
+                    let total = lot.size + remaining;
+                    lot.price = (lot.size * lot.price + remaining * price) / total;
+                    lot.size = total;
+                }
+                _ => self.lots.push_back(Lot {
+                    size: remaining,
+                    price,
+                }),
+            }
+        }
+
+        let previous = self.size;
+        self.size += size;
+        self.realized_pnl += realized_pnl;
+        self.fees += fee;
+
+        let flipped = !previous.is_zero()
+            && !self.size.is_zero()
+            && previous.is_sign_negative() != self.size.is_sign_negative();
+        let entry_price = self.entry_price();
+        let Some(segment) = self.segments.last_mut() else {
+            return;
+        };
+        segment.realized_pnl += realized_pnl;
+        segment.fees += fee;
+        if flipped {
+            segment.size = Decimal::ZERO;
+            let mut opened = PositionSegment::new(height);
+            opened.size = self.size;
+            opened.entry_price = entry_price;
+            self.segments.push(opened);
+        } else {
+            segment.size = self.size;
+            if !self.size.is_zero() {
+                segment.entry_price = entry_price;
+            }
+        }
+    }
+}
+
+/// A field of a [`PerpetualPositionResponseStruct`] that differs from the
+/// ledger by more than the tolerance.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Discrepancy {
+    pub market: String,
+    pub field: &'static str,
+    pub indexer: Decimal,
+    pub ledger: Decimal,
+}
+
+/// Replays fill and funding history into per market positions, to audit
+/// what the indexer reports.
+///
+/// Events must be applied oldest first; [`Ledger::replay`] sorts them. Fills
+/// already applied are skipped by id.
+#[derive(Clone, Debug)]
+pub struct Ledger {
+    cost_basis: CostBasis,
+    positions: BTreeMap<String, PositionLedger>,
+    fill_ids: HashSet<String>,
+}
+
+impl Ledger {
+    pub fn new(cost_basis: CostBasis) -> Self {
+        Ledger {
+            cost_basis,
+            positions: BTreeMap::new(),
+            fill_ids: HashSet::new(),
+        }
+    }
+
+    pub fn cost_basis(&self) -> CostBasis {
+        self.cost_basis
+    }
+
+    pub fn position(&self, market: &str) -> Option<&PositionLedger> {
+        self.positions.get(market)
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = &PositionLedger> {
+        self.positions.values()
+    }
+
+    /// Applies `fills` and `funding`, e.g. as returned by
+    /// [`HistoryPager`](super::pagination::HistoryPager), in time order. A
+    /// funding payment at the same time as a fill is applied first.
+    pub fn replay(
+        &mut self,
+        fills: &[FillResponseStruct],
+        funding: &[HistoricalFundingResponseStruct],
+    ) {
+        let mut fills: Vec<_> = fills.iter().collect();
+        fills.sort_by_key(|fill| (fill.created_at_height, fill.created_at));
+        let mut funding: Vec<_> = funding.iter().collect();
+        funding.sort_by_key(|funding| (funding.effective_at_height, funding.effective_at));
+
+        let mut funding = funding.into_iter().peekable();
+        for fill in fills {
+            while let Some(payment) =
+                funding.next_if(|payment| payment.effective_at <= fill.created_at)
+            {
+                self.apply_funding(payment);
+            }
+            self.apply_fill(fill);
+        }
+        for payment in funding {
+            self.apply_funding(payment);
+        }
+    }
+
+    pub fn apply_fill(&mut self, fill: &FillResponseStruct) {
+        if !self.fill_ids.insert(fill.id.clone()) {
+            return;
+        }
+        let size = match fill.side {
+            OrderSide::BUY => fill.size,
+            OrderSide::SELL => -fill.size,
+        };
+        let cost_basis = self.cost_basis;
+        self.position_mut(&fill.market).fill(
+            size,
+            fill.price,
+            fill.fee,
+            fill.created_at_height,
+            cost_basis,
+        );
+    }
+
+    /// Applies a funding tick to the position held in its market at that
+    /// time: longs pay a positive rate, shorts receive it.
+    pub fn apply_funding(&mut self, funding: &HistoricalFundingResponseStruct) {
+        if let Some(position) = self.positions.get(&funding.ticker) {
+            let received = -position.size * funding.price * funding.rate;
+            self.apply_funding_payment(&funding.ticker, received);
+        }
+    }
+
+    /// Adds a funding payment known by amount, received positive.
+    pub fn apply_funding_payment(&mut self, market: &str, received: Decimal) {
+        let position = self.position_mut(market);
+        position.net_funding += received;
+        if let Some(segment) = position.segments.last_mut() {
+            segment.net_funding += received;
+        }
+    }
+
+    /// Compares the ledger with a position read from the indexer, open or
+    /// closed. It is matched to the segment opened at its
+    /// `created_at_height`, or to the current segment if none was. Under
+    /// [`CostBasis::Fifo`] the entry price is expected to differ after a
+    /// partial close, since the indexer uses average cost.
+    pub fn reconcile(
+        &self,
+        position: &PerpetualPositionResponseStruct,
+        tolerance: Decimal,
+    ) -> Vec<Discrepancy> {
+        let empty = PositionSegment::new(position.created_at_height);
+        let segments = self
+            .positions
+            .get(&position.market)
+            .map(|ledger| ledger.segments())
+            .unwrap_or_default();
+        let segment = segments
+            .iter()
+            .rev()
+            .find(|segment| segment.opened_at_height == position.created_at_height)
+            .or(segments.last())
+            .unwrap_or(&empty);
+        let size = match position.side {
+            Some(PositionSide::SHORT) => -position.size.abs(),
+            _ => position.size,
+        };
+
+        [
+            ("size", size, segment.size),
+            ("entry_price", position.entry_price, segment.entry_price),
+            ("realized_pnl", position.realized_pnl, segment.realized_pnl),
+            ("net_funding", position.net_funding, segment.net_funding),
+        ]
+        .into_iter()
+        .filter(|(_, indexer, ledger)| (indexer - ledger).abs() > tolerance)
+        .map(|(field, indexer, ledger)| Discrepancy {
+            market: position.market.clone(),
+            field,
+            indexer,
+            ledger,
+        })
+        .collect()
+    }
+
+    fn position_mut(&mut self, market: &str) -> &mut PositionLedger {
+        self.positions
+            .entry(market.to_string())
+            .or_insert_with(|| PositionLedger::new(market.to_string()))
+    }
+}
+
+// ========================================
+// Tests
+// ========================================
+
+#[cfg(test)]
+mod tests {
+    use super::super::indexer_client_types::FillType;
+    use super::*;
+
+    fn decimal(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn fill(id: &str, height: u64, side: &str, size: &str, price: &str) -> FillResponseStruct {
+        typed_fill(id, height, side, size, price, "LIMIT")
+    }
+
+    fn typed_fill(
+        id: &str,
+        height: u64,
+        side: &str,
+        size: &str,
+        price: &str,
+        fill_type: &str,
+    ) -> FillResponseStruct {
+        serde_json::from_str(&format!(
+            r#"{{"id":"{id}","side":"{side}","liquidity":"TAKER","type":"{fill_type}","market":"BTC-USD","marketType":"PERPETUAL","price":"{price}","size":"{size}","fee":"0.1","createdAt":"2024-01-01T00:0{height}:00.000Z","createdAtHeight":"{height}","orderId":"o{id}","clientMetadata":"0"}}"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_replays_fills_and_funding() {
+        let fills = [
+            fill("3", 3, "SELL", "1.5", "120"),
+            fill("1", 1, "BUY", "1", "100"),
+            fill("2", 2, "BUY", "1", "110"),
+            fill("4", 4, "SELL", "1", "100"),
+        ];
+        let funding: HistoricalFundingResponseStruct = serde_json::from_str(
+            r#"{"ticker":"BTC-USD","rate":"0.001","price":"100","effectiveAt":"2024-01-01T00:02:30.000Z","effectiveAtHeight":"2"}"#,
+        )
+        .unwrap();
+
+        let mut fifo = Ledger::new(CostBasis::Fifo);
+        fifo.replay(&fills[..3], std::slice::from_ref(&funding));
+        let position = fifo.position("BTC-USD").unwrap();
+        assert_eq!(position.size, decimal("0.5"));
+        assert_eq!(position.entry_price(), decimal("110"));
+        assert_eq!(position.realized_pnl, decimal("25"));
+        assert_eq!(position.funding_paid(), decimal("0.2"));
+        assert_eq!(position.fees, decimal("0.3"));
+
+        let mut average = Ledger::new(CostBasis::AverageCost);
+        average.replay(&fills, &[funding]);
+        average.apply_fill(&fills[0]);
+        let position = average.position("BTC-USD").unwrap();
+        assert_eq!(position.size, decimal("-0.5"));
+        assert_eq!(position.entry_price(), decimal("100"));
+        assert_eq!(position.realized_pnl, decimal("20"));
+        assert_eq!(position.net_realized_pnl(), decimal("19.4"));
+
+        let indexer: PerpetualPositionResponseStruct = serde_json::from_str(
+            r#"{"market":"BTC-USD","status":"OPEN","side":"SHORT","size":"-0.5","maxSize":"0.5","entryPrice":"100","realizedPnl":"0","createdAt":"2024-01-01T00:04:00.000Z","createdAtHeight":"4","sumOpen":"0.5","sumClose":"0","netFunding":"-0.05","unrealizedPnl":"0","closedAt":null,"exitPrice":null}"#,
+        )
+        .unwrap();
+        let discrepancies = average.reconcile(&indexer, decimal("0.01"));
+        assert_eq!(
+            discrepancies,
+            vec![Discrepancy {
+                market: "BTC-USD".to_string(),
+                field: "net_funding",
+                indexer: decimal("-0.05"),
+                ledger: decimal("0"),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_reconciles_a_reopened_position() {
+        let fills = [
+            fill("1", 1, "BUY", "1", "100"),
+            fill("2", 2, "SELL", "1", "110"),
+            fill("3", 3, "BUY", "2", "120"),
+        ];
+        let mut ledger = Ledger::new(CostBasis::AverageCost);
+        ledger.replay(&fills, &[]);
+
+        let position = ledger.position("BTC-USD").unwrap();
+        assert_eq!(position.realized_pnl, decimal("10"));
+        assert_eq!(position.segments().len(), 2);
+        let current = position.current_segment().unwrap();
+        assert_eq!(current.opened_at_height, 3);
+        assert_eq!(current.realized_pnl, Decimal::ZERO);
+        assert_eq!(current.fees, decimal("0.1"));
+
+        let open: PerpetualPositionResponseStruct = serde_json::from_str(
+            r#"{"market":"BTC-USD","status":"OPEN","side":"LONG","size":"2","maxSize":"2","entryPrice":"120","realizedPnl":"0","createdAt":"2024-01-01T00:03:00.000Z","createdAtHeight":"3","sumOpen":"2","sumClose":"0","netFunding":"0","unrealizedPnl":"0","closedAt":null,"exitPrice":null}"#,
+        )
+        .unwrap();
+        let reconciled = ledger.reconcile(&open, decimal("0.01"));
+        assert_eq!(reconciled, vec![]);
+
+        let closed: PerpetualPositionResponseStruct = serde_json::from_str(
+            r#"{"market":"BTC-USD","status":"CLOSED","side":"LONG","size":"0","maxSize":"1","entryPrice":"100","realizedPnl":"10","createdAt":"2024-01-01T00:01:00.000Z","createdAtHeight":"1","sumOpen":"1","sumClose":"1","netFunding":"0","unrealizedPnl":"0","closedAt":"2024-01-01T00:02:00.000Z","exitPrice":"110"}"#,
+        )
+        .unwrap();
+        let reconciled = ledger.reconcile(&closed, decimal("0.01"));
+        assert_eq!(reconciled, vec![]);
+    }
+
+    #[test]
+    fn test_replays_deleveraged_fills() {
+        let fills = [
+            fill("1", 1, "BUY", "2", "100"),
+            typed_fill("2", 2, "SELL", "1.5", "90", "DELEVERAGED"),
+            typed_fill("3", 3, "SELL", "0.5", "95", "TWAP_SUBORDER"),
+        ];
+        assert_eq!(fills[1].fill_type, FillType::Deleveraged);
+        assert_eq!(fills[2].fill_type, FillType::Unknown);
+
+        let mut ledger = Ledger::new(CostBasis::Fifo);
+        ledger.replay(&fills, &[]);
+        let position = ledger.position("BTC-USD").unwrap();
+        assert_eq!(position.size, Decimal::ZERO);
+        assert_eq!(position.realized_pnl, decimal("-17.5"));
+        assert_eq!(position.fees, decimal("0.3"));
+    }
+}
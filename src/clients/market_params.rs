@@ -0,0 +1,372 @@
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+use super::errors::MarketParamsError;
+use super::indexer_client_types::PerpetualMarketResponseStruct;
+
+/// Atomic resolution of USDC, in which quote quantums are denominated.
+const QUOTE_QUANTUMS_ATOMIC_RESOLUTION: i32 = -6;
+
+/// Direction in which a price or size is rounded to the market's tick or
+/// step size.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Rounding {
+    Down,
+    Up,
+    /// Halves round up.
+    Nearest,
+}
+
+/// The parameters of a perpetual market needed to convert human readable
+/// prices and sizes to the subticks and quantums of an order, and back.
+///
+/// All conversions are exact decimal arithmetic.
+#[derive(Clone, PartialEq, Debug)]
+pub struct MarketParams {
+    ticker: String,
+    clob_pair_id: u32,
+    atomic_resolution: i32,
+    quantum_conversion_exponent: i32,
+    step_base_quantums: u64,
+    subticks_per_tick: u64,
+}
+
+impl TryFrom<&PerpetualMarketResponseStruct> for MarketParams {
+    type Error = MarketParamsError;
+
+    fn try_from(market: &PerpetualMarketResponseStruct) -> Result<Self, Self::Error> {
+        let clob_pair_id =
+            market
+                .clob_pair_id
+                .parse()
+                .map_err(|source| MarketParamsError::InvalidClobPairId {
+                    ticker: market.ticker.clone(),
+                    source,
+                })?;
+        MarketParams::new(
+            market.ticker.clone(),
+            clob_pair_id,
+            market.atomic_resolution,
+            market.quantum_conversion_exponent,
+            market.step_base_quantums,
+            market.subticks_per_tick,
+        )
+    }
+}
+
+impl MarketParams {
+    pub fn new(
+        ticker: String,
+        clob_pair_id: u32,
+        atomic_resolution: i32,
+        quantum_conversion_exponent: i32,
+        step_base_quantums: u64,
+        subticks_per_tick: u64,
+    ) -> Result<Self, MarketParamsError> {
+        if step_base_quantums == 0 || subticks_per_tick == 0 {
+            return Err(MarketParamsError::ZeroIncrement { ticker });
+        }
+        Ok(MarketParams {
+            ticker,
+            clob_pair_id,
+            atomic_resolution,
+            quantum_conversion_exponent,
+            step_base_quantums,
+            subticks_per_tick,
+        })
+    }
+
+    pub fn ticker(&self) -> &str {
+        &self.ticker
+    }
+
+    pub fn clob_pair_id(&self) -> u32 {
+        self.clob_pair_id
+    }
+
+    pub fn atomic_resolution(&self) -> i32 {
+        self.atomic_resolution
+    }
+
+    pub fn quantum_conversion_exponent(&self) -> i32 {
+        self.quantum_conversion_exponent
+    }
+
+    pub fn step_base_quantums(&self) -> u64 {
+        self.step_base_quantums
+    }
+
+    pub fn subticks_per_tick(&self) -> u64 {
+        self.subticks_per_tick
+    }
+
+    /// Smallest size increment, in base asset units.
+    pub fn step_size(&self) -> Result<Decimal, MarketParamsError> {
+        self.quantums_to_size(self.step_base_quantums)
+    }
+
+    /// Smallest price increment, in quote units.
+    pub fn tick_size(&self) -> Result<Decimal, MarketParamsError> {
+        self.subticks_to_price(self.subticks_per_tick)
+    }
+
+    /// Converts a size in base asset units to quantums, rounded to a
+    /// multiple of the step size. Sizes below one step are rejected
+    /// whatever the rounding.
+    pub fn size_to_quantums(
+        &self,
+        size: Decimal,
+        rounding: Rounding,
+    ) -> Result<u64, MarketParamsError> {
+        let raw_quantums = scale(size, -self.atomic_resolution)?;
+        if raw_quantums < Decimal::from(self.step_base_quantums) {
+            return Err(MarketParamsError::BelowStepSize {
+                ticker: self.ticker.clone(),
+                size,
+                step_size: self.step_size()?,
+            });
+        }
+        to_u64(round_to_multiple(
+            raw_quantums,
+            self.step_base_quantums,
+            rounding,
+        )?)
+    }
+
+    /// Converts quantums to a size in base asset units. Fails only if the
+    /// market's atomic resolution is out of the range `Decimal` can express.
+    pub fn quantums_to_size(&self, quantums: u64) -> Result<Decimal, MarketParamsError> {
+        Ok(scale(Decimal::from(quantums), self.atomic_resolution)?.normalize())
+    }
+
+    /// Converts a price in quote units to subticks, rounded to a multiple of
+    /// the tick size. Prices that round to zero are rejected.
+    pub fn price_to_subticks(
+        &self,
+        price: Decimal,
+        rounding: Rounding,
+    ) -> Result<u64, MarketParamsError> {
+        let raw_subticks = scale(price, self.subticks_exponent())?;
+        let subticks = round_to_multiple(raw_subticks, self.subticks_per_tick, rounding)?;
+        if subticks <= Decimal::ZERO {
+            return Err(MarketParamsError::BelowTickSize {
+                ticker: self.ticker.clone(),
+                price,
+                tick_size: self.tick_size()?,
+            });
+        }
+        to_u64(subticks)
+    }
+
+    /// Converts subticks to a price in quote units. Fails only if the
+    /// market's exponents are out of the range `Decimal` can express.
+    pub fn subticks_to_price(&self, subticks: u64) -> Result<Decimal, MarketParamsError> {
+        Ok(scale(Decimal::from(subticks), -self.subticks_exponent())?.normalize())
+    }
+
+    /// Rounds a size to a multiple of the step size.
+    pub fn round_size(
+        &self,
+        size: Decimal,
+        rounding: Rounding,
+    ) -> Result<Decimal, MarketParamsError> {
+        self.quantums_to_size(self.size_to_quantums(size, rounding)?)
+    }
+
+    /// Rounds a price to a multiple of the tick size.
+    pub fn round_price(
+        &self,
+        price: Decimal,
+        rounding: Rounding,
+    ) -> Result<Decimal, MarketParamsError> {
+        self.subticks_to_price(self.price_to_subticks(price, rounding)?)
+    }
+
+    /// Subticks per quote unit are `10^exponent`.
+    fn subticks_exponent(&self) -> i32 {
+        self.atomic_resolution - self.quantum_conversion_exponent - QUOTE_QUANTUMS_ATOMIC_RESOLUTION
+    }
+}
+
+/// `value * 10^exponent`, exactly.
+fn scale(value: Decimal, exponent: i32) -> Result<Decimal, MarketParamsError> {
+    let factor = match exponent.unsigned_abs() {
+        digits if digits <= Decimal::MAX_SCALE => {
+            Decimal::from_i128_with_scale(10i128.pow(digits), 0)
+        }
+        _ => return Err(MarketParamsError::ExponentOutOfRange(exponent)),
+    };
+    let scaled = if exponent >= 0 {
+        value.checked_mul(factor)
+    } else {
+        value.checked_div(factor)
+    };
+    scaled.ok_or(MarketParamsError::OutOfRange(value))
+}
+
+fn round_to_multiple(
+    value: Decimal,
+    multiple: u64,
+    rounding: Rounding,
+) -> Result<Decimal, MarketParamsError> {
+    let multiple = Decimal::from(multiple);
+    let remainder = value % multiple;
+    let down = value - remainder;
+    let up = if remainder.is_zero() {
+        Some(down)
+    } else {
+        down.checked_add(multiple)
+    };
+    let rounded = match rounding {
+        Rounding::Down => Some(down),
+        Rounding::Up => up,
+        Rounding::Nearest if remainder * Decimal::TWO >= multiple => up,
+        Rounding::Nearest => Some(down),
+    };
+    rounded.ok_or(MarketParamsError::OutOfRange(value))
+}
+
+fn to_u64(value: Decimal) -> Result<u64, MarketParamsError> {
+    value.to_u64().ok_or(MarketParamsError::OutOfRange(value))
+}
+
+// ========================================
+// Tests
+// ========================================
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    /// BTC-USD and ETH-USD on mainnet.
+    fn btc() -> MarketParams {
+        MarketParams::new("BTC-USD".to_string(), 0, -10, -9, 1_000_000, 100_000).unwrap()
+    }
+
+    fn eth() -> MarketParams {
+        MarketParams::new("ETH-USD".to_string(), 1, -9, -9, 1_000_000, 100_000).unwrap()
+    }
+
+    fn decimal(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_rounds_in_the_chosen_direction() {
+        let btc = btc();
+        assert_eq!(btc.step_size().unwrap(), decimal("0.0001"));
+        assert_eq!(btc.tick_size().unwrap(), decimal("1"));
+
+        let size = decimal("0.01005");
+        assert_eq!(
+            btc.size_to_quantums(size, Rounding::Down).unwrap(),
+            100_000_000
+        );
+        assert_eq!(
+            btc.size_to_quantums(size, Rounding::Up).unwrap(),
+            101_000_000
+        );
+        assert_eq!(
+            btc.size_to_quantums(size, Rounding::Nearest).unwrap(),
+            101_000_000
+        );
+        assert_eq!(
+            btc.size_to_quantums(decimal("0.00009"), Rounding::Up),
+            Err(MarketParamsError::BelowStepSize {
+                ticker: "BTC-USD".to_string(),
+                size: decimal("0.00009"),
+                step_size: decimal("0.0001"),
+            })
+        );
+
+        let price = decimal("50000.4");
+        assert_eq!(
+            btc.round_price(price, Rounding::Down).unwrap(),
+            decimal("50000")
+        );
+        assert_eq!(
+            btc.round_price(price, Rounding::Up).unwrap(),
+            decimal("50001")
+        );
+        assert_eq!(
+            btc.price_to_subticks(price, Rounding::Nearest).unwrap(),
+            5_000_000_000
+        );
+        assert!(matches!(
+            btc.price_to_subticks(decimal("0.4"), Rounding::Down),
+            Err(MarketParamsError::BelowTickSize { .. })
+        ));
+
+        let eth = eth();
+        assert_eq!(eth.tick_size().unwrap(), decimal("0.1"));
+        assert_eq!(
+            eth.price_to_subticks(decimal("2345.6"), Rounding::Down)
+                .unwrap(),
+            2_345_600_000
+        );
+    }
+
+    #[test]
+    fn test_out_of_range_conversions_fail() {
+        let market =
+            MarketParams::new("HUGE-USD".to_string(), 2, 40, -9, 1_000_000, 100_000).unwrap();
+        assert_eq!(
+            market.quantums_to_size(1),
+            Err(MarketParamsError::ExponentOutOfRange(40))
+        );
+        assert!(market.step_size().is_err());
+        assert!(market.subticks_to_price(1).is_err());
+        assert!(market.round_price(decimal("1"), Rounding::Down).is_err());
+
+        let btc = btc();
+        assert!(btc.quantums_to_size(u64::MAX).is_ok());
+        assert!(matches!(
+            btc.round_size(Decimal::MAX, Rounding::Up),
+            Err(MarketParamsError::OutOfRange(_))
+        ));
+        assert!(matches!(
+            MarketParams::new("BTC-USD".to_string(), 0, -10, -9, 0, 100_000),
+            Err(MarketParamsError::ZeroIncrement { .. })
+        ));
+    }
+
+    proptest! {
+        #[test]
+        fn test_quantums_round_trip(steps in 1u64..1_000_000_000, eth_market: bool) {
+            let market = if eth_market { eth() } else { btc() };
+            let quantums = steps * market.step_base_quantums();
+            let size = market.quantums_to_size(quantums).unwrap();
+            for rounding in [Rounding::Down, Rounding::Up, Rounding::Nearest] {
+                prop_assert_eq!(market.size_to_quantums(size, rounding).unwrap(), quantums);
+            }
+        }
+
+        #[test]
+        fn test_subticks_round_trip(ticks in 1u64..1_000_000_000, eth_market: bool) {
+            let market = if eth_market { eth() } else { btc() };
+            let subticks = ticks * market.subticks_per_tick();
+            let price = market.subticks_to_price(subticks).unwrap();
+            for rounding in [Rounding::Down, Rounding::Up, Rounding::Nearest] {
+                prop_assert_eq!(market.price_to_subticks(price, rounding).unwrap(), subticks);
+            }
+        }
+
+        #[test]
+        fn test_rounding_brackets_the_size(mantissa in 1i64..1_000_000_000_000, digits in 3u32..12) {
+            let market = btc();
+            let step_size = market.step_size().unwrap();
+            let size = Decimal::new(mantissa, digits);
+            prop_assume!(size >= step_size);
+
+            let down = market.round_size(size, Rounding::Down).unwrap();
+            let up = market.round_size(size, Rounding::Up).unwrap();
+            let nearest = market.round_size(size, Rounding::Nearest).unwrap();
+            prop_assert!(down <= size && size <= up);
+            prop_assert!(up - down == Decimal::ZERO || up - down == step_size);
+            prop_assert!(nearest == down || nearest == up);
+            prop_assert!((nearest - size).abs() <= step_size / Decimal::TWO);
+        }
+    }
+}
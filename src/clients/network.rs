@@ -0,0 +1,203 @@
+use std::time::Duration;
+
+use crate::constants::{
+    CHAIN_TOKEN_DECIMALS, LOCAL_CHAIN_ID, LOCAL_CHAIN_TOKEN_DENOM, LOCAL_GRPC_ENDPOINT,
+    LOCAL_INDEXER_REST_ENDPOINT, LOCAL_INDEXER_WEBSOCKET_ENDPOINT, MAINNET_CHAIN_ID,
+    MAINNET_CHAIN_TOKEN_DENOM, MAINNET_GRPC_ENDPOINT, MAINNET_INDEXER_REST_ENDPOINT,
+    MAINNET_INDEXER_WEBSOCKET_ENDPOINT, TESTNET_CHAIN_ID, TESTNET_CHAIN_TOKEN_DENOM,
+    TESTNET_GRPC_ENDPOINT, TESTNET_INDEXER_REST_ENDPOINT, TESTNET_INDEXER_WEBSOCKET_ENDPOINT,
+    USDC_ASSET_ID, USDC_DECIMALS, USDC_DENOM, USDC_GAS_PRICE,
+};
+
+use super::indexer_client::IndexerConfig;
+use super::validator_client::{DenomConfig, ValidatorConfig};
+
+/// Everything needed to talk to one dYdX deployment: the indexer, a full
+/// node and the chain's denominations.
+///
+/// Start from a preset and override what differs, e.g. a private full node
+/// with [`Network::with_grpc_endpoint`].
+#[derive(Clone)]
+pub struct Network {
+    indexer_config: IndexerConfig,
+    validator_config: ValidatorConfig,
+    usdc_asset_id: u32,
+}
+
+impl Network {
+    pub fn new(
+        indexer_config: IndexerConfig,
+        validator_config: ValidatorConfig,
+        usdc_asset_id: u32,
+    ) -> Self {
+        Network {
+            indexer_config,
+            validator_config,
+            usdc_asset_id,
+        }
+    }
+
+    pub fn mainnet() -> Self {
+        Network::preset(
+            MAINNET_INDEXER_REST_ENDPOINT,
+            MAINNET_INDEXER_WEBSOCKET_ENDPOINT,
+            MAINNET_GRPC_ENDPOINT,
+            MAINNET_CHAIN_ID,
+            MAINNET_CHAIN_TOKEN_DENOM,
+        )
+    }
+
+    pub fn testnet() -> Self {
+        Network::preset(
+            TESTNET_INDEXER_REST_ENDPOINT,
+            TESTNET_INDEXER_WEBSOCKET_ENDPOINT,
+            TESTNET_GRPC_ENDPOINT,
+            TESTNET_CHAIN_ID,
+            TESTNET_CHAIN_TOKEN_DENOM,
+        )
+    }
+
+    /// A devnet started from the dYdX protocol repository on this machine.
+    pub fn local() -> Self {
+        Network::preset(
+            LOCAL_INDEXER_REST_ENDPOINT,
+            LOCAL_INDEXER_WEBSOCKET_ENDPOINT,
+            LOCAL_GRPC_ENDPOINT,
+            LOCAL_CHAIN_ID,
+            LOCAL_CHAIN_TOKEN_DENOM,
+        )
+    }
+
+    fn preset(
+        rest_endpoint: &str,
+        websocket_endpoint: &str,
+        grpc_endpoint: &str,
+        chain_id: &str,
+        chain_token_denom: &str,
+    ) -> Self {
+        let denoms = DenomConfig::new(
+            USDC_DENOM.to_string(),
+            USDC_DECIMALS,
+            USDC_GAS_PRICE,
+            chain_token_denom.to_string(),
+            CHAIN_TOKEN_DECIMALS,
+        );
+        Network::new(
+            IndexerConfig::new(rest_endpoint.to_string(), websocket_endpoint.to_string()),
+            ValidatorConfig::new(grpc_endpoint.to_string(), chain_id.to_string(), denoms),
+            USDC_ASSET_ID,
+        )
+    }
+
+    pub fn with_indexer_endpoints(
+        mut self,
+        rest_endpoint: String,
+        websocket_endpoint: String,
+    ) -> Self {
+        self.indexer_config = IndexerConfig::new(rest_endpoint, websocket_endpoint);
+        self
+    }
+
+    pub fn with_grpc_endpoint(mut self, grpc_endpoint: String) -> Self {
+        self.validator_config = ValidatorConfig::new(
+            grpc_endpoint,
+            self.validator_config.chain_id().to_string(),
+            self.validator_config.denoms().clone(),
+        )
+        .with_timeout(self.validator_config.timeout());
+        self
+    }
+
+    pub fn with_chain_id(mut self, chain_id: String) -> Self {
+        self.validator_config = ValidatorConfig::new(
+            self.validator_config.grpc_endpoint().to_string(),
+            chain_id,
+            self.validator_config.denoms().clone(),
+        )
+        .with_timeout(self.validator_config.timeout());
+        self
+    }
+
+    pub fn with_denoms(mut self, denoms: DenomConfig) -> Self {
+        self.validator_config = ValidatorConfig::new(
+            self.validator_config.grpc_endpoint().to_string(),
+            self.validator_config.chain_id().to_string(),
+            denoms,
+        )
+        .with_timeout(self.validator_config.timeout());
+        self
+    }
+
+    /// Timeout for connecting to the full node and for each gRPC request.
+    pub fn with_grpc_timeout(mut self, timeout: Duration) -> Self {
+        self.validator_config = self.validator_config.with_timeout(timeout);
+        self
+    }
+
+    pub fn with_usdc_asset_id(mut self, usdc_asset_id: u32) -> Self {
+        self.usdc_asset_id = usdc_asset_id;
+        self
+    }
+
+    pub fn indexer_config(&self) -> &IndexerConfig {
+        &self.indexer_config
+    }
+
+    pub fn validator_config(&self) -> &ValidatorConfig {
+        &self.validator_config
+    }
+
+    pub fn chain_id(&self) -> &str {
+        self.validator_config.chain_id()
+    }
+
+    pub fn usdc_denom(&self) -> &str {
+        self.validator_config.denoms().usdc_denom()
+    }
+
+    pub fn usdc_asset_id(&self) -> u32 {
+        self.usdc_asset_id
+    }
+
+    pub fn gas_denom(&self) -> &str {
+        self.validator_config.denoms().gas_denom()
+    }
+}
+
+// ========================================
+// Tests
+// ========================================
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+
+    use super::*;
+
+    #[test]
+    fn test_overrides_keep_the_rest_of_the_preset() {
+        let testnet = Network::testnet();
+        assert_eq!(testnet.chain_id(), "dydx-testnet-4");
+        assert_eq!(testnet.gas_denom(), testnet.usdc_denom());
+        assert_eq!(testnet.usdc_asset_id(), 0);
+
+        let denoms = testnet
+            .validator_config()
+            .denoms()
+            .clone()
+            .with_gas_denom("adv4tnt".to_string(), Decimal::new(25_000_000_000, 0));
+        let network = testnet
+            .with_grpc_endpoint("http://127.0.0.1:9090".to_string())
+            .with_denoms(denoms);
+        assert_eq!(
+            network.validator_config().grpc_endpoint(),
+            "http://127.0.0.1:9090"
+        );
+        assert_eq!(network.chain_id(), "dydx-testnet-4");
+        assert_eq!(network.gas_denom(), "adv4tnt");
+        assert_eq!(
+            network.indexer_config().rest_endpoint(),
+            "https://indexer.v4testnet.dydx.exchange"
+        );
+    }
+}
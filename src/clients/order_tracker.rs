@@ -0,0 +1,778 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+#[cfg(feature = "blocking")]
+use std::sync::mpsc;
+
+#[cfg(not(feature = "blocking"))]
+use futures_channel::mpsc;
+
+use rust_decimal::Decimal;
+
+use super::indexer_client_types::{FillResponseStruct, OrderResponseStruct};
+use super::socket_client_types::{
+    ChannelBatchDataMessage, ChannelDataMessage, SocketMessage, SubaccountFillUpdate,
+    SubaccountOrderUpdate, SubaccountsUpdateContents, SubscribedMessage,
+};
+use crate::constants::{OrderSide, OrderStatus};
+
+const DEFAULT_MAX_PENDING_FILLS: usize = 1000;
+const DEFAULT_MAX_FINAL_ORDERS: usize = 1000;
+
+/// Receiver of [`OrderTracker::events`]: a [`Stream`](futures_util::Stream)
+/// of events, or an [`std::sync::mpsc::Receiver`] with the `blocking`
+/// feature.
+#[cfg(not(feature = "blocking"))]
+pub type OrderEvents = mpsc::UnboundedReceiver<OrderEvent>;
+#[cfg(feature = "blocking")]
+pub type OrderEvents = mpsc::Receiver<OrderEvent>;
+
+#[cfg(not(feature = "blocking"))]
+type OrderEventSender = mpsc::UnboundedSender<OrderEvent>;
+#[cfg(feature = "blocking")]
+type OrderEventSender = mpsc::Sender<OrderEvent>;
+
+/// A fill of a tracked order.
+#[derive(Clone, PartialEq, Debug)]
+pub struct OrderFill {
+    pub id: String,
+    pub size: Decimal,
+    pub price: Decimal,
+    pub fee: Option<Decimal>,
+}
+
+/// The latest known state of an order.
+///
+/// Order updates of the subaccounts channel only carry the fields that
+/// changed, so the fields of an order first seen through one may be missing.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TrackedOrder {
+    pub id: String,
+    pub client_id: Option<String>,
+    pub clob_pair_id: Option<String>,
+    pub order_flags: Option<String>,
+    pub ticker: Option<String>,
+    pub side: Option<OrderSide>,
+    pub size: Option<Decimal>,
+    pub price: Option<Decimal>,
+    pub status: OrderStatus,
+    /// The larger of the indexer's `total_filled` and the sum of `fills`.
+    pub total_filled: Decimal,
+    pub fills: Vec<OrderFill>,
+    pub removal_reason: Option<String>,
+}
+
+impl TrackedOrder {
+    /// The order's client id key, once all of its parts are known.
+    pub fn client_key(&self) -> Option<ClientOrderKey> {
+        Some(ClientOrderKey {
+            client_id: self.client_id.clone()?,
+            clob_pair_id: self.clob_pair_id.clone()?,
+            order_flags: self.order_flags.clone()?,
+        })
+    }
+
+    /// Whether the order can no longer change.
+    pub fn is_final(&self) -> bool {
+        is_final(&self.status)
+    }
+
+    pub fn is_partially_filled(&self) -> bool {
+        !self.total_filled.is_zero() && self.status != OrderStatus::Filled
+    }
+
+    pub fn remaining(&self) -> Option<Decimal> {
+        self.size
+            .map(|size| (size - self.total_filled).max(Decimal::ZERO))
+    }
+}
+
+/// Identifies an order of a subaccount by client id. As in the protocol's
+/// order ids, a client id is only unique within one market and one kind of
+/// order: short term, long term or conditional, told apart by the order
+/// flags.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ClientOrderKey {
+    pub client_id: String,
+    pub clob_pair_id: String,
+    pub order_flags: String,
+}
+
+impl ClientOrderKey {
+    pub fn new(client_id: &str, clob_pair_id: &str, order_flags: &str) -> Self {
+        ClientOrderKey {
+            client_id: client_id.to_string(),
+            clob_pair_id: clob_pair_id.to_string(),
+            order_flags: order_flags.to_string(),
+        }
+    }
+}
+
+/// A change of a tracked order.
+#[derive(Clone, PartialEq, Debug)]
+pub enum OrderEvent {
+    /// The order was seen for the first time or changed status.
+    StatusChanged {
+        order: TrackedOrder,
+        previous: Option<OrderStatus>,
+    },
+    /// More of the order was filled.
+    Filled { order: TrackedOrder, size: Decimal },
+    /// The indexer reported a status the order cannot move to from its
+    /// current one, e.g. `CANCELED` to `OPEN`. The status is not applied.
+    IllegalTransition {
+        order: TrackedOrder,
+        from: OrderStatus,
+        to: OrderStatus,
+    },
+}
+
+type OrderCallback = Box<dyn FnMut(&OrderEvent) + Send>;
+
+/// Follows the orders of one subaccount from REST reads and the subaccounts
+/// channel, keyed by order id and [`ClientOrderKey`].
+///
+/// Every change is returned by the method that caused it and published to
+/// the callbacks of [`OrderTracker::on_event`] and the receivers of
+/// [`OrderTracker::events`].
+///
+/// Fills that arrive before their order are held until it is seen, up to
+/// [`OrderTracker::with_max_pending_fills`]; past that the oldest are
+/// dropped. Final orders are kept up to
+/// [`OrderTracker::with_max_final_orders`]; past that the oldest to become
+/// final are removed, as by [`OrderTracker::remove`].
+pub struct OrderTracker {
+    orders: HashMap<String, TrackedOrder>,
+    client_ids: HashMap<ClientOrderKey, String>,
+    /// Fills of orders not seen yet, with their order id, oldest first.
+    pending_fills: VecDeque<(String, OrderFill)>,
+    max_pending_fills: usize,
+    /// Ids of the final orders, in the order they became final.
+    final_orders: VecDeque<String>,
+    max_final_orders: usize,
+    fill_ids: HashSet<String>,
+    callbacks: Vec<OrderCallback>,
+    senders: Vec<OrderEventSender>,
+}
+
+impl Default for OrderTracker {
+    fn default() -> Self {
+        OrderTracker {
+            orders: HashMap::new(),
+            client_ids: HashMap::new(),
+            pending_fills: VecDeque::new(),
+            max_pending_fills: DEFAULT_MAX_PENDING_FILLS,
+            final_orders: VecDeque::new(),
+            max_final_orders: DEFAULT_MAX_FINAL_ORDERS,
+            fill_ids: HashSet::new(),
+            callbacks: Vec::new(),
+            senders: Vec::new(),
+        }
+    }
+}
+
+impl OrderTracker {
+    pub fn new() -> Self {
+        OrderTracker::default()
+    }
+
+    /// Number of fills held for orders not seen yet, 1000 by default.
+    pub fn with_max_pending_fills(mut self, max_pending_fills: usize) -> Self {
+        self.max_pending_fills = max_pending_fills;
+        self
+    }
+
+    /// Number of final orders kept, 1000 by default.
+    pub fn with_max_final_orders(mut self, max_final_orders: usize) -> Self {
+        self.max_final_orders = max_final_orders;
+        self
+    }
+
+    pub fn pending_fills(&self) -> usize {
+        self.pending_fills.len()
+    }
+
+    /// Calls `callback` with every future event.
+    pub fn on_event(&mut self, callback: impl FnMut(&OrderEvent) + Send + 'static) {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    /// Returns a receiver of every future event. Dropping it unsubscribes,
+    /// and dropping the tracker ends it.
+    #[cfg(not(feature = "blocking"))]
+    pub fn events(&mut self) -> OrderEvents {
+        let (sender, receiver) = mpsc::unbounded();
+        self.senders.push(sender);
+        receiver
+    }
+
+    /// Returns a receiver of every future event. Dropping it unsubscribes,
+    /// and dropping the tracker disconnects it.
+    #[cfg(feature = "blocking")]
+    pub fn events(&mut self) -> OrderEvents {
+        let (sender, receiver) = mpsc::channel();
+        self.senders.push(sender);
+        receiver
+    }
+
+    /// Stops tracking an order and forgets its fills. Later updates of the
+    /// order are tracked as those of a new one.
+    pub fn remove(&mut self, order_id: &str) -> Option<TrackedOrder> {
+        self.final_orders
+            .retain(|final_order| final_order != order_id);
+        self.forget(order_id)
+    }
+
+    /// Removes every final order, see [`OrderTracker::remove`].
+    pub fn prune_final(&mut self) -> Vec<TrackedOrder> {
+        std::mem::take(&mut self.final_orders)
+            .into_iter()
+            .filter_map(|order_id| self.forget(&order_id))
+            .collect()
+    }
+
+    pub fn order(&self, order_id: &str) -> Option<&TrackedOrder> {
+        self.orders.get(order_id)
+    }
+
+    pub fn order_by_client_id(&self, key: &ClientOrderKey) -> Option<&TrackedOrder> {
+        self.client_ids
+            .get(key)
+            .and_then(|order_id| self.orders.get(order_id))
+    }
+
+    pub fn orders(&self) -> impl Iterator<Item = &TrackedOrder> {
+        self.orders.values()
+    }
+
+    pub fn open_orders(&self) -> impl Iterator<Item = &TrackedOrder> {
+        self.orders.values().filter(|order| !order.is_final())
+    }
+
+    /// Applies an order read from `get_order`, `get_sub_account_orders` or
+    /// the initial message of the subaccounts channel.
+    pub fn apply_order(&mut self, order: &OrderResponseStruct) -> Vec<OrderEvent> {
+        let update = OrderChange {
+            id: order.id.clone(),
+            client_id: order.client_id.clone(),
+            clob_pair_id: order.clob_pair_id.clone(),
+            order_flags: order.order_flags.clone(),
+            ticker: Some(order.ticker.clone()),
+            side: Some(order.side.clone()),
+            size: Some(order.size),
+            price: Some(order.price),
+            status: Some(order.status.clone()),
+            total_filled: Some(order.total_filled),
+            removal_reason: None,
+        };
+        self.apply_change(update)
+    }
+
+    /// Applies an order update of the subaccounts channel.
+    pub fn apply_order_update(&mut self, update: &SubaccountOrderUpdate) -> Vec<OrderEvent> {
+        let update = OrderChange {
+            id: update.id.clone(),
+            client_id: update.client_id.clone(),
+            clob_pair_id: update.clob_pair_id.clone(),
+            order_flags: update.order_flags.clone(),
+            ticker: update.ticker.clone(),
+            side: update.side.clone(),
+            size: update.size,
+            price: update.price,
+            status: update.status.clone(),
+            total_filled: update.total_filled,
+            removal_reason: update.removal_reason.clone(),
+        };
+        self.apply_change(update)
+    }
+
+    /// Records a fill of the subaccounts channel. Fills without an order,
+    /// e.g. liquidations, are ignored.
+    pub fn apply_fill_update(&mut self, fill: &SubaccountFillUpdate) -> Vec<OrderEvent> {
+        let Some(order_id) = &fill.order_id else {
+            return vec![];
+        };
+        let fill = OrderFill {
+            id: fill.id.clone(),
+            size: fill.size,
+            price: fill.price,
+            fee: fill.fee,
+        };
+        self.apply_fill(order_id, fill)
+    }
+
+    /// Records a fill read from `get_sub_account_fills`.
+    pub fn apply_fill_response(&mut self, fill: &FillResponseStruct) -> Vec<OrderEvent> {
+        let Some(order_id) = &fill.order_id else {
+            return vec![];
+        };
+        let fill = OrderFill {
+            id: fill.id.clone(),
+            size: fill.size,
+            price: fill.price,
+            fee: Some(fill.fee),
+        };
+        self.apply_fill(order_id, fill)
+    }
+
+    /// Applies the orders and fills of a subaccounts channel message. Other
+    /// messages are ignored.
+    pub fn handle_message(&mut self, message: &SocketMessage) -> Vec<OrderEvent> {
+        match message {
+            SocketMessage::Subscribed(SubscribedMessage::Subaccounts(message)) => message
+                .contents
+                .orders
+                .iter()
+                .flat_map(|order| self.apply_order(order))
+                .collect(),
+            SocketMessage::ChannelData(ChannelDataMessage::Subaccounts(message)) => {
+                self.apply_contents(&message.contents)
+            }
+            SocketMessage::ChannelBatchData(ChannelBatchDataMessage::Subaccounts(message)) => {
+                let mut events = vec![];
+                for contents in &message.contents {
+                    events.extend(self.apply_contents(contents));
+                }
+                events
+            }
+            _ => vec![],
+        }
+    }
+
+    fn apply_contents(&mut self, contents: &SubaccountsUpdateContents) -> Vec<OrderEvent> {
+        let mut events = vec![];
+        for order in contents.orders.iter().flatten() {
+            events.extend(self.apply_order_update(order));
+        }
+        for fill in contents.fills.iter().flatten() {
+            events.extend(self.apply_fill_update(fill));
+        }
+        events
+    }
+
+    fn apply_change(&mut self, change: OrderChange) -> Vec<OrderEvent> {
+        if !self.orders.contains_key(&change.id) {
+            return self.insert_order(change);
+        }
+
+        let mut events = vec![];
+        let Some(order) = self.orders.get_mut(&change.id) else {
+            return events;
+        };
+        let previous_status = order.status.clone();
+        let previous_filled = order.total_filled;
+        change.merge_into(order);
+        order.total_filled = order.total_filled.max(previous_filled);
+        if let Some(key) = order.client_key() {
+            self.client_ids.insert(key, order.id.clone());
+        }
+
+        match change.status {
+            Some(status) if status != previous_status => {
+                if is_legal_transition(&previous_status, &status) {
+                    order.status = status;
+                } else {
+                    events.push(OrderEvent::IllegalTransition {
+                        order: order.clone(),
+                        from: previous_status.clone(),
+                        to: status,
+                    });
+                }
+            }
+            _ => {}
+        }
+
+        if order.total_filled > previous_filled {
+            events.push(OrderEvent::Filled {
+                order: order.clone(),
+                size: order.total_filled - previous_filled,
+            });
+        }
+        let became_final = order.is_final() && !is_final(&previous_status);
+        if order.status != previous_status {
+            events.push(OrderEvent::StatusChanged {
+                order: order.clone(),
+                previous: Some(previous_status),
+            });
+        }
+        self.publish(&events);
+        if became_final {
+            self.mark_final(change.id);
+        }
+        events
+    }
+
+    fn insert_order(&mut self, change: OrderChange) -> Vec<OrderEvent> {
+        // Without a status there is nothing to track yet.
+        let Some(status) = change.status.clone() else {
+            return vec![];
+        };
+        let mut order = TrackedOrder {
+            id: change.id.clone(),
+            client_id: None,
+            clob_pair_id: None,
+            order_flags: None,
+            ticker: None,
+            side: None,
+            size: None,
+            price: None,
+            status,
+            total_filled: Decimal::ZERO,
+            fills: self.take_pending_fills(&change.id),
+            removal_reason: None,
+        };
+        change.merge_into(&mut order);
+        order.total_filled = order.total_filled.max(sum_fills(&order.fills));
+        if let Some(key) = order.client_key() {
+            self.client_ids.insert(key, order.id.clone());
+        }
+        let inserted_final = order.is_final();
+        self.orders.insert(change.id.clone(), order.clone());
+
+        let events = vec![OrderEvent::StatusChanged {
+            order,
+            previous: None,
+        }];
+        self.publish(&events);
+        if inserted_final {
+            self.mark_final(change.id);
+        }
+        events
+    }
+
+    /// Keeps a final order, removing the oldest final orders past the limit.
+    fn mark_final(&mut self, order_id: String) {
+        self.final_orders.push_back(order_id);
+        while self.final_orders.len() > self.max_final_orders {
+            if let Some(evicted) = self.final_orders.pop_front() {
+                self.forget(&evicted);
+            }
+        }
+    }
+
+    fn forget(&mut self, order_id: &str) -> Option<TrackedOrder> {
+        let order = self.orders.remove(order_id)?;
+        if let Some(key) = order.client_key() {
+            if self.client_ids.get(&key).map(String::as_str) == Some(order_id) {
+                self.client_ids.remove(&key);
+            }
+        }
+        for fill in &order.fills {
+            self.fill_ids.remove(&fill.id);
+        }
+        Some(order)
+    }
+
+    fn apply_fill(&mut self, order_id: &str, fill: OrderFill) -> Vec<OrderEvent> {
+        if !self.fill_ids.insert(fill.id.clone()) {
+            return vec![];
+        }
+        let Some(order) = self.orders.get_mut(order_id) else {
+            self.hold_fill(order_id, fill);
+            return vec![];
+        };
+
+        order.fills.push(fill);
+        let previous_filled = order.total_filled;
+        order.total_filled = order.total_filled.max(sum_fills(&order.fills));
+        let mut events = vec![];
+        if order.total_filled > previous_filled {
+            events.push(OrderEvent::Filled {
+                order: order.clone(),
+                size: order.total_filled - previous_filled,
+            });
+        }
+        self.publish(&events);
+        events
+    }
+
+    /// Holds a fill until its order is seen, dropping the oldest held fill
+    /// when full. A dropped fill is accepted again if it is read later.
+    fn hold_fill(&mut self, order_id: &str, fill: OrderFill) {
+        self.pending_fills.push_back((order_id.to_string(), fill));
+        while self.pending_fills.len() > self.max_pending_fills {
+            if let Some((_, dropped)) = self.pending_fills.pop_front() {
+                self.fill_ids.remove(&dropped.id);
+            }
+        }
+    }
+
+    fn take_pending_fills(&mut self, order_id: &str) -> Vec<OrderFill> {
+        let mut fills = vec![];
+        self.pending_fills.retain(|(pending_order_id, fill)| {
+            if pending_order_id == order_id {
+                fills.push(fill.clone());
+                false
+            } else {
+                true
+            }
+        });
+        fills
+    }
+
+    fn publish(&mut self, events: &[OrderEvent]) {
+        for event in events {
+            for callback in &mut self.callbacks {
+                callback(event);
+            }
+            self.senders.retain(|sender| send(sender, event.clone()));
+        }
+    }
+}
+
+/// Sends an event, returning whether the receiver is still there.
+#[cfg(not(feature = "blocking"))]
+fn send(sender: &OrderEventSender, event: OrderEvent) -> bool {
+    sender.unbounded_send(event).is_ok()
+}
+
+#[cfg(feature = "blocking")]
+fn send(sender: &OrderEventSender, event: OrderEvent) -> bool {
+    sender.send(event).is_ok()
+}
+
+/// The fields of an order that a REST read or channel update carries.
+struct OrderChange {
+    id: String,
+    client_id: Option<String>,
+    clob_pair_id: Option<String>,
+    order_flags: Option<String>,
+    ticker: Option<String>,
+    side: Option<OrderSide>,
+    size: Option<Decimal>,
+    price: Option<Decimal>,
+    status: Option<OrderStatus>,
+    total_filled: Option<Decimal>,
+    removal_reason: Option<String>,
+}
+
+impl OrderChange {
+    /// Copies every field but the status, which is checked first.
+    fn merge_into(&self, order: &mut TrackedOrder) {
+        if self.client_id.is_some() {
+            order.client_id = self.client_id.clone();
+        }
+        if self.clob_pair_id.is_some() {
+            order.clob_pair_id = self.clob_pair_id.clone();
+        }
+        if self.order_flags.is_some() {
+            order.order_flags = self.order_flags.clone();
+        }
+        if self.ticker.is_some() {
+            order.ticker = self.ticker.clone();
+        }
+        if self.side.is_some() {
+            order.side = self.side.clone();
+        }
+        if self.size.is_some() {
+            order.size = self.size;
+        }
+        if self.price.is_some() {
+            order.price = self.price;
+        }
+        if let Some(total_filled) = self.total_filled {
+            order.total_filled = total_filled;
+        }
+        if self.removal_reason.is_some() {
+            order.removal_reason = self.removal_reason.clone();
+        }
+    }
+}
+
+fn is_final(status: &OrderStatus) -> bool {
+    matches!(status, OrderStatus::Filled | OrderStatus::Canceled)
+}
+
+/// `FILLED` and `CANCELED` are final, nothing goes back to `UNTRIGGERED`,
+/// and only a triggered conditional order becomes `BEST_EFFORT_OPENED`.
+/// `BEST_EFFORT_CANCELED` may still fill or reopen if the cancel fails.
+fn is_legal_transition(from: &OrderStatus, to: &OrderStatus) -> bool {
+    match (from, to) {
+        _ if from == to => true,
+        _ if is_final(from) => false,
+        (_, OrderStatus::Untriggered) => false,
+        (OrderStatus::Untriggered, OrderStatus::BestEffortOpened) => true,
+        (_, OrderStatus::BestEffortOpened) => false,
+        _ => true,
+    }
+}
+
+fn sum_fills(fills: &[OrderFill]) -> Decimal {
+    fills.iter().map(|fill| fill.size).sum()
+}
+
+// ========================================
+// Tests
+// ========================================
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::super::socket_client::parse_message;
+    use super::*;
+
+    fn channel_data(contents: &str) -> SocketMessage {
+        parse_message(&format!(
+            r#"{{"type":"channel_data","connection_id":"c","message_id":2,"channel":"v4_subaccounts","id":"dydx1address/0","version":"3.0.0","contents":{contents}}}"#
+        ))
+        .unwrap()
+    }
+
+    /// The events received so far, without waiting.
+    fn received(events: &mut OrderEvents) -> Vec<OrderEvent> {
+        std::iter::from_fn(|| events.try_recv().ok()).collect()
+    }
+
+    #[test]
+    fn test_follows_orders_through_fills_and_cancels() {
+        let mut tracker = OrderTracker::new();
+        let statuses = Arc::new(Mutex::new(vec![]));
+        let seen = statuses.clone();
+        tracker.on_event(move |event| {
+            if let OrderEvent::StatusChanged { order, .. } = event {
+                seen.lock().unwrap().push(order.status.clone());
+            }
+        });
+        let mut events = tracker.events();
+
+        let opened = channel_data(
+            r#"{"orders":[{"id":"o1","clientId":"42","clobPairId":"0","orderFlags":"0","ticker":"BTC-USD","side":"BUY","size":"1","price":"50000","status":"BEST_EFFORT_OPENED","totalFilled":"0"}]}"#,
+        );
+        tracker.handle_message(&opened);
+        let filled = channel_data(
+            r#"{"orders":[{"id":"o1","status":"OPEN","totalFilled":"0.25"}],"fills":[{"id":"f1","subaccountId":"s","side":"BUY","liquidity":"MAKER","type":"LIMIT","clobPairId":"0","orderId":"o1","size":"0.25","price":"50000","quoteAmount":"12500","eventId":"e","transactionHash":"h","createdAt":"2024-01-01T00:00:00.000Z","createdAtHeight":"1","ticker":"BTC-USD","fee":"1.25"}]}"#,
+        );
+        tracker.handle_message(&filled);
+
+        let order = tracker
+            .order_by_client_id(&ClientOrderKey::new("42", "0", "0"))
+            .unwrap();
+        assert_eq!(order.status, OrderStatus::Open);
+        assert_eq!(order.total_filled, Decimal::new(25, 2));
+        assert_eq!(order.fills.len(), 1);
+        assert!(order.is_partially_filled());
+        assert_eq!(order.remaining(), Some(Decimal::new(75, 2)));
+
+        let canceled = channel_data(r#"{"orders":[{"id":"o1","status":"CANCELED"}]}"#);
+        tracker.handle_message(&canceled);
+        let reopened = channel_data(r#"{"orders":[{"id":"o1","status":"OPEN"}]}"#);
+        let flagged = tracker.handle_message(&reopened);
+        assert!(matches!(
+            flagged.as_slice(),
+            [OrderEvent::IllegalTransition {
+                from: OrderStatus::Canceled,
+                to: OrderStatus::Open,
+                ..
+            }]
+        ));
+        assert_eq!(tracker.order("o1").unwrap().status, OrderStatus::Canceled);
+        assert_eq!(tracker.open_orders().count(), 0);
+
+        assert_eq!(
+            *statuses.lock().unwrap(),
+            vec![
+                OrderStatus::BestEffortOpened,
+                OrderStatus::Open,
+                OrderStatus::Canceled
+            ]
+        );
+        let received = received(&mut events);
+        assert_eq!(received.len(), 5);
+        assert!(
+            matches!(received[1], OrderEvent::Filled { size, .. } if size == Decimal::new(25, 2))
+        );
+    }
+
+    #[test]
+    fn test_client_ids_are_keyed_by_market_and_flags() {
+        let mut tracker = OrderTracker::new();
+        let orders = channel_data(
+            r#"{"orders":[{"id":"o1","clientId":"7","clobPairId":"0","orderFlags":"0","status":"OPEN"},{"id":"o2","clientId":"7","clobPairId":"1","orderFlags":"0","status":"OPEN"},{"id":"o3","clientId":"7","clobPairId":"0","orderFlags":"64","status":"OPEN"}]}"#,
+        );
+        tracker.handle_message(&orders);
+
+        let id = |key: ClientOrderKey| tracker.order_by_client_id(&key).unwrap().id.clone();
+        assert_eq!(id(ClientOrderKey::new("7", "0", "0")), "o1");
+        assert_eq!(id(ClientOrderKey::new("7", "1", "0")), "o2");
+        assert_eq!(id(ClientOrderKey::new("7", "0", "64")), "o3");
+        assert!(tracker
+            .order_by_client_id(&ClientOrderKey::new("7", "1", "64"))
+            .is_none());
+    }
+
+    #[test]
+    fn test_pending_fills_are_bounded() {
+        let mut tracker = OrderTracker::new().with_max_pending_fills(2);
+        let fill = |id: &str, order_id: &str| {
+            format!(
+                r#"{{"id":"{id}","subaccountId":"s","side":"BUY","liquidity":"MAKER","type":"LIMIT","clobPairId":"0","orderId":"{order_id}","size":"1","price":"100","quoteAmount":"100","eventId":"e","transactionHash":"h","createdAt":"2024-01-01T00:00:00.000Z","createdAtHeight":"1","ticker":"BTC-USD"}}"#
+            )
+        };
+        let fills = channel_data(&format!(
+            r#"{{"fills":[{},{},{}]}}"#,
+            fill("f1", "o1"),
+            fill("f2", "o2"),
+            fill("f3", "o2")
+        ));
+        tracker.handle_message(&fills);
+        assert_eq!(tracker.pending_fills(), 2);
+
+        let orders =
+            channel_data(r#"{"orders":[{"id":"o1","status":"OPEN"},{"id":"o2","status":"OPEN"}]}"#);
+        tracker.handle_message(&orders);
+        assert!(tracker.order("o1").unwrap().fills.is_empty());
+        assert_eq!(tracker.order("o2").unwrap().fills.len(), 2);
+        assert_eq!(tracker.pending_fills(), 0);
+
+        let replayed = channel_data(&format!(r#"{{"fills":[{}]}}"#, fill("f1", "o1")));
+        tracker.handle_message(&replayed);
+        assert_eq!(tracker.order("o1").unwrap().total_filled, Decimal::ONE);
+    }
+
+    #[test]
+    fn test_final_orders_are_bounded() {
+        let mut tracker = OrderTracker::new().with_max_final_orders(1);
+        let orders = channel_data(
+            r#"{"orders":[{"id":"o1","clientId":"1","clobPairId":"0","orderFlags":"0","status":"OPEN"},{"id":"o2","clientId":"2","clobPairId":"0","orderFlags":"0","status":"OPEN"},{"id":"o3","clientId":"3","clobPairId":"0","orderFlags":"0","status":"OPEN"}],"fills":[{"id":"f1","subaccountId":"s","side":"BUY","liquidity":"MAKER","type":"LIMIT","clobPairId":"0","orderId":"o1","size":"1","price":"100","quoteAmount":"100","eventId":"e","transactionHash":"h","createdAt":"2024-01-01T00:00:00.000Z","createdAtHeight":"1","ticker":"BTC-USD"}]}"#,
+        );
+        tracker.handle_message(&orders);
+        let done = channel_data(
+            r#"{"orders":[{"id":"o1","status":"FILLED"},{"id":"o2","status":"CANCELED"}]}"#,
+        );
+        tracker.handle_message(&done);
+
+        assert!(tracker.order("o1").is_none());
+        assert!(tracker
+            .order_by_client_id(&ClientOrderKey::new("1", "0", "0"))
+            .is_none());
+        assert!(tracker.order("o2").unwrap().is_final());
+        assert_eq!(tracker.fill_ids.len(), 0);
+
+        let pruned = tracker.prune_final();
+        assert_eq!(pruned.len(), 1);
+        assert_eq!(pruned[0].id, "o2");
+        assert_eq!(tracker.orders().count(), 1);
+
+        let removed = tracker.remove("o3").unwrap();
+        assert_eq!(removed.status, OrderStatus::Open);
+        assert_eq!(tracker.orders().count(), 0);
+        assert!(tracker.client_ids.is_empty());
+    }
+
+    #[cfg(not(feature = "blocking"))]
+    #[tokio::test]
+    async fn test_events_are_a_stream() {
+        use futures_util::StreamExt;
+
+        let mut tracker = OrderTracker::new();
+        let events = tracker.events();
+        let opened = channel_data(r#"{"orders":[{"id":"o1","status":"OPEN"}]}"#);
+        tracker.handle_message(&opened);
+        drop(tracker);
+
+        let received: Vec<OrderEvent> = events.collect().await;
+        assert!(matches!(
+            received.as_slice(),
+            [OrderEvent::StatusChanged { previous: None, .. }]
+        ));
+    }
+}
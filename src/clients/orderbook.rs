@@ -0,0 +1,459 @@
+use std::collections::BTreeMap;
+
+use maybe_async::maybe_async;
+use rust_decimal::Decimal;
+
+use crate::constants::OrderSide;
+
+use super::errors::{OrderbookError, SocketError};
+use super::indexer_client_types::{OrderbookResponse, OrderbookResponsePriceLevel};
+use super::socket_client::SocketClient;
+use super::socket_client_types::{
+    ChannelBatchDataMessage, ChannelDataMessage, ChannelMessage, OrderbookUpdateContents,
+    SocketChannel, SocketMessage, SubscribedMessage,
+};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PriceLevel {
+    pub price: Decimal,
+    pub size: Decimal,
+}
+
+/// The best levels of each side, best price first.
+#[derive(Clone, PartialEq, Debug)]
+pub struct OrderbookDepth {
+    pub bids: Vec<PriceLevel>,
+    pub asks: Vec<PriceLevel>,
+}
+
+/// A local copy of one market's orderbook, seeded from a snapshot and kept
+/// up to date with the `v4_orderbook` channel.
+///
+/// Message ids count every message sent on a websocket connection, not only
+/// those of one channel. Feed the book every message read from its
+/// connection through [`Orderbook::handle_message`] so that a skipped id is
+/// a real gap. After a gap the book rejects updates until it is seeded again,
+/// e.g. by the subscribed message following [`Orderbook::resubscribe`].
+///
+/// Only the snapshot of a subscribed message is exact: it carries the message
+/// id from which updates follow. A book seeded from the REST snapshot is
+/// approximate, see [`Orderbook::from_snapshot`].
+#[derive(Clone, Debug)]
+pub struct Orderbook {
+    market: String,
+    bids: BTreeMap<Decimal, Decimal>,
+    asks: BTreeMap<Decimal, Decimal>,
+    last_message_id: Option<u64>,
+    in_sync: bool,
+    // Seeded without a message id, so updates may be missing or applied
+    // twice.
+    approximate: bool,
+}
+
+impl Orderbook {
+    /// Seeds the book from the REST snapshot. The first update applied
+    /// afterwards starts the message sequence.
+    ///
+    /// The snapshot carries no message id, so updates sent around the time
+    /// it was taken may be missing from the book or applied on top of it
+    /// twice. The book is approximate and [`Orderbook::is_in_sync`] stays
+    /// false until the snapshot of a subscribed message seeds it.
+    pub fn from_snapshot(market: String, snapshot: &OrderbookResponse) -> Self {
+        let mut orderbook = Orderbook {
+            market,
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            last_message_id: None,
+            in_sync: true,
+            approximate: true,
+        };
+        orderbook.reseed(snapshot, None);
+        orderbook
+    }
+
+    /// Seeds the book from the message confirming a `v4_orderbook`
+    /// subscription.
+    pub fn from_subscribed(message: &ChannelMessage<OrderbookResponse>) -> Self {
+        let market = message.id.clone().unwrap_or_default();
+        let mut orderbook = Orderbook::from_snapshot(market, &message.contents);
+        orderbook.reseed(&message.contents, Some(message.message_id));
+        orderbook
+    }
+
+    /// Replaces the book's levels with `snapshot` and accepts updates again.
+    /// Without the `message_id` of the snapshot the book is approximate, as
+    /// with [`Orderbook::from_snapshot`].
+    pub fn reseed(&mut self, snapshot: &OrderbookResponse, message_id: Option<u64>) {
+        self.bids = levels(&snapshot.bids);
+        self.asks = levels(&snapshot.asks);
+        self.last_message_id = message_id;
+        self.in_sync = true;
+        self.approximate = message_id.is_none();
+    }
+
+    pub fn market(&self) -> &str {
+        &self.market
+    }
+
+    pub fn last_message_id(&self) -> Option<u64> {
+        self.last_message_id
+    }
+
+    /// Whether the book is exact: seeded by a subscribed message and
+    /// without a gap since. False after a gap until the book is seeded
+    /// again, and for a book seeded from the REST snapshot.
+    pub fn is_in_sync(&self) -> bool {
+        self.in_sync && !self.approximate
+    }
+
+    /// Applies an update of this book's market. Returns false for a message
+    /// that was already seen.
+    pub fn apply_update(
+        &mut self,
+        message: &ChannelMessage<OrderbookUpdateContents>,
+    ) -> Result<bool, OrderbookError> {
+        if !self.advance(message.message_id)? {
+            return Ok(false);
+        }
+        self.apply(&message.contents);
+        Ok(true)
+    }
+
+    /// Applies a batch of updates of this book's market, sent as one
+    /// message.
+    pub fn apply_batch(
+        &mut self,
+        message: &ChannelMessage<Vec<OrderbookUpdateContents>>,
+    ) -> Result<bool, OrderbookError> {
+        if !self.advance(message.message_id)? {
+            return Ok(false);
+        }
+        for contents in &message.contents {
+            self.apply(contents);
+        }
+        Ok(true)
+    }
+
+    /// Follows the message sequence of the book's connection and applies the
+    /// messages of its market. Returns whether the book changed.
+    ///
+    /// A new connection leaves the book out of sync, as it has to subscribe
+    /// again.
+    pub fn handle_message(&mut self, message: &SocketMessage) -> Result<bool, OrderbookError> {
+        match message {
+            SocketMessage::Connected(_) => {
+                self.in_sync = false;
+                self.last_message_id = None;
+                Ok(false)
+            }
+            SocketMessage::Subscribed(SubscribedMessage::Orderbook(message))
+                if self.is_own(message) =>
+            {
+                self.reseed(&message.contents, Some(message.message_id));
+                Ok(true)
+            }
+            SocketMessage::ChannelData(ChannelDataMessage::Orderbook(message))
+                if self.is_own(message) =>
+            {
+                self.apply_update(message)
+            }
+            SocketMessage::ChannelBatchData(ChannelBatchDataMessage::Orderbook(message))
+                if self.is_own(message) =>
+            {
+                self.apply_batch(message)
+            }
+            message => {
+                if let (true, Some(message_id)) = (self.in_sync, message_id(message)) {
+                    self.advance(message_id)?;
+                }
+                Ok(false)
+            }
+        }
+    }
+
+    /// Subscribes to the book's market again to receive a fresh snapshot,
+    /// e.g. after a gap.
+    #[maybe_async]
+    pub async fn resubscribe(&self, socket: &mut SocketClient) -> Result<(), SocketError> {
+        let channel = SocketChannel::Orderbook(self.market.clone());
+        socket.unsubscribe(channel.clone()).await?;
+        socket.subscribe(channel, false).await
+    }
+
+    pub fn best_bid(&self) -> Option<PriceLevel> {
+        self.bids
+            .iter()
+            .next_back()
+            .map(|(price, size)| PriceLevel {
+                price: *price,
+                size: *size,
+            })
+    }
+
+    pub fn best_ask(&self) -> Option<PriceLevel> {
+        self.asks.iter().next().map(|(price, size)| PriceLevel {
+            price: *price,
+            size: *size,
+        })
+    }
+
+    pub fn mid(&self) -> Option<Decimal> {
+        let bid = self.best_bid()?;
+        let ask = self.best_ask()?;
+        Some((bid.price + ask.price) / Decimal::TWO)
+    }
+
+    pub fn spread(&self) -> Option<Decimal> {
+        let bid = self.best_bid()?;
+        let ask = self.best_ask()?;
+        Some(ask.price - bid.price)
+    }
+
+    /// Up to `levels` levels of each side.
+    pub fn depth(&self, levels: usize) -> OrderbookDepth {
+        OrderbookDepth {
+            bids: self
+                .bids
+                .iter()
+                .rev()
+                .take(levels)
+                .map(|(price, size)| PriceLevel {
+                    price: *price,
+                    size: *size,
+                })
+                .collect(),
+            asks: self
+                .asks
+                .iter()
+                .take(levels)
+                .map(|(price, size)| PriceLevel {
+                    price: *price,
+                    size: *size,
+                })
+                .collect(),
+        }
+    }
+
+    /// Average price of an order of `side` for `size`, filled against the
+    /// opposite side of the book. `None` if the book is too thin.
+    pub fn vwap(&self, side: OrderSide, size: Decimal) -> Option<Decimal> {
+        if size <= Decimal::ZERO {
+            return None;
+        }
+
+        let levels: Box<dyn Iterator<Item = (&Decimal, &Decimal)>> = match side {
+            OrderSide::BUY => Box::new(self.asks.iter()),
+            OrderSide::SELL => Box::new(self.bids.iter().rev()),
+        };
+
+        let mut remaining = size;
+        let mut notional = Decimal::ZERO;
+        for (price, level_size) in levels {
+            let filled = remaining.min(*level_size);
+            notional += filled * price;
+            remaining -= filled;
+            if remaining.is_zero() {
+                return Some(notional / size);
+            }
+        }
+        None
+    }
+
+    fn is_own<T>(&self, message: &ChannelMessage<T>) -> bool {
+        message.id.as_deref() == Some(self.market.as_str())
+    }
+
+    /// Moves the sequence to `message_id`, returning false for a message
+    /// that was already seen.
+    fn advance(&mut self, message_id: u64) -> Result<bool, OrderbookError> {
+        if !self.in_sync {
+            return Err(OrderbookError::OutOfSync);
+        }
+        match self.last_message_id {
+            Some(last) if message_id <= last => Ok(false),
+            Some(last) if message_id > last + 1 => {
+                self.in_sync = false;
+                Err(OrderbookError::Gap {
+                    expected: last + 1,
+                    received: message_id,
+                })
+            }
+            _ => {
+                self.last_message_id = Some(message_id);
+                Ok(true)
+            }
+        }
+    }
+
+    fn apply(&mut self, contents: &OrderbookUpdateContents) {
+        for level in contents.bids.iter().flatten() {
+            set_level(&mut self.bids, level.price, level.size);
+        }
+        for level in contents.asks.iter().flatten() {
+            set_level(&mut self.asks, level.price, level.size);
+        }
+    }
+}
+
+fn levels(levels: &[OrderbookResponsePriceLevel]) -> BTreeMap<Decimal, Decimal> {
+    let mut book = BTreeMap::new();
+    for level in levels {
+        set_level(&mut book, level.price, level.size);
+    }
+    book
+}
+
+fn set_level(book: &mut BTreeMap<Decimal, Decimal>, price: Decimal, size: Decimal) {
+    if size.is_zero() {
+        book.remove(&price);
+    } else {
+        book.insert(price, size);
+    }
+}
+
+fn message_id(message: &SocketMessage) -> Option<u64> {
+    match message {
+        SocketMessage::Connected(message) => Some(message.message_id),
+        SocketMessage::Subscribed(message) => Some(match message {
+            SubscribedMessage::Markets(message) => message.message_id,
+            SubscribedMessage::Orderbook(message) => message.message_id,
+            SubscribedMessage::Trades(message) => message.message_id,
+            SubscribedMessage::Candles(message) => message.message_id,
+            SubscribedMessage::Subaccounts(message) => message.message_id,
+        }),
+        SocketMessage::ChannelData(message) => Some(match message {
+            ChannelDataMessage::Markets(message) => message.message_id,
+            ChannelDataMessage::Orderbook(message) => message.message_id,
+            ChannelDataMessage::Trades(message) => message.message_id,
+            ChannelDataMessage::Candles(message) => message.message_id,
+            ChannelDataMessage::Subaccounts(message) => message.message_id,
+        }),
+        SocketMessage::ChannelBatchData(message) => Some(match message {
+            ChannelBatchDataMessage::Markets(message) => message.message_id,
+            ChannelBatchDataMessage::Orderbook(message) => message.message_id,
+            ChannelBatchDataMessage::Trades(message) => message.message_id,
+            ChannelBatchDataMessage::Candles(message) => message.message_id,
+            ChannelBatchDataMessage::Subaccounts(message) => message.message_id,
+        }),
+        SocketMessage::Unsubscribed(message) => Some(message.message_id),
+        SocketMessage::Error(message) => message.message_id,
+    }
+}
+
+// ========================================
+// Tests
+// ========================================
+
+#[cfg(test)]
+mod tests {
+    use super::super::socket_client::parse_message;
+    use super::*;
+
+    fn decimal(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn subscribed(message_id: u64) -> SocketMessage {
+        parse_message(&format!(
+            r#"{{"type":"subscribed","connection_id":"c","message_id":{message_id},
+            "channel":"v4_orderbook","id":"BTC-USD",
+            "contents":{{"bids":[{{"price":"100","size":"1"}},{{"price":"99","size":"2"}}],
+            "asks":[{{"price":"101","size":"1"}},{{"price":"102","size":"3"}}]}}}}"#
+        ))
+        .unwrap()
+    }
+
+    fn update(message_id: u64, market: &str, contents: &str) -> SocketMessage {
+        parse_message(&format!(
+            r#"{{"type":"channel_data","connection_id":"c","message_id":{message_id},
+            "channel":"v4_orderbook","id":"{market}","version":"1.0.0","contents":{contents}}}"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_applies_updates_and_answers_queries() {
+        let mut orderbook = match subscribed(1) {
+            SocketMessage::Subscribed(SubscribedMessage::Orderbook(message)) => {
+                Orderbook::from_subscribed(&message)
+            }
+            message => panic!("Unexpected message: {:?}", message),
+        };
+        assert!(orderbook.is_in_sync());
+
+        let changed = orderbook
+            .handle_message(&update(
+                2,
+                "BTC-USD",
+                r#"{"bids":[["100","0"],["99.5","4"]]}"#,
+            ))
+            .unwrap();
+        assert!(changed);
+        let changed = orderbook
+            .handle_message(&update(3, "ETH-USD", r#"{"asks":[["1","1"]]}"#))
+            .unwrap();
+        assert!(!changed);
+        let stale = orderbook
+            .handle_message(&update(2, "BTC-USD", r#"{"asks":[["50","1"]]}"#))
+            .unwrap();
+        assert!(!stale);
+
+        assert_eq!(orderbook.best_bid().unwrap().price, decimal("99.5"));
+        assert_eq!(orderbook.best_ask().unwrap().price, decimal("101"));
+        assert_eq!(orderbook.mid().unwrap(), decimal("100.25"));
+        assert_eq!(orderbook.spread().unwrap(), decimal("1.5"));
+
+        let depth = orderbook.depth(1);
+        assert_eq!(depth.bids.len(), 1);
+        assert_eq!(depth.asks[0].size, decimal("1"));
+
+        // 1 @ 101 and 1 @ 102.
+        assert_eq!(
+            orderbook.vwap(OrderSide::BUY, decimal("2")).unwrap(),
+            decimal("101.5")
+        );
+        assert_eq!(
+            orderbook.vwap(OrderSide::SELL, decimal("4")).unwrap(),
+            decimal("99.5")
+        );
+        assert!(orderbook.vwap(OrderSide::BUY, decimal("5")).is_none());
+    }
+
+    #[test]
+    fn test_gap_requires_a_new_snapshot() {
+        let mut orderbook = Orderbook::from_snapshot(
+            "BTC-USD".to_string(),
+            &OrderbookResponse {
+                bids: vec![],
+                asks: vec![],
+            },
+        );
+        assert!(!orderbook.is_in_sync());
+        orderbook
+            .handle_message(&update(5, "BTC-USD", r#"{"bids":[["100","1"]]}"#))
+            .unwrap();
+        assert_eq!(orderbook.last_message_id(), Some(5));
+        assert!(!orderbook.is_in_sync());
+
+        let gap = orderbook.handle_message(&update(7, "BTC-USD", r#"{"bids":[["100","0"]]}"#));
+        assert_eq!(
+            gap,
+            Err(OrderbookError::Gap {
+                expected: 6,
+                received: 7
+            })
+        );
+        assert!(!orderbook.is_in_sync());
+        let rejected = orderbook.handle_message(&update(8, "BTC-USD", "{}"));
+        assert_eq!(rejected, Err(OrderbookError::OutOfSync));
+
+        orderbook.handle_message(&subscribed(9)).unwrap();
+        assert!(orderbook.is_in_sync());
+        assert_eq!(orderbook.best_bid().unwrap().price, decimal("100"));
+        let applied = orderbook
+            .handle_message(&update(10, "BTC-USD", r#"{"asks":[["101","0"]]}"#))
+            .unwrap();
+        assert!(applied);
+        assert_eq!(orderbook.best_ask().unwrap().price, decimal("102"));
+    }
+}
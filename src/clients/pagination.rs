@@ -0,0 +1,650 @@
+use std::collections::{HashSet, VecDeque};
+
+use chrono::{DateTime, Utc};
+use maybe_async::maybe_async;
+
+use super::errors::{APIError, PagerError};
+use super::indexer_client::{AccountsClient, MarketsClient};
+use super::indexer_client_types::{
+    FillResponseStruct, FillsRequest, HistoricalFundingRequest, HistoricalFundingResponseStruct,
+    TradeResponseStruct, TradesRequest, TransferResponseStruct, TransfersRequest,
+};
+
+const DEFAULT_PAGE_SIZE: u32 = 100;
+/// Largest `limit` the indexer accepts.
+const MAX_PAGE_SIZE: u32 = 1000;
+
+/// Oldest record a [`HistoryPager`] returns. Records strictly older than the
+/// bound end the walk.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum StopAt {
+    Height(u64),
+    Time(DateTime<Utc>),
+}
+
+/// A record of a history endpoint that can be paged by block height.
+pub trait HistoryRecord {
+    /// Identifies the record across overlapping pages.
+    fn record_id(&self) -> String;
+    fn height(&self) -> u64;
+    fn time(&self) -> DateTime<Utc>;
+}
+
+/// One history endpoint, queried a page at a time.
+#[maybe_async]
+pub trait PageSource {
+    type Record: HistoryRecord;
+
+    /// Whether the endpoint takes the indexer's `page` parameter.
+    fn supports_page(&self) -> bool {
+        false
+    }
+
+    /// Fetches up to `limit` records created at or before
+    /// `before_or_at_height`, newest first. `page` counts from 1 and skips
+    /// the first `(page - 1) * limit` records; it is only given when
+    /// [`PageSource::supports_page`] holds.
+    async fn fetch_page(
+        &self,
+        before_or_at_height: Option<u64>,
+        limit: u32,
+        page: Option<u32>,
+    ) -> Result<Vec<Self::Record>, APIError>;
+}
+
+/// Walks backward through a history endpoint, newest record first.
+///
+/// Each page is requested at or before the oldest height of the previous
+/// one, so records sharing that height are fetched twice and dropped the
+/// second time. When a whole page sits at the cursor's height, the rest of
+/// that height is read with the indexer's `page` parameter or, for
+/// endpoints without it, with a larger limit. A height that still does not
+/// fit fails with [`PagerError::PageOverflow`].
+///
+/// Without the `blocking` feature records are read with
+/// [`HistoryPager::next_record`] or as a stream, with it the pager is an
+/// [`Iterator`].
+pub struct HistoryPager<S: PageSource> {
+    source: S,
+    page_size: u32,
+    stop_at: Option<StopAt>,
+    cursor: Option<u64>,
+    // Set while reading past the first page at the cursor's height.
+    page: Option<u32>,
+    limit: u32,
+    buffer: VecDeque<S::Record>,
+    boundary_ids: HashSet<String>,
+    exhausted: bool,
+}
+
+impl<S: PageSource> HistoryPager<S> {
+    pub fn new(source: S) -> Self {
+        HistoryPager {
+            source,
+            page_size: DEFAULT_PAGE_SIZE,
+            stop_at: None,
+            cursor: None,
+            page: None,
+            limit: DEFAULT_PAGE_SIZE,
+            buffer: VecDeque::new(),
+            boundary_ids: HashSet::new(),
+            exhausted: false,
+        }
+    }
+
+    /// Number of records requested per page, 100 by default.
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size.max(1);
+        self.limit = self.page_size;
+        self
+    }
+
+    pub fn stop_at(mut self, stop_at: StopAt) -> Self {
+        self.stop_at = Some(stop_at);
+        self
+    }
+
+    /// Returns the next older record, fetching a page when needed. After an
+    /// error the pager is exhausted.
+    #[maybe_async]
+    pub async fn next_record(&mut self) -> Option<Result<S::Record, PagerError>> {
+        loop {
+            if let Some(record) = self.buffer.pop_front() {
+                return Some(Ok(record));
+            }
+            if self.exhausted {
+                return None;
+            }
+            if let Err(error) = self.fetch_next_page().await {
+                self.exhausted = true;
+                return Some(Err(error));
+            }
+        }
+    }
+
+    /// Reads the remaining history into memory.
+    #[maybe_async]
+    pub async fn collect_all(mut self) -> Result<Vec<S::Record>, PagerError> {
+        let mut records = Vec::new();
+        while let Some(record) = self.next_record().await {
+            records.push(record?);
+        }
+        Ok(records)
+    }
+
+    #[maybe_async]
+    async fn fetch_next_page(&mut self) -> Result<(), PagerError> {
+        let page = self
+            .source
+            .fetch_page(self.cursor, self.limit, self.page)
+            .await?;
+        let full = page.len() >= self.limit as usize;
+        if !full {
+            self.exhausted = true;
+        }
+
+        let oldest = match page.iter().map(|record| record.height()).min() {
+            Some(oldest) => oldest,
+            None => {
+                self.exhausted = true;
+                return Ok(());
+            }
+        };
+
+        let mut boundary_ids = HashSet::new();
+        for record in page {
+            if self.is_past_stop(&record) {
+                self.exhausted = true;
+                continue;
+            }
+            let id = record.record_id();
+            if self.boundary_ids.contains(&id) {
+                continue;
+            }
+            if record.height() == oldest {
+                boundary_ids.insert(id);
+            }
+            self.buffer.push_back(record);
+        }
+
+        if !full || self.cursor != Some(oldest) {
+            self.boundary_ids = boundary_ids;
+            self.cursor = Some(oldest);
+            self.page = None;
+            self.limit = self.page_size;
+            return Ok(());
+        }
+
+        // A full page at the cursor's own height: the rest of that height
+        // lies past it.
+        self.boundary_ids.extend(boundary_ids);
+        if self.source.supports_page() {
+            self.page = Some(self.page.unwrap_or(1) + 1);
+        } else if self.limit < MAX_PAGE_SIZE {
+            self.limit = self.limit.saturating_mul(2).min(MAX_PAGE_SIZE);
+        } else {
+            return Err(PagerError::PageOverflow {
+                height: oldest,
+                limit: self.limit,
+            });
+        }
+        Ok(())
+    }
+
+    fn is_past_stop(&self, record: &S::Record) -> bool {
+        match self.stop_at {
+            Some(StopAt::Height(height)) => record.height() < height,
+            Some(StopAt::Time(time)) => record.time() < time,
+            None => false,
+        }
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<S: PageSource> Iterator for HistoryPager<S> {
+    type Item = Result<S::Record, PagerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record()
+    }
+}
+
+#[cfg(not(feature = "blocking"))]
+impl<S> HistoryPager<S>
+where
+    S: PageSource + Send,
+    S::Record: Send,
+{
+    pub fn into_stream(self) -> impl futures_util::Stream<Item = Result<S::Record, PagerError>> {
+        futures_util::stream::unfold(self, |mut pager| async move {
+            pager.next_record().await.map(|record| (record, pager))
+        })
+    }
+}
+
+// ========================================
+// Sources
+// ========================================
+
+pub struct FillsSource<'a, C: ?Sized> {
+    client: &'a C,
+    request: FillsRequest,
+}
+
+pub struct TransfersSource<'a, C: ?Sized> {
+    client: &'a C,
+    request: TransfersRequest,
+}
+
+pub struct TradesSource<'a, C: ?Sized> {
+    client: &'a C,
+    request: TradesRequest,
+}
+
+pub struct HistoricalFundingSource<'a, C: ?Sized> {
+    client: &'a C,
+    request: HistoricalFundingRequest,
+}
+
+// The height cursor, limit and page of each request are set by the pager.
+
+impl<'a, C: AccountsClient + Sync + ?Sized> HistoryPager<FillsSource<'a, C>> {
+    pub fn fills(client: &'a C, request: FillsRequest) -> Self {
+        HistoryPager::new(FillsSource { client, request })
+    }
+}
+
+impl<'a, C: AccountsClient + Sync + ?Sized> HistoryPager<TransfersSource<'a, C>> {
+    pub fn transfers(client: &'a C, request: TransfersRequest) -> Self {
+        HistoryPager::new(TransfersSource { client, request })
+    }
+}
+
+impl<'a, C: MarketsClient + Sync + ?Sized> HistoryPager<TradesSource<'a, C>> {
+    pub fn trades(client: &'a C, request: TradesRequest) -> Self {
+        HistoryPager::new(TradesSource { client, request })
+    }
+}
+
+impl<'a, C: MarketsClient + Sync + ?Sized> HistoryPager<HistoricalFundingSource<'a, C>> {
+    pub fn historical_funding(client: &'a C, request: HistoricalFundingRequest) -> Self {
+        HistoryPager::new(HistoricalFundingSource { client, request })
+    }
+}
+
+#[maybe_async]
+impl<C: AccountsClient + Sync + ?Sized> PageSource for FillsSource<'_, C> {
+    type Record = FillResponseStruct;
+
+    fn supports_page(&self) -> bool {
+        true
+    }
+
+    async fn fetch_page(
+        &self,
+        before_or_at_height: Option<u64>,
+        limit: u32,
+        page: Option<u32>,
+    ) -> Result<Vec<Self::Record>, APIError> {
+        let mut request = self.request.clone().limit(limit);
+        if let Some(height) = before_or_at_height {
+            request = request.created_before_or_at_height(height);
+        }
+        if let Some(page) = page {
+            request = request.page(page);
+        }
+        let response = self.client.get_sub_account_fills(request).await?;
+        Ok(response.fills)
+    }
+}
+
+#[maybe_async]
+impl<C: AccountsClient + Sync + ?Sized> PageSource for TransfersSource<'_, C> {
+    type Record = TransferResponseStruct;
+
+    fn supports_page(&self) -> bool {
+        true
+    }
+
+    async fn fetch_page(
+        &self,
+        before_or_at_height: Option<u64>,
+        limit: u32,
+        page: Option<u32>,
+    ) -> Result<Vec<Self::Record>, APIError> {
+        let mut request = self.request.clone().limit(limit);
+        if let Some(height) = before_or_at_height {
+            request = request.created_before_or_at_height(height);
+        }
+        if let Some(page) = page {
+            request = request.page(page);
+        }
+        let response = self.client.get_sub_account_transfers(request).await?;
+        Ok(response.transfers)
+    }
+}
+
+#[maybe_async]
+impl<C: MarketsClient + Sync + ?Sized> PageSource for TradesSource<'_, C> {
+    type Record = TradeResponseStruct;
+
+    fn supports_page(&self) -> bool {
+        true
+    }
+
+    async fn fetch_page(
+        &self,
+        before_or_at_height: Option<u64>,
+        limit: u32,
+        page: Option<u32>,
+    ) -> Result<Vec<Self::Record>, APIError> {
+        let mut request = self.request.clone().limit(limit);
+        if let Some(height) = before_or_at_height {
+            request = request.created_before_or_at_height(height);
+        }
+        if let Some(page) = page {
+            request = request.page(page);
+        }
+        let response = self.client.get_perpetual_market_trades(request).await?;
+        Ok(response.trades)
+    }
+}
+
+#[maybe_async]
+impl<C: MarketsClient + Sync + ?Sized> PageSource for HistoricalFundingSource<'_, C> {
+    type Record = HistoricalFundingResponseStruct;
+
+    async fn fetch_page(
+        &self,
+        before_or_at_height: Option<u64>,
+        limit: u32,
+        _page: Option<u32>,
+    ) -> Result<Vec<Self::Record>, APIError> {
+        let mut request = self.request.clone().limit(limit);
+        if let Some(height) = before_or_at_height {
+            request = request.effective_before_or_at_height(height);
+        }
+        let response = self
+            .client
+            .get_perpetual_market_historical_funding(request)
+            .await?;
+        Ok(response.historical_funding)
+    }
+}
+
+impl HistoryRecord for FillResponseStruct {
+    fn record_id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn height(&self) -> u64 {
+        self.created_at_height
+    }
+
+    fn time(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+}
+
+impl HistoryRecord for TransferResponseStruct {
+    fn record_id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn height(&self) -> u64 {
+        self.created_at_height
+    }
+
+    fn time(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+}
+
+impl HistoryRecord for TradeResponseStruct {
+    fn record_id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn height(&self) -> u64 {
+        self.created_at_height
+    }
+
+    fn time(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+}
+
+impl HistoryRecord for HistoricalFundingResponseStruct {
+    /// A market has at most one funding rate per height.
+    fn record_id(&self) -> String {
+        self.effective_at_height.to_string()
+    }
+
+    fn height(&self) -> u64 {
+        self.effective_at_height
+    }
+
+    fn time(&self) -> DateTime<Utc> {
+        self.effective_at
+    }
+}
+
+// ========================================
+// Tests
+// ========================================
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use chrono::TimeZone;
+
+    use super::super::indexer_client::IndexerClient;
+    use super::super::indexer_fixtures::IndexerFixtures;
+    use super::*;
+
+    struct TestRecord {
+        id: String,
+        height: u64,
+    }
+
+    impl HistoryRecord for TestRecord {
+        fn record_id(&self) -> String {
+            self.id.clone()
+        }
+
+        fn height(&self) -> u64 {
+            self.height
+        }
+
+        fn time(&self) -> DateTime<Utc> {
+            Utc.timestamp_opt(self.height as i64, 0).unwrap()
+        }
+    }
+
+    /// `(cursor, limit, page)` of a request.
+    type TestRequest = (Option<u64>, u32, Option<u32>);
+
+    /// Serves `(id, height)` records newest first, like the indexer, and
+    /// records each request.
+    struct TestSource {
+        records: Vec<(String, u64)>,
+        supports_page: bool,
+        requests: Mutex<Vec<TestRequest>>,
+    }
+
+    impl TestSource {
+        fn new(records: Vec<(&str, u64)>) -> Self {
+            TestSource {
+                records: records
+                    .into_iter()
+                    .map(|(id, height)| (id.to_string(), height))
+                    .collect(),
+                supports_page: true,
+                requests: Mutex::new(vec![]),
+            }
+        }
+
+        fn without_page(mut self) -> Self {
+            self.supports_page = false;
+            self
+        }
+    }
+
+    #[maybe_async]
+    impl PageSource for &TestSource {
+        type Record = TestRecord;
+
+        fn supports_page(&self) -> bool {
+            self.supports_page
+        }
+
+        async fn fetch_page(
+            &self,
+            before_or_at_height: Option<u64>,
+            limit: u32,
+            page: Option<u32>,
+        ) -> Result<Vec<TestRecord>, APIError> {
+            self.requests
+                .lock()
+                .unwrap()
+                .push((before_or_at_height, limit, page));
+            let skip = (page.unwrap_or(1) - 1) * limit;
+            Ok(self
+                .records
+                .iter()
+                .filter(|(_, height)| before_or_at_height.is_none_or(|cursor| *height <= cursor))
+                .skip(skip as usize)
+                .take(limit as usize)
+                .map(|(id, height)| TestRecord {
+                    id: id.clone(),
+                    height: *height,
+                })
+                .collect())
+        }
+    }
+
+    fn ids(records: Vec<TestRecord>) -> Vec<String> {
+        records.into_iter().map(|record| record.id).collect()
+    }
+
+    #[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+    async fn test_pages_backward_without_duplicates() {
+        let source = TestSource::new(vec![
+            ("f", 30),
+            ("e", 20),
+            ("d", 20),
+            ("c", 20),
+            ("b", 10),
+            ("a", 5),
+        ]);
+
+        let records = HistoryPager::new(&source)
+            .page_size(3)
+            .collect_all()
+            .await
+            .unwrap();
+        assert_eq!(ids(records), vec!["f", "e", "d", "c", "b", "a"]);
+        assert_eq!(
+            *source.requests.lock().unwrap(),
+            vec![(None, 3, None), (Some(20), 3, None), (Some(20), 3, Some(2))]
+        );
+    }
+
+    #[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+    async fn test_stops_at_height_and_time() {
+        let source = TestSource::new(vec![("c", 30), ("b", 20), ("a", 10)]);
+
+        let records = HistoryPager::new(&source)
+            .stop_at(StopAt::Height(20))
+            .collect_all()
+            .await
+            .unwrap();
+        assert_eq!(ids(records), vec!["c", "b"]);
+
+        let records = HistoryPager::new(&source)
+            .stop_at(StopAt::Time(Utc.timestamp_opt(25, 0).unwrap()))
+            .collect_all()
+            .await
+            .unwrap();
+        assert_eq!(ids(records), vec!["c"]);
+    }
+
+    #[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+    async fn test_pages_through_a_crowded_height() {
+        let source = TestSource::new(vec![("e", 20), ("d", 20), ("c", 20), ("b", 20), ("a", 10)]);
+
+        let records = HistoryPager::new(&source)
+            .page_size(2)
+            .collect_all()
+            .await
+            .unwrap();
+        assert_eq!(ids(records), vec!["e", "d", "c", "b", "a"]);
+        assert_eq!(
+            *source.requests.lock().unwrap(),
+            vec![
+                (None, 2, None),
+                (Some(20), 2, None),
+                (Some(20), 2, Some(2)),
+                (Some(20), 2, Some(3)),
+            ]
+        );
+    }
+
+    #[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+    async fn test_widens_the_limit_without_pages() {
+        let source =
+            TestSource::new(vec![("d", 20), ("c", 20), ("b", 20), ("a", 10)]).without_page();
+
+        let records = HistoryPager::new(&source)
+            .page_size(2)
+            .collect_all()
+            .await
+            .unwrap();
+        assert_eq!(ids(records), vec!["d", "c", "b", "a"]);
+        assert_eq!(
+            *source.requests.lock().unwrap(),
+            vec![
+                (None, 2, None),
+                (Some(20), 2, None),
+                (Some(20), 4, None),
+                (Some(10), 2, None),
+            ]
+        );
+    }
+
+    #[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+    async fn test_crowded_height_beyond_the_limit_fails() {
+        let ids: Vec<String> = (0..=MAX_PAGE_SIZE).map(|id| id.to_string()).collect();
+        let source =
+            TestSource::new(ids.iter().map(|id| (id.as_str(), 20)).collect()).without_page();
+
+        let result = HistoryPager::new(&source).collect_all().await;
+        match result {
+            Err(PagerError::PageOverflow { height, limit }) => {
+                assert_eq!(height, 20);
+                assert_eq!(limit, MAX_PAGE_SIZE);
+            }
+            _ => panic!("expected a page overflow"),
+        }
+    }
+
+    #[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+    async fn test_fills_from_indexer() {
+        let fills = r#"{"fills":[
+            {"id":"2","side":"SELL","liquidity":"TAKER","type":"LIMIT","market":"BTC-USD","marketType":"PERPETUAL","price":"50100","size":"0.01","fee":"0.25","createdAt":"2024-01-01T00:01:00.000Z","createdAtHeight":"101","orderId":"o2","clientMetadata":"0"},
+            {"id":"1","side":"BUY","liquidity":"MAKER","type":"LIMIT","market":"BTC-USD","marketType":"PERPETUAL","price":"50000","size":"0.01","fee":"-0.05","createdAt":"2024-01-01T00:00:00.000Z","createdAtHeight":"100","orderId":"o1","clientMetadata":"0"}
+        ]}"#;
+        let client =
+            IndexerClient::from_fixtures(IndexerFixtures::new().with_response("/v4/fills", fills));
+
+        let mut pager =
+            HistoryPager::fills(&client, FillsRequest::new("dydx1address".to_string(), 0));
+        let newest = pager.next_record().await.unwrap().unwrap();
+        assert_eq!(newest.created_at_height, 101);
+        let oldest = pager.next_record().await.unwrap().unwrap();
+        assert_eq!(oldest.id, "1");
+        let end = pager.next_record().await;
+        assert!(end.is_none());
+    }
+}
@@ -0,0 +1,215 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use super::errors::APIError;
+
+/// Groups of indexer endpoints that are budgeted separately.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum EndpointGroup {
+    /// Endpoints of [`AccountsClient`](super::indexer_client::AccountsClient).
+    Accounts,
+    /// Endpoints of [`MarketsClient`](super::indexer_client::MarketsClient).
+    Markets,
+    /// Endpoints of [`UtilityClient`](super::indexer_client::UtilityClient).
+    Utility,
+    /// Endpoints of [`AffiliatesClient`](super::indexer_client::AffiliatesClient).
+    Affiliates,
+    /// Endpoints of [`VaultsClient`](super::indexer_client::VaultsClient).
+    Vaults,
+}
+
+/// What a request does when its endpoint group has run out of tokens.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RateLimitMode {
+    /// Sleep until a token is available.
+    Wait,
+    /// Return [`APIError::Throttled`] without sending the request.
+    FailFast,
+}
+
+/// Allows `requests` requests per `period`, in bursts of up to `requests`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RateLimit {
+    requests: u32,
+    period: Duration,
+}
+
+impl RateLimit {
+    pub fn new(requests: u32, period: Duration) -> Self {
+        RateLimit { requests, period }
+    }
+
+    pub fn requests(&self) -> u32 {
+        self.requests
+    }
+
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+}
+
+/// A client side token bucket per [`EndpointGroup`]. Share one limiter
+/// between several [`IndexerClient`]s through an `Arc` to keep them within a
+/// common budget. Groups without a limit are not throttled.
+///
+/// [`IndexerClient`]: super::indexer_client::IndexerClient
+#[derive(Debug)]
+pub struct RateLimiter {
+    mode: RateLimitMode,
+    buckets: HashMap<EndpointGroup, Mutex<TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(mode: RateLimitMode) -> Self {
+        RateLimiter {
+            mode,
+            buckets: HashMap::new(),
+        }
+    }
+
+    pub fn with_limit(mut self, group: EndpointGroup, limit: RateLimit) -> Self {
+        self.buckets
+            .insert(group, Mutex::new(TokenBucket::new(limit)));
+        self
+    }
+
+    pub fn mode(&self) -> RateLimitMode {
+        self.mode
+    }
+
+    /// Takes a token for a request to `group`, returning how long to wait
+    /// before sending it. In [`RateLimitMode::FailFast`] the delay is always
+    /// zero and an empty bucket is an error instead.
+    pub(crate) fn acquire(&self, group: EndpointGroup) -> Result<Duration, APIError> {
+        let mut bucket = match self.buckets.get(&group) {
+            Some(bucket) => match bucket.lock() {
+                Ok(bucket) => bucket,
+                Err(poisoned) => poisoned.into_inner(),
+            },
+            None => return Ok(Duration::ZERO),
+        };
+
+        let now = Instant::now();
+        match self.mode {
+            RateLimitMode::Wait => Ok(bucket.reserve(now)),
+            RateLimitMode::FailFast => match bucket.try_take(now) {
+                Ok(()) => Ok(Duration::ZERO),
+                Err(retry_after) => Err(APIError::Throttled { retry_after }),
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    tokens_per_second: f64,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: RateLimit) -> Self {
+        let capacity = f64::from(limit.requests.max(1));
+        TokenBucket {
+            capacity,
+            tokens_per_second: capacity / limit.period.as_secs_f64().max(f64::EPSILON),
+            tokens: capacity,
+            refilled_at: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.refilled_at);
+        self.tokens =
+            (self.tokens + elapsed.as_secs_f64() * self.tokens_per_second).min(self.capacity);
+        self.refilled_at = now;
+    }
+
+    /// Takes a token, going into debt if there is none, and returns how long
+    /// until the debt is paid off. Waiting callers are thereby served in the
+    /// order they arrived.
+    fn reserve(&mut self, now: Instant) -> Duration {
+        self.refill(now);
+        self.tokens -= 1.0;
+        self.time_until_available(0.0)
+    }
+
+    fn try_take(&mut self, now: Instant) -> Result<(), Duration> {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(self.time_until_available(1.0))
+        }
+    }
+
+    fn time_until_available(&self, tokens: f64) -> Duration {
+        if self.tokens >= tokens {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((tokens - self.tokens) / self.tokens_per_second)
+        }
+    }
+}
+
+// ========================================
+// Tests
+// ========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fail_fast_limits_each_group() {
+        let limiter = RateLimiter::new(RateLimitMode::FailFast).with_limit(
+            EndpointGroup::Accounts,
+            RateLimit::new(2, Duration::from_secs(1)),
+        );
+
+        assert_eq!(
+            limiter.acquire(EndpointGroup::Accounts).unwrap(),
+            Duration::ZERO
+        );
+        assert_eq!(
+            limiter.acquire(EndpointGroup::Accounts).unwrap(),
+            Duration::ZERO
+        );
+        match limiter.acquire(EndpointGroup::Accounts) {
+            Err(APIError::Throttled { retry_after }) => {
+                assert!(retry_after > Duration::from_millis(400));
+                assert!(retry_after <= Duration::from_millis(500));
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+
+        for _ in 0..10 {
+            assert_eq!(
+                limiter.acquire(EndpointGroup::Markets).unwrap(),
+                Duration::ZERO
+            );
+        }
+    }
+
+    #[test]
+    fn test_wait_reserves_tokens_in_order() {
+        let limiter = RateLimiter::new(RateLimitMode::Wait).with_limit(
+            EndpointGroup::Markets,
+            RateLimit::new(1, Duration::from_secs(1)),
+        );
+
+        assert_eq!(
+            limiter.acquire(EndpointGroup::Markets).unwrap(),
+            Duration::ZERO
+        );
+        let second = limiter.acquire(EndpointGroup::Markets).unwrap();
+        let third = limiter.acquire(EndpointGroup::Markets).unwrap();
+        assert!(second > Duration::from_millis(900) && second <= Duration::from_secs(1));
+        assert!(third > Duration::from_millis(1900) && third <= Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,268 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+const DEFAULT_MAX_ENTRIES: usize = 1024;
+
+/// Endpoints of [`MarketsClient`] whose responses can be cached.
+///
+/// [`MarketsClient`]: super::indexer_client::MarketsClient
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum CachedEndpoint {
+    PerpetualMarkets,
+    Orderbook,
+    Trades,
+    Candles,
+    HistoricalFunding,
+    Sparklines,
+}
+
+impl CachedEndpoint {
+    fn from_path(path: &str) -> Option<Self> {
+        let endpoint = match path {
+            "/v4/perpetualMarkets" => CachedEndpoint::PerpetualMarkets,
+            "/v4/sparklines" => CachedEndpoint::Sparklines,
+            _ if path.starts_with("/v4/orderbooks/perpetualMarket/") => CachedEndpoint::Orderbook,
+            _ if path.starts_with("/v4/trades/perpetualMarket/") => CachedEndpoint::Trades,
+            _ if path.starts_with("/v4/candles/perpetualMarket/") => CachedEndpoint::Candles,
+            _ if path.starts_with("/v4/historicalFunding/") => CachedEndpoint::HistoricalFunding,
+            _ => return None,
+        };
+        Some(endpoint)
+    }
+}
+
+/// Counters of a [`ResponseCache`] since it was created or its stats reset.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct CacheStats {
+    /// Requests answered from the cache without contacting the indexer.
+    pub hits: u64,
+    /// Requests answered with a new response from the indexer.
+    pub misses: u64,
+    /// Expired responses the indexer confirmed unchanged with
+    /// `304 Not Modified`.
+    pub revalidated: u64,
+}
+
+/// An opt-in cache of [`MarketsClient`] responses, installed with
+/// [`IndexerClient::with_cache`] and shareable between clients through an
+/// `Arc`.
+///
+/// Only endpoints given a TTL are cached, each request url separately. Once
+/// a response is older than its TTL it is revalidated with `If-None-Match`
+/// if the indexer sent an `ETag`, and fetched again otherwise. A TTL of zero
+/// revalidates on every request.
+///
+/// At most [`ResponseCache::with_max_entries`] responses are kept; when
+/// full, expired responses are dropped first, then the oldest.
+///
+/// [`MarketsClient`]: super::indexer_client::MarketsClient
+/// [`IndexerClient::with_cache`]: super::indexer_client::IndexerClient::with_cache
+#[derive(Debug)]
+pub struct ResponseCache {
+    ttls: HashMap<CachedEndpoint, Duration>,
+    max_entries: usize,
+    state: Mutex<CacheState>,
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        ResponseCache {
+            ttls: HashMap::new(),
+            max_entries: DEFAULT_MAX_ENTRIES,
+            state: Mutex::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<String, CacheEntry>,
+    stats: CacheStats,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    endpoint: CachedEndpoint,
+    body: String,
+    etag: Option<String>,
+    fetched_at: Instant,
+}
+
+/// What the cache holds for a request.
+pub(crate) enum CacheLookup {
+    /// The endpoint is not cached.
+    Uncached,
+    Fresh(String),
+    /// Expired or absent; revalidate with the etag if there is one.
+    Stale {
+        etag: Option<String>,
+    },
+}
+
+impl ResponseCache {
+    pub fn new() -> Self {
+        ResponseCache::default()
+    }
+
+    pub fn with_ttl(mut self, endpoint: CachedEndpoint, ttl: Duration) -> Self {
+        self.ttls.insert(endpoint, ttl);
+        self
+    }
+
+    /// Number of responses kept, 1024 by default.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries.max(1);
+        self
+    }
+
+    pub fn ttl(&self, endpoint: CachedEndpoint) -> Option<Duration> {
+        self.ttls.get(&endpoint).copied()
+    }
+
+    /// Drops the cached responses of `endpoint`, e.g. after a market's
+    /// parameters changed.
+    pub fn invalidate(&self, endpoint: CachedEndpoint) {
+        self.state()
+            .entries
+            .retain(|_, entry| entry.endpoint != endpoint);
+    }
+
+    pub fn invalidate_all(&self) {
+        self.state().entries.clear();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.state().stats
+    }
+
+    pub fn reset_stats(&self) {
+        self.state().stats = CacheStats::default();
+    }
+
+    pub(crate) fn lookup(&self, path: &str, url: &str) -> CacheLookup {
+        let Some(ttl) = CachedEndpoint::from_path(path).and_then(|endpoint| self.ttl(endpoint))
+        else {
+            return CacheLookup::Uncached;
+        };
+
+        let mut state = self.state();
+        let etag = match state.entries.get(url) {
+            Some(entry) if entry.fetched_at.elapsed() < ttl => {
+                let body = entry.body.clone();
+                state.stats.hits += 1;
+                return CacheLookup::Fresh(body);
+            }
+            Some(entry) => entry.etag.clone(),
+            None => None,
+        };
+        CacheLookup::Stale { etag }
+    }
+
+    pub(crate) fn store(&self, path: &str, url: &str, body: &str, etag: Option<String>) {
+        let Some(endpoint) = CachedEndpoint::from_path(path) else {
+            return;
+        };
+        if self.ttl(endpoint).is_none() {
+            return;
+        }
+        let mut state = self.state();
+        state.stats.misses += 1;
+        if !state.entries.contains_key(url) && state.entries.len() >= self.max_entries {
+            self.evict(&mut state.entries);
+        }
+        state.entries.insert(
+            url.to_string(),
+            CacheEntry {
+                endpoint,
+                body: body.to_string(),
+                etag,
+                fetched_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Marks the response to `url` as confirmed unchanged and returns it.
+    pub(crate) fn revalidated(&self, url: &str) -> Option<String> {
+        let mut state = self.state();
+        let entry = state.entries.get_mut(url)?;
+        entry.fetched_at = Instant::now();
+        let body = entry.body.clone();
+        state.stats.revalidated += 1;
+        Some(body)
+    }
+
+    /// Makes room for one entry: drops the expired ones, or the oldest if
+    /// none has expired.
+    fn evict(&self, entries: &mut HashMap<String, CacheEntry>) {
+        entries.retain(|_, entry| {
+            self.ttl(entry.endpoint)
+                .is_some_and(|ttl| entry.fetched_at.elapsed() < ttl)
+        });
+        if entries.len() < self.max_entries {
+            return;
+        }
+        let oldest = entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.fetched_at)
+            .map(|(url, _)| url.clone());
+        if let Some(url) = oldest {
+            entries.remove(&url);
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, CacheState> {
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+// ========================================
+// Tests
+// ========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MARKETS: &str = "/v4/perpetualMarkets";
+    const SPARKLINES: &str = "/v4/sparklines";
+
+    #[test]
+    fn test_endpoint_without_ttl_is_not_stored() {
+        let cache =
+            ResponseCache::new().with_ttl(CachedEndpoint::Sparklines, Duration::from_secs(60));
+
+        cache.store(MARKETS, "markets", "{}", None);
+        assert_eq!(cache.stats(), CacheStats::default());
+        assert!(cache.state().entries.is_empty());
+        assert!(matches!(
+            cache.lookup(MARKETS, "markets"),
+            CacheLookup::Uncached
+        ));
+    }
+
+    #[test]
+    fn test_full_cache_evicts_expired_then_oldest() {
+        let cache = ResponseCache::new()
+            .with_ttl(CachedEndpoint::PerpetualMarkets, Duration::ZERO)
+            .with_ttl(CachedEndpoint::Sparklines, Duration::from_secs(60))
+            .with_max_entries(2);
+
+        cache.store(SPARKLINES, "sparklines-1", "{}", None);
+        cache.store(MARKETS, "markets", "{}", None);
+        cache.store(SPARKLINES, "sparklines-2", "{}", None);
+        let mut urls: Vec<String> = cache.state().entries.keys().cloned().collect();
+        urls.sort();
+        assert_eq!(urls, vec!["sparklines-1", "sparklines-2"]);
+
+        cache.store(SPARKLINES, "sparklines-3", "{}", None);
+        let mut urls: Vec<String> = cache.state().entries.keys().cloned().collect();
+        urls.sort();
+        assert_eq!(urls, vec!["sparklines-2", "sparklines-3"]);
+    }
+}
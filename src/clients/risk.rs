@@ -0,0 +1,325 @@
+use std::collections::{BTreeMap, HashMap};
+
+use rust_decimal::Decimal;
+
+use super::errors::RiskError;
+use super::indexer_client_types::{
+    PerpetualMarketResponseStruct, PositionSide, SubAccountResponseObject,
+};
+use crate::constants::OrderSide;
+
+const USDC_SYMBOL: &str = "USDC";
+
+/// The balances margin is computed from: the USDC balance and the signed
+/// size of every perpetual position, short positions negative.
+///
+/// Everything here is pure: margin is computed at the oracle prices of the
+/// markets passed in, the same way the protocol and the indexer compute
+/// `equity` and `free_collateral`.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Portfolio {
+    pub quote_balance: Decimal,
+    pub positions: BTreeMap<String, Decimal>,
+}
+
+/// Margin of a single perpetual position.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PositionRisk {
+    pub market: String,
+    pub size: Decimal,
+    pub oracle_price: Decimal,
+    /// Signed value of the position, `size * oracle_price`.
+    pub value: Decimal,
+    pub notional: Decimal,
+    pub initial_margin_requirement: Decimal,
+    pub maintenance_margin_requirement: Decimal,
+    /// Notional over the equity of the whole account, `None` when equity is
+    /// not positive.
+    pub leverage: Option<Decimal>,
+    /// Oracle price at which the account falls below its maintenance margin,
+    /// the other markets unchanged. `None` if no positive price does.
+    pub liquidation_price: Option<Decimal>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct MarginSummary {
+    pub equity: Decimal,
+    pub notional: Decimal,
+    pub initial_margin_requirement: Decimal,
+    pub maintenance_margin_requirement: Decimal,
+    /// Equity above the initial margin requirement, what the indexer reports
+    /// as `free_collateral`.
+    pub free_collateral: Decimal,
+    pub leverage: Option<Decimal>,
+    pub positions: Vec<PositionRisk>,
+}
+
+impl MarginSummary {
+    /// Whether the account can be liquidated at the current oracle prices.
+    pub fn is_liquidatable(&self) -> bool {
+        self.equity < self.maintenance_margin_requirement
+    }
+}
+
+/// Margin before and after a hypothetical order fills.
+#[derive(Clone, PartialEq, Debug)]
+pub struct OrderImpact {
+    pub before: MarginSummary,
+    pub after: MarginSummary,
+}
+
+impl OrderImpact {
+    /// Whether the account still meets its initial margin requirement once
+    /// the order filled.
+    pub fn is_collateralized(&self) -> bool {
+        self.after.free_collateral >= Decimal::ZERO
+    }
+}
+
+impl Portfolio {
+    pub fn new(quote_balance: Decimal, positions: BTreeMap<String, Decimal>) -> Self {
+        Portfolio {
+            quote_balance,
+            positions,
+        }
+    }
+
+    /// Reads the USDC balance and open positions of a subaccount. Sizes are
+    /// signed by their `side`, whichever way the indexer reports them.
+    pub fn from_subaccount(subaccount: &SubAccountResponseObject) -> Self {
+        let quote_balance = subaccount
+            .asset_positions
+            .iter()
+            .flat_map(|positions| positions.values())
+            .filter(|position| position.symbol == USDC_SYMBOL)
+            .map(|position| signed(position.size, position.side.as_ref()))
+            .sum();
+
+        let positions = subaccount
+            .open_perpetual_positions
+            .iter()
+            .flat_map(|positions| positions.values())
+            .filter(|position| !position.size.is_zero())
+            .map(|position| {
+                (
+                    position.market.clone(),
+                    signed(position.size, position.side.as_ref()),
+                )
+            })
+            .collect();
+
+        Portfolio::new(quote_balance, positions)
+    }
+
+    /// The portfolio once an order of `size` fills at `price`, fees aside.
+    pub fn with_order(&self, market: &str, side: OrderSide, size: Decimal, price: Decimal) -> Self {
+        let size = match side {
+            OrderSide::BUY => size.abs(),
+            OrderSide::SELL => -size.abs(),
+        };
+
+        let mut portfolio = self.clone();
+        portfolio.quote_balance -= size * price;
+        let position = portfolio
+            .positions
+            .entry(market.to_string())
+            .or_insert(Decimal::ZERO);
+        *position += size;
+        if position.is_zero() {
+            portfolio.positions.remove(market);
+        }
+        portfolio
+    }
+
+    /// Margin of the portfolio at the oracle prices of `markets`, keyed by
+    /// ticker as in [`PerpetualMarketsResponse`].
+    ///
+    /// [`PerpetualMarketsResponse`]: super::indexer_client_types::PerpetualMarketsResponse
+    pub fn margin(
+        &self,
+        markets: &HashMap<String, PerpetualMarketResponseStruct>,
+    ) -> Result<MarginSummary, RiskError> {
+        let mut equity = self.quote_balance;
+        let mut notional = Decimal::ZERO;
+        let mut initial_margin_requirement = Decimal::ZERO;
+        let mut maintenance_margin_requirement = Decimal::ZERO;
+        let mut positions = Vec::with_capacity(self.positions.len());
+
+        for (ticker, size) in &self.positions {
+            let market = markets
+                .get(ticker)
+                .ok_or_else(|| RiskError::UnknownMarket(ticker.clone()))?;
+            let value = size * market.oracle_price;
+            let position_notional = value.abs();
+            let position = PositionRisk {
+                market: ticker.clone(),
+                size: *size,
+                oracle_price: market.oracle_price,
+                value,
+                notional: position_notional,
+                initial_margin_requirement: position_notional * market.initial_margin_fraction,
+                maintenance_margin_requirement: position_notional
+                    * market.maintenance_margin_fraction,
+                leverage: None,
+                liquidation_price: None,
+            };
+
+            equity += value;
+            notional += position_notional;
+            initial_margin_requirement += position.initial_margin_requirement;
+            maintenance_margin_requirement += position.maintenance_margin_requirement;
+            positions.push(position);
+        }
+
+        for position in &mut positions {
+            let market = &markets[&position.market];
+            position.leverage = leverage(position.notional, equity);
+            position.liquidation_price = liquidation_price(
+                position,
+                market.maintenance_margin_fraction,
+                equity,
+                maintenance_margin_requirement,
+            );
+        }
+
+        Ok(MarginSummary {
+            equity,
+            notional,
+            initial_margin_requirement,
+            maintenance_margin_requirement,
+            free_collateral: equity - initial_margin_requirement,
+            leverage: leverage(notional, equity),
+            positions,
+        })
+    }
+
+    /// Margin now and once an order of `size` fills at `price`, fees aside.
+    pub fn order_impact(
+        &self,
+        markets: &HashMap<String, PerpetualMarketResponseStruct>,
+        market: &str,
+        side: OrderSide,
+        size: Decimal,
+        price: Decimal,
+    ) -> Result<OrderImpact, RiskError> {
+        Ok(OrderImpact {
+            before: self.margin(markets)?,
+            after: self.with_order(market, side, size, price).margin(markets)?,
+        })
+    }
+}
+
+fn signed(size: Decimal, side: Option<&PositionSide>) -> Decimal {
+    match side {
+        Some(PositionSide::SHORT) => -size.abs(),
+        Some(PositionSide::LONG) => size.abs(),
+        None => size,
+    }
+}
+
+fn leverage(notional: Decimal, equity: Decimal) -> Option<Decimal> {
+    if equity <= Decimal::ZERO {
+        return None;
+    }
+    Some(notional / equity)
+}
+
+/// Solves `equity(p) = maintenance(p)` for the price `p` of one position:
+/// `E + s * (p - p0) = M' + |s| * p * mmf`, `M'` being the requirement of
+/// the other positions.
+fn liquidation_price(
+    position: &PositionRisk,
+    maintenance_margin_fraction: Decimal,
+    equity: Decimal,
+    maintenance_margin_requirement: Decimal,
+) -> Option<Decimal> {
+    let others = maintenance_margin_requirement - position.maintenance_margin_requirement;
+    let denominator = position.size - position.size.abs() * maintenance_margin_fraction;
+    if denominator.is_zero() {
+        return None;
+    }
+    let price = (others - equity + position.value).checked_div(denominator)?;
+    (price > Decimal::ZERO).then_some(price)
+}
+
+// ========================================
+// Tests
+// ========================================
+
+#[cfg(test)]
+mod tests {
+    use super::super::indexer_client_types::PerpetualMarketsResponse;
+    use super::*;
+
+    fn market(ticker: &str, oracle_price: &str) -> String {
+        format!(
+            r#""{ticker}":{{"clobPairId":"0","ticker":"{ticker}","status":"ACTIVE","lastPrice":"{oracle_price}","oraclePrice":"{oracle_price}","priceChange24H":"0","volume24H":"0","trades24H":0,"nextFundingRate":"0","initialMarginFraction":"0.05","maintenanceMarginFraction":"0.03","basePositionNotional":"1000000","openInterest":"0","atomicResolution":-10,"quantumConversionExponent":-9,"tickSize":"1","stepSize":"0.0001","stepBaseQuantums":1000000,"subticksPerTick":100000}}"#
+        )
+    }
+
+    fn position(market: &str, side: &str, size: &str) -> String {
+        format!(
+            r#""{market}":{{"market":"{market}","status":"OPEN","side":"{side}","size":"{size}","maxSize":"{size}","entryPrice":"0","realizedPnl":"0","createdAt":"2024-01-01T00:00:00.000Z","createdAtHeight":"1","sumOpen":"0","sumClose":"0","netFunding":"0","unrealizedPnl":"0","closedAt":null,"exitPrice":null}}"#
+        )
+    }
+
+    #[test]
+    fn test_margin_matches_indexer() {
+        let markets: PerpetualMarketsResponse = serde_json::from_str(&format!(
+            r#"{{"markets":{{{},{}}}}}"#,
+            market("BTC-USD", "50000"),
+            market("ETH-USD", "2500")
+        ))
+        .unwrap();
+        let subaccount: SubAccountResponseObject = serde_json::from_str(&format!(
+            r#"{{"address":"dydx1address","subaccountNumber":0,"equity":"10000","freeCollateral":"6250","openPerpetualPositions":{{{},{}}},"assetPositions":{{"USDC":{{"symbol":"USDC","side":"SHORT","size":"15000","assetId":"0"}}}},"marginEnabled":true}}"#,
+            position("BTC-USD", "LONG", "1"),
+            position("ETH-USD", "SHORT", "-10")
+        ))
+        .unwrap();
+        let decimal = |value: &str| value.parse::<Decimal>().unwrap();
+
+        let portfolio = Portfolio::from_subaccount(&subaccount);
+        let margin = portfolio.margin(&markets.markets).unwrap();
+        assert_eq!(margin.equity, subaccount.equity);
+        assert_eq!(margin.free_collateral, subaccount.free_collateral);
+        assert_eq!(margin.maintenance_margin_requirement, decimal("2250"));
+        assert_eq!(margin.leverage, Some(decimal("7.5")));
+        assert!(!margin.is_liquidatable());
+
+        let btc = &margin.positions[0];
+        assert_eq!(btc.market, "BTC-USD");
+        assert_eq!(btc.leverage, Some(decimal("5")));
+        assert_eq!(
+            btc.liquidation_price,
+            Some(decimal("40750") / decimal("0.97"))
+        );
+        let eth = &margin.positions[1];
+        assert_eq!(eth.size, decimal("-10"));
+        assert_eq!(
+            eth.liquidation_price,
+            Some(decimal("33500") / decimal("10.3"))
+        );
+
+        let impact = portfolio
+            .order_impact(
+                &markets.markets,
+                "BTC-USD",
+                OrderSide::BUY,
+                decimal("0.1"),
+                decimal("50000"),
+            )
+            .unwrap();
+        assert_eq!(impact.before, margin);
+        assert_eq!(impact.after.equity, decimal("10000"));
+        assert_eq!(impact.after.free_collateral, decimal("6000"));
+        assert!(impact.is_collateralized());
+
+        let unknown =
+            portfolio.with_order("SOL-USD", OrderSide::SELL, decimal("1"), decimal("100"));
+        assert_eq!(
+            unknown.margin(&markets.markets),
+            Err(RiskError::UnknownMarket("SOL-USD".to_string()))
+        );
+    }
+}
@@ -0,0 +1,598 @@
+#[cfg(feature = "blocking")]
+use std::net::TcpStream;
+
+#[cfg(not(feature = "blocking"))]
+use futures_util::{SinkExt, StreamExt};
+use maybe_async::maybe_async;
+use serde::de::DeserializeOwned;
+#[cfg(not(feature = "blocking"))]
+use tokio::net::TcpStream;
+#[cfg(not(feature = "blocking"))]
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tungstenite::Message;
+#[cfg(feature = "blocking")]
+use tungstenite::{stream::MaybeTlsStream, WebSocket};
+
+use super::errors::SocketError;
+use super::indexer_client::IndexerConfig;
+use super::socket_client_types::{
+    ChannelBatchDataMessage, ChannelDataMessage, ChannelMessage, ConnectedMessage, ErrorMessage,
+    RawSocketMessage, SocketChannel, SocketMessage, SocketRequest, SubscribedMessage,
+    UnsubscribedMessage,
+};
+
+#[cfg(feature = "blocking")]
+type Socket = WebSocket<MaybeTlsStream<TcpStream>>;
+#[cfg(not(feature = "blocking"))]
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// A connection to the indexer websocket.
+///
+/// Without the `blocking` feature the client runs on tokio and reading
+/// waits without blocking the runtime. With it every call blocks the
+/// calling thread.
+pub struct SocketClient {
+    socket: Socket,
+    connection_id: String,
+}
+
+#[maybe_async]
+impl SocketClient {
+    /// Connects to the indexer websocket endpoint and waits for the
+    /// `connected` message that opens every session.
+    pub async fn connect(indexer_config: &IndexerConfig) -> Result<Self, SocketError> {
+        let socket = open(indexer_config.websocket_endpoint()).await?;
+
+        let mut client = SocketClient {
+            socket,
+            connection_id: String::new(),
+        };
+
+        match client.read_message().await? {
+            SocketMessage::Connected(connected) => {
+                client.connection_id = connected.connection_id;
+                Ok(client)
+            }
+            message => Err(SocketError::new(format!(
+                "Expected connected message, received: {:?}",
+                message
+            ))),
+        }
+    }
+
+    pub fn connection_id(&self) -> &str {
+        &self.connection_id
+    }
+
+    pub async fn subscribe(
+        &mut self,
+        channel: SocketChannel,
+        batched: bool,
+    ) -> Result<(), SocketError> {
+        self.send(SocketRequest {
+            request_type: "subscribe",
+            channel: channel.name(),
+            id: channel.id(),
+            batched: Some(batched),
+        })
+        .await
+    }
+
+    pub async fn unsubscribe(&mut self, channel: SocketChannel) -> Result<(), SocketError> {
+        self.send(SocketRequest {
+            request_type: "unsubscribe",
+            channel: channel.name(),
+            id: channel.id(),
+            batched: None,
+        })
+        .await
+    }
+
+    /// Waits for the next indexer message. Pings are answered while reading,
+    /// so the caller only needs to keep calling this.
+    pub async fn read_message(&mut self) -> Result<SocketMessage, SocketError> {
+        loop {
+            match self.read_frame().await? {
+                Message::Text(text) => return parse_message(text.as_str()),
+                Message::Close(frame) => {
+                    return Err(SocketError::new(format!(
+                        "Connection closed by indexer: {:?}",
+                        frame
+                    )))
+                }
+                _ => continue,
+            }
+        }
+    }
+
+    pub async fn close(&mut self) -> Result<(), SocketError> {
+        match self.socket.close(None).await {
+            Ok(()) => Ok(()),
+            Err(e) => Err(SocketError::new(e.to_string())),
+        }
+    }
+
+    async fn send(&mut self, request: SocketRequest) -> Result<(), SocketError> {
+        let body = match serde_json::to_string(&request) {
+            Ok(body) => body,
+            Err(e) => return Err(SocketError::new(e.to_string())),
+        };
+
+        match self.socket.send(Message::text(body)).await {
+            Ok(()) => Ok(()),
+            Err(e) => Err(SocketError::new(e.to_string())),
+        }
+    }
+}
+
+impl SocketClient {
+    #[cfg(feature = "blocking")]
+    fn read_frame(&mut self) -> Result<Message, SocketError> {
+        match self.socket.read() {
+            Ok(message) => Ok(message),
+            Err(e) => Err(SocketError::new(e.to_string())),
+        }
+    }
+
+    #[cfg(not(feature = "blocking"))]
+    async fn read_frame(&mut self) -> Result<Message, SocketError> {
+        match self.socket.next().await {
+            Some(Ok(message)) => Ok(message),
+            Some(Err(e)) => Err(SocketError::new(e.to_string())),
+            None => Err(SocketError::new("Connection closed".to_string())),
+        }
+    }
+}
+
+#[cfg(feature = "blocking")]
+fn open(endpoint: &str) -> Result<Socket, SocketError> {
+    match tungstenite::connect(endpoint) {
+        Ok((socket, _)) => Ok(socket),
+        Err(e) => Err(SocketError::new(e.to_string())),
+    }
+}
+
+#[cfg(not(feature = "blocking"))]
+async fn open(endpoint: &str) -> Result<Socket, SocketError> {
+    match tokio_tungstenite::connect_async(endpoint).await {
+        Ok((socket, _)) => Ok(socket),
+        Err(e) => Err(SocketError::new(e.to_string())),
+    }
+}
+
+pub(crate) fn parse_message(text: &str) -> Result<SocketMessage, SocketError> {
+    let raw: RawSocketMessage = match serde_json::from_str(text) {
+        Ok(raw) => raw,
+        Err(e) => return Err(SocketError::new(format!("{}: {}", e, text))),
+    };
+
+    Ok(match raw {
+        RawSocketMessage::Connected {
+            connection_id,
+            message_id,
+        } => SocketMessage::Connected(ConnectedMessage {
+            connection_id,
+            message_id,
+        }),
+        RawSocketMessage::Subscribed {
+            connection_id,
+            message_id,
+            channel,
+            id,
+            contents,
+        } => {
+            let envelope = Envelope {
+                connection_id,
+                message_id,
+                id,
+                version: None,
+            };
+            SocketMessage::Subscribed(match channel.as_str() {
+                "v4_markets" => SubscribedMessage::Markets(envelope.with(contents)?),
+                "v4_orderbook" => SubscribedMessage::Orderbook(envelope.with(contents)?),
+                "v4_trades" => SubscribedMessage::Trades(envelope.with(contents)?),
+                "v4_candles" => SubscribedMessage::Candles(envelope.with(contents)?),
+                "v4_subaccounts" => SubscribedMessage::Subaccounts(envelope.with(contents)?),
+                _ => return Err(unknown_channel(&channel)),
+            })
+        }
+        RawSocketMessage::ChannelData {
+            connection_id,
+            message_id,
+            channel,
+            id,
+            version,
+            contents,
+        } => {
+            let envelope = Envelope {
+                connection_id,
+                message_id,
+                id,
+                version,
+            };
+            SocketMessage::ChannelData(match channel.as_str() {
+                "v4_markets" => ChannelDataMessage::Markets(envelope.with(contents)?),
+                "v4_orderbook" => ChannelDataMessage::Orderbook(envelope.with(contents)?),
+                "v4_trades" => ChannelDataMessage::Trades(envelope.with(contents)?),
+                "v4_candles" => ChannelDataMessage::Candles(envelope.with(contents)?),
+                "v4_subaccounts" => ChannelDataMessage::Subaccounts(envelope.with(contents)?),
+                _ => return Err(unknown_channel(&channel)),
+            })
+        }
+        RawSocketMessage::ChannelBatchData {
+            connection_id,
+            message_id,
+            channel,
+            id,
+            version,
+            contents,
+        } => {
+            let envelope = Envelope {
+                connection_id,
+                message_id,
+                id,
+                version,
+            };
+            let contents = serde_json::Value::Array(contents);
+            SocketMessage::ChannelBatchData(match channel.as_str() {
+                "v4_markets" => ChannelBatchDataMessage::Markets(envelope.with(contents)?),
+                "v4_orderbook" => ChannelBatchDataMessage::Orderbook(envelope.with(contents)?),
+                "v4_trades" => ChannelBatchDataMessage::Trades(envelope.with(contents)?),
+                "v4_candles" => ChannelBatchDataMessage::Candles(envelope.with(contents)?),
+                "v4_subaccounts" => ChannelBatchDataMessage::Subaccounts(envelope.with(contents)?),
+                _ => return Err(unknown_channel(&channel)),
+            })
+        }
+        RawSocketMessage::Unsubscribed {
+            connection_id,
+            message_id,
+            channel,
+            id,
+        } => SocketMessage::Unsubscribed(UnsubscribedMessage {
+            connection_id,
+            message_id,
+            channel,
+            id,
+        }),
+        RawSocketMessage::Error {
+            message,
+            connection_id,
+            message_id,
+        } => SocketMessage::Error(ErrorMessage {
+            message,
+            connection_id,
+            message_id,
+        }),
+    })
+}
+
+struct Envelope {
+    connection_id: String,
+    message_id: u64,
+    id: Option<String>,
+    version: Option<String>,
+}
+
+impl Envelope {
+    fn with<T: DeserializeOwned>(
+        self,
+        contents: serde_json::Value,
+    ) -> Result<ChannelMessage<T>, SocketError> {
+        let contents = match serde_json::from_value(contents) {
+            Ok(contents) => contents,
+            Err(e) => return Err(SocketError::new(e.to_string())),
+        };
+
+        Ok(ChannelMessage {
+            connection_id: self.connection_id,
+            message_id: self.message_id,
+            id: self.id,
+            version: self.version,
+            contents,
+        })
+    }
+}
+
+fn unknown_channel(channel: &str) -> SocketError {
+    SocketError::new(format!("Unknown channel: {}", channel))
+}
+
+// ========================================
+// Tests
+// ========================================
+
+#[cfg(test)]
+mod tests {
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+    #[cfg(not(feature = "blocking"))]
+    use std::{sync::mpsc, time::Duration};
+
+    use rust_decimal::Decimal;
+    use tungstenite::WebSocket;
+
+    use super::*;
+    use crate::clients::indexer_client_types::CandleResolution;
+    #[cfg(not(feature = "blocking"))]
+    use crate::clients::{indexer_client_types::OrderbookResponse, orderbook::Orderbook};
+    use crate::constants::OrderStatus;
+
+    fn mock_indexer<F>(script: F) -> (IndexerConfig, thread::JoinHandle<()>)
+    where
+        F: FnOnce(&mut WebSocket<TcpStream>) + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut socket = tungstenite::accept(stream).unwrap();
+            socket
+                .send(Message::text(
+                    r#"{"type":"connected","connection_id":"conn-1","message_id":0}"#,
+                ))
+                .unwrap();
+            script(&mut socket);
+        });
+        let config = IndexerConfig::new(
+            "http://127.0.0.1".to_string(),
+            format!("ws://127.0.0.1:{port}"),
+        );
+        (config, handle)
+    }
+
+    fn read_request(socket: &mut WebSocket<TcpStream>) -> serde_json::Value {
+        loop {
+            if let Message::Text(text) = socket.read().unwrap() {
+                return serde_json::from_str(text.as_str()).unwrap();
+            }
+        }
+    }
+
+    #[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+    async fn test_orderbook_subscribe_and_unsubscribe() {
+        let (config, server) = mock_indexer(|socket| {
+            let request = read_request(socket);
+            assert_eq!(
+                request,
+                serde_json::json!({
+                    "type": "subscribe",
+                    "channel": "v4_orderbook",
+                    "id": "BTC-USD",
+                    "batched": false
+                })
+            );
+            socket
+                .send(Message::text(
+                    r#"{"type":"subscribed","connection_id":"conn-1","message_id":1,
+                    "channel":"v4_orderbook","id":"BTC-USD",
+                    "contents":{"bids":[{"price":"42000","size":"1.5"}],"asks":[{"price":"42001","size":"0.5"}]}}"#,
+                ))
+                .unwrap();
+            socket
+                .send(Message::text(
+                    r#"{"type":"channel_data","connection_id":"conn-1","message_id":2,
+                    "channel":"v4_orderbook","id":"BTC-USD","version":"1.0.0",
+                    "contents":{"bids":[["42000","0"]]}}"#,
+                ))
+                .unwrap();
+
+            let request = read_request(socket);
+            assert_eq!(
+                request,
+                serde_json::json!({
+                    "type": "unsubscribe",
+                    "channel": "v4_orderbook",
+                    "id": "BTC-USD"
+                })
+            );
+            socket
+                .send(Message::text(
+                    r#"{"type":"unsubscribed","connection_id":"conn-1","message_id":3,
+                    "channel":"v4_orderbook","id":"BTC-USD"}"#,
+                ))
+                .unwrap();
+        });
+
+        let mut client = SocketClient::connect(&config).await.unwrap();
+        assert_eq!(client.connection_id(), "conn-1");
+
+        let channel = SocketChannel::Orderbook("BTC-USD".to_string());
+        client.subscribe(channel.clone(), false).await.unwrap();
+
+        match client.read_message().await.unwrap() {
+            SocketMessage::Subscribed(SubscribedMessage::Orderbook(message)) => {
+                assert_eq!(message.message_id, 1);
+                assert_eq!(message.id, Some("BTC-USD".to_string()));
+            }
+            message => panic!("Unexpected message: {:?}", message),
+        }
+
+        match client.read_message().await.unwrap() {
+            SocketMessage::ChannelData(ChannelDataMessage::Orderbook(message)) => {
+                assert_eq!(message.message_id, 2);
+                assert_eq!(message.version, Some("1.0.0".to_string()));
+                let bids = message.contents.bids.unwrap();
+                assert_eq!(bids.len(), 1);
+                assert_eq!(bids[0].price, Decimal::new(42000, 0));
+                assert_eq!(bids[0].size, Decimal::ZERO);
+                assert!(message.contents.asks.is_none());
+            }
+            message => panic!("Unexpected message: {:?}", message),
+        }
+
+        client.unsubscribe(channel).await.unwrap();
+        match client.read_message().await.unwrap() {
+            SocketMessage::Unsubscribed(message) => {
+                assert_eq!(message.channel, "v4_orderbook");
+            }
+            message => panic!("Unexpected message: {:?}", message),
+        }
+
+        server.join().unwrap();
+    }
+
+    #[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+    async fn test_candles_and_subaccounts_channels() {
+        let (config, server) = mock_indexer(|socket| {
+            let request = read_request(socket);
+            assert_eq!(request["channel"], "v4_candles");
+            assert_eq!(request["id"], "ETH-USD/1MIN");
+            socket
+                .send(Message::text(
+                    r#"{"type":"channel_batch_data","connection_id":"conn-1","message_id":4,
+                    "channel":"v4_candles","id":"ETH-USD/1MIN","version":"1.0.0",
+                    "contents":[{"startedAt":"2024-01-01T00:00:00.000Z","ticker":"ETH-USD",
+                    "resolution":"1MIN","low":"2200","high":"2210","open":"2201","close":"2205",
+                    "baseTokenVolume":"10","usdVolume":"22050","trades":3,
+                    "startingOpenInterest":"100","id":"candle-1"}]}"#,
+                ))
+                .unwrap();
+
+            let request = read_request(socket);
+            assert_eq!(request["channel"], "v4_subaccounts");
+            assert_eq!(request["id"], "dydx1address/0");
+            socket
+                .send(Message::text(
+                    r#"{"type":"channel_data","connection_id":"conn-1","message_id":5,
+                    "channel":"v4_subaccounts","id":"dydx1address/0","version":"2.4.0",
+                    "contents":{"blockHeight":"1234","orders":[{"id":"order-1","clientId":"7",
+                    "status":"BEST_EFFORT_CANCELED","ticker":"ETH-USD"}]}}"#,
+                ))
+                .unwrap();
+            socket
+                .send(Message::text(
+                    r#"{"type":"error","message":"Invalid subscribe message","connection_id":"conn-1","message_id":6}"#,
+                ))
+                .unwrap();
+        });
+
+        let mut client = SocketClient::connect(&config).await.unwrap();
+        client
+            .subscribe(
+                SocketChannel::Candles("ETH-USD".to_string(), CandleResolution::OneMin),
+                true,
+            )
+            .await
+            .unwrap();
+
+        match client.read_message().await.unwrap() {
+            SocketMessage::ChannelBatchData(ChannelBatchDataMessage::Candles(message)) => {
+                assert_eq!(message.contents.len(), 1);
+            }
+            message => panic!("Unexpected message: {:?}", message),
+        }
+
+        client
+            .subscribe(
+                SocketChannel::Subaccounts("dydx1address".to_string(), 0),
+                false,
+            )
+            .await
+            .unwrap();
+
+        match client.read_message().await.unwrap() {
+            SocketMessage::ChannelData(ChannelDataMessage::Subaccounts(message)) => {
+                assert_eq!(message.contents.block_height, Some(1234));
+                let orders = message.contents.orders.unwrap();
+                assert_eq!(orders[0].client_id, Some("7".to_string()));
+                assert_eq!(orders[0].status, Some(OrderStatus::BestEffortCanceled));
+            }
+            message => panic!("Unexpected message: {:?}", message),
+        }
+
+        match client.read_message().await.unwrap() {
+            SocketMessage::Error(message) => {
+                assert_eq!(message.message, "Invalid subscribe message");
+            }
+            message => panic!("Unexpected message: {:?}", message),
+        }
+
+        server.join().unwrap();
+    }
+
+    #[cfg(not(feature = "blocking"))]
+    #[tokio::test]
+    async fn test_waiting_for_a_message_does_not_block_the_runtime() {
+        let (send_update, updates) = mpsc::channel::<()>();
+        let (config, server) = mock_indexer(move |socket| {
+            let unsubscribe = read_request(socket);
+            assert_eq!(unsubscribe["type"], "unsubscribe");
+            let subscribe = read_request(socket);
+            assert_eq!(subscribe["type"], "subscribe");
+            assert_eq!(subscribe["id"], "BTC-USD");
+
+            updates.recv().unwrap();
+            socket
+                .send(Message::text(
+                    r#"{"type":"subscribed","connection_id":"conn-1","message_id":1,
+                    "channel":"v4_orderbook","id":"BTC-USD","contents":{"bids":[],"asks":[]}}"#,
+                ))
+                .unwrap();
+        });
+
+        let mut client = SocketClient::connect(&config).await.unwrap();
+        let orderbook = Orderbook::from_snapshot(
+            "BTC-USD".to_string(),
+            &OrderbookResponse {
+                bids: vec![],
+                asks: vec![],
+            },
+        );
+        orderbook.resubscribe(&mut client).await.unwrap();
+
+        // A blocking read would stall this single threaded runtime and the
+        // timeout would never fire.
+        let waiting = tokio::time::timeout(Duration::from_millis(50), client.read_message()).await;
+        assert!(waiting.is_err());
+
+        send_update.send(()).unwrap();
+        match client.read_message().await.unwrap() {
+            SocketMessage::Subscribed(SubscribedMessage::Orderbook(message)) => {
+                assert_eq!(message.message_id, 1);
+            }
+            message => panic!("Unexpected message: {:?}", message),
+        }
+
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_typed_payloads() {
+        let markets = parse_message(
+            r#"{"type":"channel_data","connection_id":"c","message_id":3,"channel":"v4_markets","version":"1.0.0","contents":{"trading":{"BTC-USD":{"ticker":"BTC-USD","atomicResolution":-10,"stepBaseQuantums":1000000,"trades24H":12,"openInterest":"1.5"}},"oraclePrices":{"BTC-USD":{"oraclePrice":"42000.5","effectiveAt":"2024-01-01T00:00:00.000Z","effectiveAtHeight":"1234","marketId":0}}}}"#,
+        )
+        .unwrap();
+        match markets {
+            SocketMessage::ChannelData(ChannelDataMessage::Markets(message)) => {
+                let trading = &message.contents.trading.unwrap()["BTC-USD"];
+                assert_eq!(trading.atomic_resolution, Some(-10));
+                assert_eq!(trading.step_base_quantums, Some(1_000_000));
+                assert_eq!(trading.open_interest, Some(Decimal::new(15, 1)));
+                let oracle = &message.contents.oracle_prices.unwrap()["BTC-USD"];
+                assert_eq!(oracle.oracle_price, Decimal::new(420005, 1));
+                assert_eq!(oracle.effective_at_height, 1234);
+            }
+            message => panic!("Unexpected message: {:?}", message),
+        }
+
+        let fills = parse_message(
+            r#"{"type":"channel_data","connection_id":"c","message_id":4,"channel":"v4_subaccounts","id":"dydx1address/0","version":"3.0.0","contents":{"blockHeight":"1235","fills":[{"id":"f1","subaccountId":"s","side":"BUY","liquidity":"MAKER","type":"LIMIT","clobPairId":"0","orderId":"o1","size":"0.25","price":"50000","quoteAmount":"12500","eventId":"e","transactionHash":"h","createdAt":"2024-01-01T00:00:00.000Z","createdAtHeight":"1235","ticker":"BTC-USD","fee":"1.25"}]}}"#,
+        )
+        .unwrap();
+        match fills {
+            SocketMessage::ChannelData(ChannelDataMessage::Subaccounts(message)) => {
+                assert_eq!(message.contents.block_height, Some(1235));
+                let fill = &message.contents.fills.unwrap()[0];
+                assert_eq!(fill.size, Decimal::new(25, 2));
+                assert_eq!(fill.fee, Some(Decimal::new(125, 2)));
+                assert_eq!(fill.created_at_height, 1235);
+            }
+            message => panic!("Unexpected message: {:?}", message),
+        }
+
+        let invalid = parse_message(
+            r#"{"type":"channel_data","connection_id":"c","message_id":5,"channel":"v4_orderbook","id":"BTC-USD","version":"1.0.0","contents":{"bids":[["price","1"]]}}"#,
+        );
+        assert!(invalid.is_err());
+    }
+}
@@ -0,0 +1,393 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{
+    OrderSide, OrderStatus, OrderTimeInForce, OrderType, PerpetualPositionStatus,
+};
+
+use super::indexer_client_types::{
+    height, option_height, CandleResolution, CandleResponse, CandleResponseStruct, FillType,
+    Liquidity, OrderResponseStruct, OrderbookResponse, PerpetualMarketStatus,
+    PerpetualMarketsResponse, PositionSide, SubAccountResponseObject, TradeResponse, TransferType,
+};
+
+// ========================================
+// Channels
+// ========================================
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum SocketChannel {
+    Markets,
+    Orderbook(String),
+    Trades(String),
+    Candles(String, CandleResolution),
+    Subaccounts(String, u32),
+}
+
+impl SocketChannel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SocketChannel::Markets => "v4_markets",
+            SocketChannel::Orderbook(_) => "v4_orderbook",
+            SocketChannel::Trades(_) => "v4_trades",
+            SocketChannel::Candles(_, _) => "v4_candles",
+            SocketChannel::Subaccounts(_, _) => "v4_subaccounts",
+        }
+    }
+
+    pub fn id(&self) -> Option<String> {
+        match self {
+            SocketChannel::Markets => None,
+            SocketChannel::Orderbook(market) => Some(market.clone()),
+            SocketChannel::Trades(market) => Some(market.clone()),
+            SocketChannel::Candles(market, resolution) => Some(format!("{market}/{resolution}")),
+            SocketChannel::Subaccounts(address, subaccount_number) => {
+                Some(format!("{address}/{subaccount_number}"))
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct SocketRequest {
+    #[serde(rename = "type")]
+    pub request_type: &'static str,
+    pub channel: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batched: Option<bool>,
+}
+
+// ========================================
+// Raw messages as sent by the indexer
+// ========================================
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum RawSocketMessage {
+    Connected {
+        connection_id: String,
+        message_id: u64,
+    },
+    Subscribed {
+        connection_id: String,
+        message_id: u64,
+        channel: String,
+        id: Option<String>,
+        contents: serde_json::Value,
+    },
+    ChannelData {
+        connection_id: String,
+        message_id: u64,
+        channel: String,
+        id: Option<String>,
+        version: Option<String>,
+        contents: serde_json::Value,
+    },
+    ChannelBatchData {
+        connection_id: String,
+        message_id: u64,
+        channel: String,
+        id: Option<String>,
+        version: Option<String>,
+        contents: Vec<serde_json::Value>,
+    },
+    Unsubscribed {
+        connection_id: String,
+        message_id: u64,
+        channel: String,
+        id: Option<String>,
+    },
+    Error {
+        message: String,
+        connection_id: Option<String>,
+        message_id: Option<u64>,
+    },
+}
+
+// ========================================
+// Typed messages
+// ========================================
+
+#[derive(Clone, Debug)]
+pub enum SocketMessage {
+    Connected(ConnectedMessage),
+    Subscribed(SubscribedMessage),
+    ChannelData(ChannelDataMessage),
+    ChannelBatchData(ChannelBatchDataMessage),
+    Unsubscribed(UnsubscribedMessage),
+    Error(ErrorMessage),
+}
+
+#[derive(Clone, Debug)]
+pub struct ConnectedMessage {
+    pub connection_id: String,
+    pub message_id: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct UnsubscribedMessage {
+    pub connection_id: String,
+    pub message_id: u64,
+    pub channel: String,
+    pub id: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ErrorMessage {
+    pub message: String,
+    pub connection_id: Option<String>,
+    pub message_id: Option<u64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ChannelMessage<T> {
+    pub connection_id: String,
+    pub message_id: u64,
+    pub id: Option<String>,
+    pub version: Option<String>,
+    pub contents: T,
+}
+
+#[derive(Clone, Debug)]
+pub enum SubscribedMessage {
+    Markets(ChannelMessage<PerpetualMarketsResponse>),
+    Orderbook(ChannelMessage<OrderbookResponse>),
+    Trades(ChannelMessage<TradeResponse>),
+    Candles(ChannelMessage<CandleResponse>),
+    Subaccounts(ChannelMessage<SubaccountsSubscribedContents>),
+}
+
+#[derive(Clone, Debug)]
+pub enum ChannelDataMessage {
+    Markets(ChannelMessage<MarketsUpdateContents>),
+    Orderbook(ChannelMessage<OrderbookUpdateContents>),
+    Trades(ChannelMessage<TradeResponse>),
+    Candles(ChannelMessage<CandleResponseStruct>),
+    Subaccounts(ChannelMessage<SubaccountsUpdateContents>),
+}
+
+#[derive(Clone, Debug)]
+pub enum ChannelBatchDataMessage {
+    Markets(ChannelMessage<Vec<MarketsUpdateContents>>),
+    Orderbook(ChannelMessage<Vec<OrderbookUpdateContents>>),
+    Trades(ChannelMessage<Vec<TradeResponse>>),
+    Candles(ChannelMessage<Vec<CandleResponseStruct>>),
+    Subaccounts(ChannelMessage<Vec<SubaccountsUpdateContents>>),
+}
+
+// ========================================
+// Markets contents
+// ========================================
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MarketsUpdateContents {
+    pub trading: Option<HashMap<String, TradingPerpetualMarket>>,
+    pub oracle_prices: Option<HashMap<String, OraclePriceMarket>>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TradingPerpetualMarket {
+    pub clob_pair_id: Option<String>,
+    pub ticker: Option<String>,
+    pub status: Option<PerpetualMarketStatus>,
+    pub initial_margin_fraction: Option<Decimal>,
+    pub maintenance_margin_fraction: Option<Decimal>,
+    pub open_interest: Option<Decimal>,
+    pub atomic_resolution: Option<i32>,
+    pub quantum_conversion_exponent: Option<i32>,
+    pub subticks_per_tick: Option<u64>,
+    pub step_base_quantums: Option<u64>,
+    #[serde(rename = "priceChange24H")]
+    pub price_change_24h: Option<Decimal>,
+    #[serde(rename = "volume24H")]
+    pub volume_24h: Option<Decimal>,
+    #[serde(rename = "trades24H")]
+    pub trades_24h: Option<u64>,
+    pub next_funding_rate: Option<Decimal>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OraclePriceMarket {
+    pub oracle_price: Decimal,
+    pub effective_at: DateTime<Utc>,
+    #[serde(with = "height")]
+    pub effective_at_height: u64,
+    pub market_id: u32,
+}
+
+// ========================================
+// Orderbook contents
+// ========================================
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct OrderbookUpdateContents {
+    pub bids: Option<Vec<OrderbookUpdateLevel>>,
+    pub asks: Option<Vec<OrderbookUpdateLevel>>,
+}
+
+/// A price level update, sent by the indexer as a `[price, size]` pair.
+/// A size of zero removes the level from the book.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+#[serde(from = "(Decimal, Decimal)", into = "(Decimal, Decimal)")]
+pub struct OrderbookUpdateLevel {
+    pub price: Decimal,
+    pub size: Decimal,
+}
+
+impl From<(Decimal, Decimal)> for OrderbookUpdateLevel {
+    fn from((price, size): (Decimal, Decimal)) -> Self {
+        OrderbookUpdateLevel { price, size }
+    }
+}
+
+impl From<OrderbookUpdateLevel> for (Decimal, Decimal) {
+    fn from(level: OrderbookUpdateLevel) -> Self {
+        (level.price, level.size)
+    }
+}
+
+// ========================================
+// Subaccounts contents
+// ========================================
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SubaccountsSubscribedContents {
+    pub subaccount: SubAccountResponseObject,
+    #[serde(default)]
+    pub orders: Vec<OrderResponseStruct>,
+    #[serde(default, with = "option_height")]
+    pub block_height: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SubaccountsUpdateContents {
+    #[serde(default, with = "option_height")]
+    pub block_height: Option<u64>,
+    pub perpetual_positions: Option<Vec<SubaccountPerpetualPositionUpdate>>,
+    pub asset_positions: Option<Vec<SubaccountAssetPositionUpdate>>,
+    pub orders: Option<Vec<SubaccountOrderUpdate>>,
+    pub fills: Option<Vec<SubaccountFillUpdate>>,
+    pub transfers: Option<SubaccountTransferUpdate>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SubaccountPerpetualPositionUpdate {
+    pub address: String,
+    pub subaccount_number: u32,
+    pub position_id: String,
+    pub market: String,
+    pub side: PositionSide,
+    pub status: PerpetualPositionStatus,
+    pub size: Decimal,
+    pub max_size: Decimal,
+    pub net_funding: Decimal,
+    pub entry_price: Decimal,
+    pub exit_price: Option<Decimal>,
+    pub sum_open: Decimal,
+    pub sum_close: Decimal,
+    pub realized_pnl: Option<Decimal>,
+    pub unrealized_pnl: Option<Decimal>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SubaccountAssetPositionUpdate {
+    pub address: String,
+    pub subaccount_number: u32,
+    pub position_id: String,
+    pub asset_id: String,
+    pub symbol: String,
+    pub side: PositionSide,
+    pub size: Decimal,
+}
+
+/// Order updates only carry the fields that changed alongside the order's
+/// identifiers, so everything past `id` is optional.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SubaccountOrderUpdate {
+    pub id: String,
+    pub subaccount_id: Option<String>,
+    pub client_id: Option<String>,
+    pub clob_pair_id: Option<String>,
+    pub side: Option<OrderSide>,
+    pub size: Option<Decimal>,
+    pub ticker: Option<String>,
+    pub price: Option<Decimal>,
+    #[serde(rename = "type")]
+    pub order_type: Option<OrderType>,
+    pub time_in_force: Option<OrderTimeInForce>,
+    pub post_only: Option<bool>,
+    pub reduce_only: Option<bool>,
+    pub status: Option<OrderStatus>,
+    pub order_flags: Option<String>,
+    pub total_filled: Option<Decimal>,
+    #[serde(default, with = "option_height")]
+    pub good_til_block: Option<u64>,
+    pub good_til_block_time: Option<DateTime<Utc>>,
+    pub removal_reason: Option<String>,
+    #[serde(default, with = "option_height")]
+    pub created_at_height: Option<u64>,
+    pub client_metadata: Option<String>,
+    pub trigger_price: Option<Decimal>,
+    pub updated_at: Option<DateTime<Utc>>,
+    #[serde(default, with = "option_height")]
+    pub updated_at_height: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SubaccountFillUpdate {
+    pub id: String,
+    pub subaccount_id: String,
+    pub side: OrderSide,
+    pub liquidity: Liquidity,
+    #[serde(rename = "type")]
+    pub fill_type: FillType,
+    pub clob_pair_id: String,
+    pub order_id: Option<String>,
+    pub size: Decimal,
+    pub price: Decimal,
+    pub quote_amount: Decimal,
+    pub event_id: String,
+    pub transaction_hash: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "height")]
+    pub created_at_height: u64,
+    pub ticker: String,
+    pub fee: Option<Decimal>,
+    pub client_metadata: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SubaccountTransferUpdate {
+    pub sender: SubaccountTransferParty,
+    pub recipient: SubaccountTransferParty,
+    pub symbol: String,
+    pub size: Decimal,
+    #[serde(rename = "type")]
+    pub transfer_type: TransferType,
+    pub transaction_hash: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "height")]
+    pub created_at_height: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SubaccountTransferParty {
+    pub address: String,
+    pub subaccount_number: Option<u32>,
+}
@@ -0,0 +1,30 @@
+use super::wallet::LocalWallet;
+
+/// A subaccount of a dYdX address, together with the wallet that signs
+/// transactions on its behalf.
+#[derive(Clone)]
+pub struct Subaccount {
+    wallet: LocalWallet,
+    subaccount_number: u32,
+}
+
+impl Subaccount {
+    pub fn new(wallet: LocalWallet, subaccount_number: u32) -> Self {
+        Subaccount {
+            wallet,
+            subaccount_number,
+        }
+    }
+
+    pub fn wallet(&self) -> &LocalWallet {
+        &self.wallet
+    }
+
+    pub fn address(&self) -> &str {
+        self.wallet.address()
+    }
+
+    pub fn subaccount_number(&self) -> u32 {
+        self.subaccount_number
+    }
+}
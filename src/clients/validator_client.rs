@@ -0,0 +1,980 @@
+use std::future::Future;
+use std::time::Duration;
+
+use cosmrs::{
+    tendermint::chain::Id as ChainId,
+    tx::{Body, Fee, SignDoc, SignerInfo},
+    Any, Coin as FeeCoin, Denom,
+};
+use dydx_proto::cosmos::base::query::v1beta1::PageRequest as ProtocolPageRequest;
+use dydx_proto::cosmos_sdk_proto::cosmos::{
+    auth::v1beta1::{
+        query_client::QueryClient as AuthQueryClient, BaseAccount, QueryAccountRequest,
+    },
+    bank::v1beta1::{
+        query_client::QueryClient as BankQueryClient, QueryAllBalancesRequest, QueryBalanceRequest,
+    },
+    base::{
+        abci::v1beta1::{GasInfo, TxResponse},
+        query::v1beta1::PageRequest,
+        tendermint::v1beta1::{
+            service_client::ServiceClient as BlockClient, GetLatestBlockRequest,
+        },
+        v1beta1::Coin,
+    },
+    tx::v1beta1::{
+        service_client::ServiceClient as TxClient, BroadcastMode, BroadcastTxRequest,
+        SimulateRequest,
+    },
+};
+use dydx_proto::dydxprotocol::{
+    clob::{
+        msg_cancel_order, query_client::QueryClient as ClobQueryClient, ClobPair,
+        EquityTierLimitConfiguration, MsgCancelOrder, MsgPlaceOrder, Order, OrderId,
+        QueryAllClobPairRequest, QueryEquityTierLimitConfigurationRequest, QueryGetClobPairRequest,
+    },
+    perpetuals::{
+        query_client::QueryClient as PerpetualsQueryClient, Perpetual, QueryAllPerpetualsRequest,
+        QueryPerpetualRequest,
+    },
+};
+use dydx_proto::ToAny;
+use maybe_async::maybe_async;
+use prost::{Message, Name};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+#[cfg(feature = "blocking")]
+use tokio::runtime::{Builder, Runtime};
+use tonic::transport::{Channel, ClientTlsConfig, Endpoint};
+
+use super::errors::{ConstructorError, ValidatorError};
+use super::subaccount::Subaccount;
+
+/// Simulated gas is scaled by this factor to leave headroom for state
+/// changes between simulation and inclusion.
+const GAS_MULTIPLIER: Decimal = Decimal::from_parts(14, 0, 0, false, 1);
+/// How long to wait for the full node to accept a connection and to answer
+/// a request, unless set with [`ValidatorConfig::with_timeout`].
+const DEFAULT_GRPC_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct ValidatorClient {
+    validator_config: ValidatorConfig,
+    #[cfg(feature = "blocking")]
+    runtime: Runtime,
+    channel: Channel,
+}
+
+#[maybe_async]
+impl ValidatorClient {
+    /// Creates a client for the full node's gRPC endpoint. The connection is
+    /// established lazily on the first request. Without the `blocking`
+    /// feature this must be called from within a tokio runtime.
+    pub fn new(validator_config: ValidatorConfig) -> Result<Self, ConstructorError> {
+        #[cfg(feature = "blocking")]
+        let runtime = match Builder::new_current_thread().enable_all().build() {
+            Ok(runtime) => runtime,
+            Err(e) => return Err(ConstructorError::new(e.to_string())),
+        };
+
+        let mut endpoint = match Endpoint::from_shared(validator_config.grpc_endpoint.clone()) {
+            Ok(endpoint) => endpoint,
+            Err(e) => {
+                return Err(ConstructorError::new(format!(
+                    "Provided grpc endpoint is not valid: {}: {}",
+                    validator_config.grpc_endpoint, e
+                )))
+            }
+        }
+        .connect_timeout(validator_config.timeout)
+        .timeout(validator_config.timeout);
+
+        if validator_config.grpc_endpoint.starts_with("https://") {
+            endpoint = match endpoint.tls_config(ClientTlsConfig::new().with_native_roots()) {
+                Ok(endpoint) => endpoint,
+                Err(e) => return Err(ConstructorError::new(e.to_string())),
+            };
+        }
+
+        #[cfg(feature = "blocking")]
+        let channel = {
+            let _guard = runtime.enter();
+            endpoint.connect_lazy()
+        };
+        #[cfg(not(feature = "blocking"))]
+        let channel = endpoint.connect_lazy();
+
+        Ok(ValidatorClient {
+            validator_config,
+            #[cfg(feature = "blocking")]
+            runtime,
+            channel,
+        })
+    }
+
+    pub fn validator_config(&self) -> &ValidatorConfig {
+        &self.validator_config
+    }
+
+    pub async fn get_account(&self, address: String) -> Result<BaseAccount, ValidatorError> {
+        let mut client = AuthQueryClient::new(self.channel.clone());
+        let response = self
+            .send(client.account(QueryAccountRequest {
+                address: address.clone(),
+            }))
+            .await?;
+
+        let account = match response.account {
+            Some(account) => account,
+            None => return Err(ValidatorError::new(format!("Account not found: {address}"))),
+        };
+
+        if account.type_url != BaseAccount::type_url() {
+            return Err(ValidatorError::new(format!(
+                "Unsupported account type: {}",
+                account.type_url
+            )));
+        }
+
+        match BaseAccount::decode(account.value.as_slice()) {
+            Ok(account) => Ok(account),
+            Err(e) => Err(ValidatorError::new(e.to_string())),
+        }
+    }
+
+    pub async fn get_account_balances(&self, address: String) -> Result<Vec<Coin>, ValidatorError> {
+        let mut client = BankQueryClient::new(self.channel.clone());
+        let mut balances = Vec::new();
+        let mut next_key = Vec::new();
+        loop {
+            let response = self
+                .send(client.all_balances(QueryAllBalancesRequest {
+                    address: address.clone(),
+                    pagination: Some(PageRequest {
+                        key: next_key,
+                        ..Default::default()
+                    }),
+                    resolve_denom: false,
+                }))
+                .await?;
+            balances.extend(response.balances);
+            match response.pagination {
+                Some(page) if !page.next_key.is_empty() => next_key = page.next_key,
+                _ => return Ok(balances),
+            }
+        }
+    }
+
+    pub async fn get_account_balance(
+        &self,
+        address: String,
+        denom: String,
+    ) -> Result<Coin, ValidatorError> {
+        let mut client = BankQueryClient::new(self.channel.clone());
+        let response = self
+            .send(client.balance(QueryBalanceRequest {
+                address: address.clone(),
+                denom: denom.clone(),
+            }))
+            .await?;
+
+        match response.balance {
+            Some(balance) => Ok(balance),
+            None => Err(ValidatorError::new(format!(
+                "No {denom} balance for account: {address}"
+            ))),
+        }
+    }
+
+    pub async fn get_clob_pair(&self, id: u32) -> Result<ClobPair, ValidatorError> {
+        let mut client = ClobQueryClient::new(self.channel.clone());
+        let response = self
+            .send(client.clob_pair(QueryGetClobPairRequest { id }))
+            .await?;
+
+        match response.clob_pair {
+            Some(clob_pair) => Ok(clob_pair),
+            None => Err(ValidatorError::new(format!("Clob pair not found: {id}"))),
+        }
+    }
+
+    pub async fn get_all_clob_pairs(&self) -> Result<Vec<ClobPair>, ValidatorError> {
+        let mut client = ClobQueryClient::new(self.channel.clone());
+        let mut clob_pairs = Vec::new();
+        let mut next_key = Vec::new();
+        loop {
+            let response = self
+                .send(client.clob_pair_all(QueryAllClobPairRequest {
+                    pagination: Some(ProtocolPageRequest {
+                        key: next_key,
+                        ..Default::default()
+                    }),
+                }))
+                .await?;
+            clob_pairs.extend(response.clob_pair);
+            match response.pagination {
+                Some(page) if !page.next_key.is_empty() => next_key = page.next_key,
+                _ => return Ok(clob_pairs),
+            }
+        }
+    }
+
+    pub async fn get_perpetual(&self, id: u32) -> Result<Perpetual, ValidatorError> {
+        let mut client = PerpetualsQueryClient::new(self.channel.clone());
+        let response = self
+            .send(client.perpetual(QueryPerpetualRequest { id }))
+            .await?;
+
+        match response.perpetual {
+            Some(perpetual) => Ok(perpetual),
+            None => Err(ValidatorError::new(format!("Perpetual not found: {id}"))),
+        }
+    }
+
+    pub async fn get_all_perpetuals(&self) -> Result<Vec<Perpetual>, ValidatorError> {
+        let mut client = PerpetualsQueryClient::new(self.channel.clone());
+        let mut perpetuals = Vec::new();
+        let mut next_key = Vec::new();
+        loop {
+            let response = self
+                .send(client.all_perpetuals(QueryAllPerpetualsRequest {
+                    pagination: Some(ProtocolPageRequest {
+                        key: next_key,
+                        ..Default::default()
+                    }),
+                }))
+                .await?;
+            perpetuals.extend(response.perpetual);
+            match response.pagination {
+                Some(page) if !page.next_key.is_empty() => next_key = page.next_key,
+                _ => return Ok(perpetuals),
+            }
+        }
+    }
+
+    pub async fn get_equity_tier_limit_config(
+        &self,
+    ) -> Result<EquityTierLimitConfiguration, ValidatorError> {
+        let mut client = ClobQueryClient::new(self.channel.clone());
+        let response = self
+            .send(
+                client.equity_tier_limit_configuration(QueryEquityTierLimitConfigurationRequest {}),
+            )
+            .await?;
+
+        match response.equity_tier_limit_config {
+            Some(config) => Ok(config),
+            None => Err(ValidatorError::new(
+                "Equity tier limit configuration missing from response".to_string(),
+            )),
+        }
+    }
+
+    pub async fn get_latest_block_height(&self) -> Result<u64, ValidatorError> {
+        let mut client = BlockClient::new(self.channel.clone());
+        let response = self
+            .send(client.get_latest_block(GetLatestBlockRequest {}))
+            .await?;
+
+        let height = response
+            .sdk_block
+            .and_then(|block| block.header)
+            .map(|header| header.height);
+
+        match height.map(u64::try_from) {
+            Some(Ok(height)) => Ok(height),
+            Some(Err(e)) => Err(ValidatorError::new(e.to_string())),
+            None => Err(ValidatorError::new(
+                "Block header missing from response".to_string(),
+            )),
+        }
+    }
+
+    /// Runs a signed transaction through the node without committing it.
+    pub async fn simulate_tx(&self, tx_bytes: Vec<u8>) -> Result<GasInfo, ValidatorError> {
+        let mut client = TxClient::new(self.channel.clone());
+        #[allow(deprecated)]
+        let request = SimulateRequest { tx: None, tx_bytes };
+        let response = self.send(client.simulate(request)).await?;
+
+        match response.gas_info {
+            Some(gas_info) => Ok(gas_info),
+            None => Err(ValidatorError::new(
+                "Gas info missing from simulation response".to_string(),
+            )),
+        }
+    }
+
+    /// Broadcasts a signed transaction and waits for it to pass `CheckTx`.
+    /// A non-zero result code is returned as an error carrying the raw log.
+    pub async fn broadcast_tx(&self, tx_bytes: Vec<u8>) -> Result<TxResponse, ValidatorError> {
+        let mut client = TxClient::new(self.channel.clone());
+        let response = self
+            .send(client.broadcast_tx(BroadcastTxRequest {
+                tx_bytes,
+                mode: BroadcastMode::Sync.into(),
+            }))
+            .await?;
+
+        let tx_response = match response.tx_response {
+            Some(tx_response) => tx_response,
+            None => {
+                return Err(ValidatorError::new(
+                    "Tx response missing from broadcast response".to_string(),
+                ))
+            }
+        };
+
+        if tx_response.code != 0 {
+            return Err(ValidatorError::new(format!(
+                "Transaction {} failed with code {}: {}",
+                tx_response.txhash, tx_response.code, tx_response.raw_log
+            )));
+        }
+
+        Ok(tx_response)
+    }
+
+    /// Places an order. Short-term orders are free of fees; stateful orders
+    /// pay for simulated gas in USDC.
+    pub async fn place_order(
+        &self,
+        subaccount: &Subaccount,
+        order: Order,
+    ) -> Result<TxResponse, ValidatorError> {
+        let is_short_term = order.order_id.as_ref().map(|id| id.order_flags) == Some(0);
+        let message = MsgPlaceOrder { order: Some(order) }.to_any();
+        self.sign_and_broadcast(subaccount, vec![message], is_short_term)
+            .await
+    }
+
+    pub async fn cancel_order(
+        &self,
+        subaccount: &Subaccount,
+        order_id: OrderId,
+        good_til: msg_cancel_order::GoodTilOneof,
+    ) -> Result<TxResponse, ValidatorError> {
+        let is_short_term = order_id.order_flags == 0;
+        let message = MsgCancelOrder {
+            order_id: Some(order_id),
+            good_til_oneof: Some(good_til),
+        }
+        .to_any();
+        self.sign_and_broadcast(subaccount, vec![message], is_short_term)
+            .await
+    }
+
+    /// Signs the messages with the subaccount's key and broadcasts them in a
+    /// single transaction. Unless `zero_fee` is set, the fee is derived
+    /// from a simulation of the same transaction.
+    pub async fn sign_and_broadcast(
+        &self,
+        subaccount: &Subaccount,
+        messages: Vec<Any>,
+        zero_fee: bool,
+    ) -> Result<TxResponse, ValidatorError> {
+        let account = self.get_account(subaccount.address().to_string()).await?;
+        let body = Body::new(messages, "", 0u32);
+
+        let fee = if zero_fee {
+            zero_fee_amount()
+        } else {
+            let tx_bytes = self.sign_tx(subaccount, &account, &body, zero_fee_amount())?;
+            let gas_info = self.simulate_tx(tx_bytes).await?;
+            self.fee_for_gas(gas_info.gas_used)?
+        };
+
+        let tx_bytes = self.sign_tx(subaccount, &account, &body, fee)?;
+        self.broadcast_tx(tx_bytes).await
+    }
+
+    fn sign_tx(
+        &self,
+        subaccount: &Subaccount,
+        account: &BaseAccount,
+        body: &Body,
+        fee: Fee,
+    ) -> Result<Vec<u8>, ValidatorError> {
+        let chain_id = match ChainId::try_from(self.validator_config.chain_id.clone()) {
+            Ok(chain_id) => chain_id,
+            Err(e) => return Err(ValidatorError::new(e.to_string())),
+        };
+
+        let wallet = subaccount.wallet();
+        let auth_info =
+            SignerInfo::single_direct(Some(wallet.public_key()), account.sequence).auth_info(fee);
+
+        let sign_doc = match SignDoc::new(body, &auth_info, &chain_id, account.account_number) {
+            Ok(sign_doc) => sign_doc,
+            Err(e) => return Err(ValidatorError::new(e.to_string())),
+        };
+
+        Ok(wallet.sign_direct(sign_doc)?)
+    }
+
+    fn fee_for_gas(&self, gas_used: u64) -> Result<Fee, ValidatorError> {
+        let denoms = &self.validator_config.denoms;
+        let denom: Denom = match denoms.gas_denom.parse() {
+            Ok(denom) => denom,
+            Err(e) => return Err(ValidatorError::new(e.to_string())),
+        };
+
+        let gas_limit = match Decimal::from(gas_used)
+            .checked_mul(GAS_MULTIPLIER)
+            .and_then(|gas| gas.ceil().to_u64())
+        {
+            Some(gas_limit) => gas_limit,
+            None => {
+                return Err(ValidatorError::new(format!(
+                    "Gas limit overflows: {gas_used}"
+                )))
+            }
+        };
+        let amount = match Decimal::from(gas_limit)
+            .checked_mul(denoms.gas_price)
+            .and_then(|amount| amount.ceil().to_u128())
+        {
+            Some(amount) => amount,
+            None => {
+                return Err(ValidatorError::new(format!(
+                    "Fee overflows: {gas_limit} gas at {}",
+                    denoms.gas_price
+                )))
+            }
+        };
+        Ok(Fee::from_amount_and_gas(
+            FeeCoin { denom, amount },
+            gas_limit,
+        ))
+    }
+}
+
+impl ValidatorClient {
+    #[cfg(feature = "blocking")]
+    fn send<F, T>(&self, request: F) -> Result<T, ValidatorError>
+    where
+        F: Future<Output = Result<tonic::Response<T>, tonic::Status>>,
+    {
+        into_inner(self.runtime.block_on(request))
+    }
+
+    #[cfg(not(feature = "blocking"))]
+    async fn send<F, T>(&self, request: F) -> Result<T, ValidatorError>
+    where
+        F: Future<Output = Result<tonic::Response<T>, tonic::Status>>,
+    {
+        into_inner(request.await)
+    }
+}
+
+fn into_inner<T>(response: Result<tonic::Response<T>, tonic::Status>) -> Result<T, ValidatorError> {
+    match response {
+        Ok(response) => Ok(response.into_inner()),
+        Err(status) => Err(ValidatorError::new(format!(
+            "{:?}: {}",
+            status.code(),
+            status.message()
+        ))),
+    }
+}
+
+fn zero_fee_amount() -> Fee {
+    Fee {
+        amount: vec![],
+        gas_limit: 0,
+        payer: None,
+        granter: None,
+    }
+}
+
+#[derive(Clone)]
+pub struct ValidatorConfig {
+    grpc_endpoint: String,
+    chain_id: String,
+    denoms: DenomConfig,
+    timeout: Duration,
+}
+
+impl ValidatorConfig {
+    pub fn new(grpc_endpoint: String, chain_id: String, denoms: DenomConfig) -> Self {
+        ValidatorConfig {
+            grpc_endpoint,
+            chain_id,
+            denoms,
+            timeout: DEFAULT_GRPC_TIMEOUT,
+        }
+    }
+
+    /// Bounds both connecting to the full node and every request made over
+    /// the connection.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn grpc_endpoint(&self) -> &str {
+        &self.grpc_endpoint
+    }
+
+    pub fn chain_id(&self) -> &str {
+        &self.chain_id
+    }
+
+    pub fn denoms(&self) -> &DenomConfig {
+        &self.denoms
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+
+/// Denominations used to pay fees and to express collateral on chain.
+/// Fees are paid in USDC unless another gas denom is set with
+/// [`DenomConfig::with_gas_denom`].
+#[derive(Clone)]
+pub struct DenomConfig {
+    usdc_denom: String,
+    usdc_decimals: u32,
+    usdc_gas_price: Decimal,
+    chain_token_denom: String,
+    chain_token_decimals: u32,
+    gas_denom: String,
+    gas_price: Decimal,
+}
+
+impl DenomConfig {
+    pub fn new(
+        usdc_denom: String,
+        usdc_decimals: u32,
+        usdc_gas_price: Decimal,
+        chain_token_denom: String,
+        chain_token_decimals: u32,
+    ) -> Self {
+        DenomConfig {
+            gas_denom: usdc_denom.clone(),
+            gas_price: usdc_gas_price,
+            usdc_denom,
+            usdc_decimals,
+            usdc_gas_price,
+            chain_token_denom,
+            chain_token_decimals,
+        }
+    }
+
+    /// Pays fees in `gas_denom` at `gas_price` per unit of gas, e.g. in the
+    /// chain token instead of USDC.
+    pub fn with_gas_denom(mut self, gas_denom: String, gas_price: Decimal) -> Self {
+        self.gas_denom = gas_denom;
+        self.gas_price = gas_price;
+        self
+    }
+
+    pub fn usdc_denom(&self) -> &str {
+        &self.usdc_denom
+    }
+
+    pub fn usdc_decimals(&self) -> u32 {
+        self.usdc_decimals
+    }
+
+    pub fn usdc_gas_price(&self) -> Decimal {
+        self.usdc_gas_price
+    }
+
+    pub fn chain_token_denom(&self) -> &str {
+        &self.chain_token_denom
+    }
+
+    pub fn chain_token_decimals(&self) -> u32 {
+        self.chain_token_decimals
+    }
+
+    pub fn gas_denom(&self) -> &str {
+        &self.gas_denom
+    }
+
+    pub fn gas_price(&self) -> Decimal {
+        self.gas_price
+    }
+}
+
+// ========================================
+// Tests
+// ========================================
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::marker::PhantomData;
+    use std::sync::{mpsc, Arc, Mutex};
+    use std::thread;
+
+    use cosmrs::{crypto::secp256k1::SigningKey, Tx};
+    use dydx_proto::cosmos_sdk_proto::cosmos::{
+        auth::v1beta1::QueryAccountResponse,
+        bank::v1beta1::QueryAllBalancesResponse,
+        base::query::v1beta1::PageResponse,
+        tx::v1beta1::{BroadcastTxResponse, SimulateResponse},
+    };
+    use dydx_proto::dydxprotocol::subaccounts::SubaccountId;
+    use tonic::{
+        body::Body,
+        codec::ProstCodec,
+        codegen::{http, BoxFuture, Context, Poll, Service},
+        server::{Grpc, NamedService, UnaryService},
+        transport::{server::TcpIncoming, Server},
+        Status,
+    };
+
+    use super::*;
+    use crate::clients::wallet::LocalWallet;
+
+    fn config(grpc_endpoint: &str) -> ValidatorConfig {
+        ValidatorConfig::new(
+            grpc_endpoint.to_string(),
+            "dydx-testnet-4".to_string(),
+            DenomConfig::new(
+                "ibc/8E27BA2D5493AF5636760E354E46004562C46AB7EC0CC4C1CA14E9E20E2545B5".to_string(),
+                6,
+                Decimal::new(25, 3),
+                "adv4tnt".to_string(),
+                18,
+            ),
+        )
+    }
+
+    #[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+    async fn test_new_connects_lazily() {
+        let client = ValidatorClient::new(config("http://127.0.0.1:9090")).unwrap();
+        assert_eq!(client.validator_config().chain_id(), "dydx-testnet-4");
+        assert_eq!(client.validator_config().denoms().usdc_decimals(), 6);
+    }
+
+    #[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+    async fn test_new_rejects_invalid_endpoint() {
+        assert!(ValidatorClient::new(config("not a url")).is_err());
+    }
+
+    #[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+    async fn test_unreachable_node_returns_error() {
+        let client = ValidatorClient::new(config("http://127.0.0.1:1")).unwrap();
+        let height = client.get_latest_block_height().await;
+        assert!(height.is_err());
+    }
+
+    #[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+    async fn test_silent_node_times_out() {
+        // Accepts the connection but never speaks HTTP/2.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let config = config(&endpoint).with_timeout(Duration::from_millis(200));
+        let client = ValidatorClient::new(config).unwrap();
+
+        let started = std::time::Instant::now();
+        let height = client.get_latest_block_height().await;
+        assert!(height.is_err());
+        assert!(started.elapsed() < Duration::from_secs(5));
+        drop(listener);
+    }
+
+    // ========================================
+    // Mock node
+    // ========================================
+
+    /// What the mock node answers and what it was sent.
+    #[derive(Default)]
+    struct NodeState {
+        account_number: u64,
+        sequence: u64,
+        balance_pages: Vec<Vec<Coin>>,
+        balance_keys: Vec<Vec<u8>>,
+        gas_used: u64,
+        simulated: usize,
+        broadcast_code: u32,
+        broadcast: Vec<Tx>,
+    }
+
+    /// A full node answering the queries and transactions the client sends
+    /// over gRPC. Other methods are unimplemented.
+    #[derive(Clone, Default)]
+    struct MockNode {
+        state: Arc<Mutex<NodeState>>,
+    }
+
+    impl MockNode {
+        fn state(&self) -> std::sync::MutexGuard<'_, NodeState> {
+            self.state.lock().unwrap()
+        }
+
+        /// Serves the node on a runtime of its own, so that it also runs
+        /// under the blocking client, and returns a config pointing at it.
+        fn serve(&self) -> ValidatorConfig {
+            let node = self.clone();
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || {
+                let runtime = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .unwrap();
+                runtime.block_on(async move {
+                    let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+                    sender.send(incoming.local_addr().unwrap()).unwrap();
+                    Server::builder()
+                        .add_service(NodeService::<0>(node.clone()))
+                        .add_service(NodeService::<1>(node.clone()))
+                        .add_service(NodeService::<2>(node))
+                        .serve_with_incoming(incoming)
+                        .await
+                        .unwrap();
+                });
+            });
+            config(&format!("http://{}", receiver.recv().unwrap()))
+        }
+
+        fn call(
+            &self,
+            request: http::Request<Body>,
+        ) -> BoxFuture<http::Response<Body>, Infallible> {
+            let node = self.clone();
+            match request.uri().path() {
+                "/cosmos.auth.v1beta1.Query/Account" => {
+                    unary(request, move |request: QueryAccountRequest| {
+                        let state = node.state();
+                        let account = BaseAccount {
+                            address: request.address,
+                            pub_key: None,
+                            account_number: state.account_number,
+                            sequence: state.sequence,
+                        };
+                        QueryAccountResponse {
+                            account: Some(Any {
+                                type_url: BaseAccount::type_url(),
+                                value: account.encode_to_vec(),
+                            }),
+                        }
+                    })
+                }
+                "/cosmos.bank.v1beta1.Query/AllBalances" => {
+                    unary(request, move |request: QueryAllBalancesRequest| {
+                        let mut state = node.state();
+                        let key = request.pagination.unwrap_or_default().key;
+                        let page = key.first().map_or(0, |page| *page as usize);
+                        state.balance_keys.push(key);
+                        let next_key = if page + 1 < state.balance_pages.len() {
+                            vec![page as u8 + 1]
+                        } else {
+                            vec![]
+                        };
+                        QueryAllBalancesResponse {
+                            balances: state.balance_pages[page].clone(),
+                            pagination: Some(PageResponse { next_key, total: 0 }),
+                        }
+                    })
+                }
+                "/cosmos.tx.v1beta1.Service/Simulate" => {
+                    unary(request, move |_: SimulateRequest| {
+                        let mut state = node.state();
+                        state.simulated += 1;
+                        SimulateResponse {
+                            gas_info: Some(GasInfo {
+                                gas_wanted: 0,
+                                gas_used: state.gas_used,
+                            }),
+                            result: None,
+                        }
+                    })
+                }
+                "/cosmos.tx.v1beta1.Service/BroadcastTx" => {
+                    unary(request, move |request: BroadcastTxRequest| {
+                        let mut state = node.state();
+                        state
+                            .broadcast
+                            .push(Tx::from_bytes(&request.tx_bytes).unwrap());
+                        let code = state.broadcast_code;
+                        BroadcastTxResponse {
+                            tx_response: Some(TxResponse {
+                                code,
+                                txhash: "HASH".to_string(),
+                                raw_log: if code == 0 { "" } else { "insufficient fees" }
+                                    .to_string(),
+                                ..Default::default()
+                            }),
+                        }
+                    })
+                }
+                path => {
+                    let status = Status::unimplemented(path.to_string());
+                    Box::pin(async move { Ok(status.into_http()) })
+                }
+            }
+        }
+    }
+
+    const SERVICES: [&str; 3] = [
+        "cosmos.auth.v1beta1.Query",
+        "cosmos.bank.v1beta1.Query",
+        "cosmos.tx.v1beta1.Service",
+    ];
+
+    /// Routes the gRPC service `SERVICES[N]` to the node.
+    #[derive(Clone)]
+    struct NodeService<const N: usize>(MockNode);
+
+    impl<const N: usize> NamedService for NodeService<N> {
+        const NAME: &'static str = SERVICES[N];
+    }
+
+    impl<const N: usize> Service<http::Request<Body>> for NodeService<N> {
+        type Response = http::Response<Body>;
+        type Error = Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+            self.0.call(request)
+        }
+    }
+
+    struct Handler<F, T>(F, PhantomData<fn(T)>);
+
+    impl<F, T, U> UnaryService<T> for Handler<F, T>
+    where
+        F: FnMut(T) -> U,
+    {
+        type Response = U;
+        type Future = std::future::Ready<Result<tonic::Response<U>, Status>>;
+
+        fn call(&mut self, request: tonic::Request<T>) -> Self::Future {
+            std::future::ready(Ok(tonic::Response::new((self.0)(request.into_inner()))))
+        }
+    }
+
+    fn unary<T, U, F>(
+        request: http::Request<Body>,
+        handler: F,
+    ) -> BoxFuture<http::Response<Body>, Infallible>
+    where
+        T: Message + Default + Send + 'static,
+        U: Message + Send + 'static,
+        F: FnMut(T) -> U + Send + 'static,
+    {
+        Box::pin(async move {
+            let mut grpc = Grpc::new(ProstCodec::<U, T>::default());
+            Ok(grpc.unary(Handler(handler, PhantomData), request).await)
+        })
+    }
+
+    fn coin(denom: &str, amount: &str) -> Coin {
+        Coin {
+            denom: denom.to_string(),
+            amount: amount.to_string(),
+        }
+    }
+
+    fn subaccount() -> Subaccount {
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        LocalWallet::from_signing_key(signing_key)
+            .unwrap()
+            .subaccount(0)
+    }
+
+    fn order(order_flags: u32) -> Order {
+        let subaccount = subaccount();
+        Order {
+            order_id: Some(OrderId {
+                subaccount_id: Some(SubaccountId {
+                    owner: subaccount.address().to_string(),
+                    number: 0,
+                }),
+                client_id: 1,
+                order_flags,
+                clob_pair_id: 0,
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+    async fn test_account_and_paginated_balances() {
+        let node = MockNode::default();
+        {
+            let mut state = node.state();
+            state.account_number = 7;
+            state.sequence = 3;
+            state.balance_pages = vec![
+                vec![coin("adv4tnt", "10"), coin("ibc/usdc", "20")],
+                vec![coin("uatom", "30")],
+            ];
+        }
+        let client = ValidatorClient::new(node.serve()).unwrap();
+
+        let account = client
+            .get_account("dydx1address".to_string())
+            .await
+            .unwrap();
+        assert_eq!(account.address, "dydx1address");
+        assert_eq!((account.account_number, account.sequence), (7, 3));
+
+        let balances = client
+            .get_account_balances("dydx1address".to_string())
+            .await
+            .unwrap();
+        let denoms: Vec<&str> = balances.iter().map(|coin| coin.denom.as_str()).collect();
+        assert_eq!(denoms, vec!["adv4tnt", "ibc/usdc", "uatom"]);
+        assert_eq!(node.state().balance_keys, vec![vec![], vec![1]]);
+
+        let unimplemented = client.get_latest_block_height().await;
+        assert!(unimplemented.is_err());
+    }
+
+    #[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+    async fn test_stateful_orders_pay_for_simulated_gas() {
+        let node = MockNode::default();
+        node.state().gas_used = 100_000;
+        let client = ValidatorClient::new(node.serve()).unwrap();
+        let subaccount = subaccount();
+
+        let gas_info = client.simulate_tx(vec![]).await.unwrap();
+        assert_eq!(gas_info.gas_used, 100_000);
+
+        let response = client.place_order(&subaccount, order(64)).await.unwrap();
+        assert_eq!(response.txhash, "HASH");
+        {
+            let state = node.state();
+            assert_eq!(state.simulated, 2);
+            let fee = &state.broadcast[0].auth_info.fee;
+            // 100_000 gas used * 1.4, at 0.025 per unit of gas
+            assert_eq!(fee.gas_limit, 140_000);
+            assert_eq!(fee.amount[0].amount, 3_500);
+            assert_eq!(
+                fee.amount[0].denom.as_ref(),
+                client.validator_config().denoms().gas_denom()
+            );
+        }
+
+        client.place_order(&subaccount, order(0)).await.unwrap();
+        let state = node.state();
+        assert_eq!(state.simulated, 2);
+        let fee = &state.broadcast[1].auth_info.fee;
+        assert_eq!(fee.gas_limit, 0);
+        assert!(fee.amount.is_empty());
+    }
+
+    #[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+    async fn test_rejected_broadcast_returns_error() {
+        let node = MockNode::default();
+        node.state().broadcast_code = 13;
+        let client = ValidatorClient::new(node.serve()).unwrap();
+
+        let result = client.place_order(&subaccount(), order(0)).await;
+        let error = result.err().unwrap().to_string();
+        assert_eq!(
+            error,
+            "Transaction HASH failed with code 13: insufficient fees"
+        );
+        assert_eq!(node.state().broadcast.len(), 1);
+    }
+}
@@ -0,0 +1,190 @@
+use std::sync::Arc;
+
+use bip32::DerivationPath;
+use bip39::{Language, Mnemonic};
+use cosmrs::{
+    crypto::{secp256k1::SigningKey, PublicKey},
+    tx::SignDoc,
+};
+
+use super::errors::WalletError;
+use super::subaccount::Subaccount;
+
+pub const ADDRESS_PREFIX: &str = "dydx";
+
+/// Cosmos HD path without the address index, see BIP-44 and SLIP-44.
+const COSMOS_HD_PATH: &str = "m/44'/118'/0'/0";
+
+/// A wallet holding a secp256k1 key in memory, derived from a BIP-39
+/// mnemonic on the Cosmos HD path.
+#[derive(Clone)]
+pub struct LocalWallet {
+    signing_key: Arc<SigningKey>,
+    address: String,
+}
+
+impl LocalWallet {
+    /// Derives the wallet at index 0, i.e. `m/44'/118'/0'/0/0`.
+    pub fn from_mnemonic(mnemonic: &str) -> Result<Self, WalletError> {
+        LocalWallet::from_mnemonic_with_index(mnemonic, 0)
+    }
+
+    /// Derives the wallet at `m/44'/118'/0'/0/{index}`. Phrases of 12 to 24
+    /// words are accepted, separated by any whitespace.
+    pub fn from_mnemonic_with_index(mnemonic: &str, index: u32) -> Result<Self, WalletError> {
+        let words = mnemonic.split_whitespace().collect::<Vec<_>>().join(" ");
+        let mnemonic = match Mnemonic::parse_in(Language::English, words) {
+            Ok(mnemonic) => mnemonic,
+            Err(e) => return Err(WalletError::new(format!("Invalid mnemonic: {e}"))),
+        };
+        let path: DerivationPath = match format!("{COSMOS_HD_PATH}/{index}").parse() {
+            Ok(path) => path,
+            Err(e) => return Err(WalletError::new(e.to_string())),
+        };
+
+        let seed = mnemonic.to_seed("");
+        match SigningKey::derive_from_path(seed, &path) {
+            Ok(signing_key) => LocalWallet::from_signing_key(signing_key),
+            Err(e) => Err(WalletError::new(e.to_string())),
+        }
+    }
+
+    pub fn from_signing_key(signing_key: SigningKey) -> Result<Self, WalletError> {
+        let address = match signing_key.public_key().account_id(ADDRESS_PREFIX) {
+            Ok(account_id) => account_id.to_string(),
+            Err(e) => return Err(WalletError::new(e.to_string())),
+        };
+
+        Ok(LocalWallet {
+            signing_key: Arc::new(signing_key),
+            address,
+        })
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        self.signing_key.public_key()
+    }
+
+    pub fn subaccount(&self, subaccount_number: u32) -> Subaccount {
+        Subaccount::new(self.clone(), subaccount_number)
+    }
+
+    /// Signs the document in SIGN_MODE_DIRECT and returns the encoded
+    /// `TxRaw` bytes, ready to be simulated or broadcast.
+    pub fn sign_direct(&self, sign_doc: SignDoc) -> Result<Vec<u8>, WalletError> {
+        match sign_doc
+            .sign(&self.signing_key)
+            .and_then(|raw| raw.to_bytes())
+        {
+            Ok(tx_bytes) => Ok(tx_bytes),
+            Err(e) => Err(WalletError::new(e.to_string())),
+        }
+    }
+}
+
+impl From<&LocalWallet> for String {
+    fn from(value: &LocalWallet) -> Self {
+        value.address.clone()
+    }
+}
+
+// ========================================
+// Tests
+// ========================================
+
+#[cfg(test)]
+mod tests {
+    use bip32::secp256k1::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+    use cosmrs::{
+        proto::cosmos::tx::v1beta1::TxRaw,
+        tendermint::chain::Id as ChainId,
+        tx::{Body, Fee, Raw, SignerInfo},
+    };
+    use prost::Message;
+
+    use super::*;
+
+    const TEST_MNEMONIC: &str = "mirror actor skill push coach wait confirm orchard lunch mobile athlete gossip awake miracle matter bus reopen team ladder lazy list timber render wait";
+
+    #[test]
+    fn test_address_from_mnemonic() {
+        let wallet = LocalWallet::from_mnemonic(TEST_MNEMONIC).unwrap();
+        assert_eq!(
+            wallet.address(),
+            "dydx14zzueazeh0hj67cghhf9jypslcf9sh2n5k6art"
+        );
+        assert_eq!(String::from(&wallet), wallet.address());
+
+        let other = LocalWallet::from_mnemonic_with_index(TEST_MNEMONIC, 1).unwrap();
+        assert_eq!(
+            other.address(),
+            "dydx1t3vv5f3clj6e99jvh4nujuhzwht2a5l9wn2425"
+        );
+
+        assert!(LocalWallet::from_mnemonic("mirror actor skill").is_err());
+    }
+
+    #[test]
+    fn test_address_from_short_mnemonic() {
+        // The Cosmos test vector, also checked under its own prefix.
+        let abandon = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let wallet = LocalWallet::from_mnemonic(abandon).unwrap();
+        assert_eq!(
+            wallet.address(),
+            "dydx19rl4cm2hmr8afy4kldpxz3fka4jguq0a4erelz"
+        );
+        assert_eq!(
+            wallet
+                .public_key()
+                .account_id("cosmos")
+                .unwrap()
+                .to_string(),
+            "cosmos19rl4cm2hmr8afy4kldpxz3fka4jguq0auqdal4"
+        );
+
+        let spaced = format!("  {}\n", TEST_MNEMONIC.replace(' ', "  \t"));
+        let wallet = LocalWallet::from_mnemonic(&spaced).unwrap();
+        assert_eq!(
+            wallet.address(),
+            "dydx14zzueazeh0hj67cghhf9jypslcf9sh2n5k6art"
+        );
+    }
+
+    #[test]
+    fn test_sign_direct() {
+        let wallet = LocalWallet::from_mnemonic(TEST_MNEMONIC).unwrap();
+        let body = Body::new(Vec::<cosmrs::Any>::new(), "", 0u32);
+        let auth_info = SignerInfo::single_direct(Some(wallet.public_key()), 3).auth_info(Fee {
+            amount: vec![],
+            gas_limit: 0,
+            payer: None,
+            granter: None,
+        });
+        let chain_id = ChainId::try_from("dydx-testnet-4".to_string()).unwrap();
+        let sign_doc = || SignDoc::new(&body, &auth_info, &chain_id, 7).unwrap();
+
+        let tx_bytes = wallet.sign_direct(sign_doc()).unwrap();
+        let raw = Raw::from_bytes(&tx_bytes).unwrap();
+        assert_eq!(raw.to_bytes().unwrap(), tx_bytes);
+        // RFC 6979 signatures are deterministic
+        assert_eq!(wallet.sign_direct(sign_doc()).unwrap(), tx_bytes);
+
+        let tx_raw = TxRaw::decode(tx_bytes.as_slice()).unwrap();
+        assert_eq!(tx_raw.body_bytes, sign_doc().body_bytes);
+        assert_eq!(tx_raw.auth_info_bytes, sign_doc().auth_info_bytes);
+        assert_eq!(tx_raw.signatures.len(), 1);
+
+        let verifying_key = VerifyingKey::from_sec1_bytes(&wallet.public_key().to_bytes()).unwrap();
+        let signature = Signature::from_slice(&tx_raw.signatures[0]).unwrap();
+        let signed_bytes = sign_doc().into_bytes().unwrap();
+        assert!(verifying_key.verify(&signed_bytes, &signature).is_ok());
+
+        let mut tampered = signed_bytes.clone();
+        tampered[0] ^= 1;
+        assert!(verifying_key.verify(&tampered, &signature).is_err());
+    }
+}
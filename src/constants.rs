@@ -1,3 +1,6 @@
+use std::fmt;
+
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
@@ -5,18 +8,18 @@ pub enum TickerType {
     PERPETUAL,
 }
 
-impl Into<String> for TickerType {
-    fn into(self) -> String {
-        match self {
+impl From<TickerType> for String {
+    fn from(value: TickerType) -> Self {
+        match value {
             TickerType::PERPETUAL => String::from("PERPETUAL"),
         }
     }
 }
 
-impl ToString for TickerType {
-    fn to_string(&self) -> String {
+impl fmt::Display for TickerType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            TickerType::PERPETUAL => String::from("PERPETUAL"),
+            TickerType::PERPETUAL => write!(f, "PERPETUAL"),
         }
     }
 }
@@ -28,9 +31,9 @@ pub enum PerpetualPositionStatus {
     LIQUIDATED,
 }
 
-impl Into<String> for PerpetualPositionStatus {
-    fn into(self) -> String {
-        match self {
+impl From<PerpetualPositionStatus> for String {
+    fn from(value: PerpetualPositionStatus) -> Self {
+        match value {
             PerpetualPositionStatus::OPEN => String::from("OPEN"),
             PerpetualPositionStatus::CLOSED => String::from("CLOSED"),
             PerpetualPositionStatus::LIQUIDATED => String::from("LIQUIDATED"),
@@ -49,9 +52,9 @@ pub enum OrderStatus {
     Untriggered,
 }
 
-impl Into<String> for OrderStatus {
-    fn into(self) -> String {
-        match self {
+impl From<OrderStatus> for String {
+    fn from(value: OrderStatus) -> Self {
+        match value {
             OrderStatus::BestEffortOpened => String::from("BEST_EFFORT_OPENED"),
             OrderStatus::Open => String::from("OPEN"),
             OrderStatus::Filled => String::from("FILLED"),
@@ -62,40 +65,39 @@ impl Into<String> for OrderStatus {
     }
 }
 
-impl ToString for OrderStatus {
-    fn to_string(&self) -> String {
+impl fmt::Display for OrderStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            OrderStatus::BestEffortOpened => String::from("BEST_EFFORT_OPENED"),
-            OrderStatus::Open => String::from("OPEN"),
-            OrderStatus::Filled => String::from("FILLED"),
-            OrderStatus::BestEffortCanceled => String::from("BEST_EFFORT_CANCELED"),
-            OrderStatus::Canceled => String::from("CANCELED"),
-            OrderStatus::Untriggered => String::from("UNTRIGGERED"),
+            OrderStatus::BestEffortOpened => write!(f, "BEST_EFFORT_OPENED"),
+            OrderStatus::Open => write!(f, "OPEN"),
+            OrderStatus::Filled => write!(f, "FILLED"),
+            OrderStatus::BestEffortCanceled => write!(f, "BEST_EFFORT_CANCELED"),
+            OrderStatus::Canceled => write!(f, "CANCELED"),
+            OrderStatus::Untriggered => write!(f, "UNTRIGGERED"),
         }
     }
 }
 
 #[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
-#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum OrderSide {
     BUY,
     SELL,
 }
 
-impl Into<String> for OrderSide {
-    fn into(self) -> String {
-        match self {
+impl From<OrderSide> for String {
+    fn from(value: OrderSide) -> Self {
+        match value {
             OrderSide::BUY => String::from("BUY"),
             OrderSide::SELL => String::from("SELL"),
         }
     }
 }
 
-impl ToString for OrderSide {
-    fn to_string(&self) -> String {
+impl fmt::Display for OrderSide {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            OrderSide::BUY => String::from("BUY"),
-            OrderSide::SELL => String::from("SELL"),
+            OrderSide::BUY => write!(f, "BUY"),
+            OrderSide::SELL => write!(f, "SELL"),
         }
     }
 }
@@ -111,9 +113,9 @@ pub enum OrderType {
     TakeProfitMarket,
 }
 
-impl Into<String> for OrderType {
-    fn into(self) -> String {
-        match self {
+impl From<OrderType> for String {
+    fn from(value: OrderType) -> Self {
+        match value {
             OrderType::Limit => String::from("LIMIT"),
             OrderType::Market => String::from("MARKET"),
             OrderType::StopLimit => String::from("STOP_LIMIT"),
@@ -124,30 +126,37 @@ impl Into<String> for OrderType {
     }
 }
 
-impl ToString for OrderType {
-    fn to_string(&self) -> String {
+impl fmt::Display for OrderType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            OrderType::Limit => String::from("LIMIT"),
-            OrderType::Market => String::from("MARKET"),
-            OrderType::StopLimit => String::from("STOP_LIMIT"),
-            OrderType::TakeProfitLimit => String::from("TAKE_PROFIT_LIMIT"),
-            OrderType::StopMarket => String::from("STOP_MARKET"),
-            OrderType::TakeProfitMarket => String::from("TAKE_PROFIT_MARKET"),
+            OrderType::Limit => write!(f, "LIMIT"),
+            OrderType::Market => write!(f, "MARKET"),
+            OrderType::StopLimit => write!(f, "STOP_LIMIT"),
+            OrderType::TakeProfitLimit => write!(f, "TAKE_PROFIT_LIMIT"),
+            OrderType::StopMarket => write!(f, "STOP_MARKET"),
+            OrderType::TakeProfitMarket => write!(f, "TAKE_PROFIT_MARKET"),
         }
     }
 }
 
 #[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
 pub enum OrderTimeInForce {
-    GIT,
+    #[serde(alias = "GIT")]
+    GTT,
     IOC,
     FOK,
 }
 
-impl Into<String> for OrderTimeInForce {
-    fn into(self) -> String {
-        match self {
-            OrderTimeInForce::GIT => String::from("GIT"),
+impl OrderTimeInForce {
+    /// Former, misspelled name of [`OrderTimeInForce::GTT`].
+    #[deprecated(note = "use OrderTimeInForce::GTT")]
+    pub const GIT: OrderTimeInForce = OrderTimeInForce::GTT;
+}
+
+impl From<OrderTimeInForce> for String {
+    fn from(value: OrderTimeInForce) -> Self {
+        match value {
+            OrderTimeInForce::GTT => String::from("GTT"),
             OrderTimeInForce::IOC => String::from("IOC"),
             OrderTimeInForce::FOK => String::from("FOK"),
         }
@@ -155,25 +164,128 @@ impl Into<String> for OrderTimeInForce {
 }
 
 #[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum TimePeriod {
     OneDay,
     SevenDays,
 }
 
-impl Into<String> for TimePeriod {
-    fn into(self) -> String {
-        match self {
+impl From<TimePeriod> for String {
+    fn from(value: TimePeriod) -> Self {
+        match value {
             TimePeriod::OneDay => String::from("ONE_DAY"),
             TimePeriod::SevenDays => String::from("SEVEN_DAYS"),
         }
     }
 }
 
-impl ToString for TimePeriod {
-    fn to_string(&self) -> String {
+impl fmt::Display for TimePeriod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            TimePeriod::OneDay => String::from("ONE_DAY"),
-            TimePeriod::SevenDays => String::from("SEVEN_DAYS"),
+            TimePeriod::OneDay => write!(f, "ONE_DAY"),
+            TimePeriod::SevenDays => write!(f, "SEVEN_DAYS"),
         }
     }
 }
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TradingRewardAggregationPeriod {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl From<TradingRewardAggregationPeriod> for String {
+    fn from(value: TradingRewardAggregationPeriod) -> Self {
+        match value {
+            TradingRewardAggregationPeriod::Daily => String::from("DAILY"),
+            TradingRewardAggregationPeriod::Weekly => String::from("WEEKLY"),
+            TradingRewardAggregationPeriod::Monthly => String::from("MONTHLY"),
+        }
+    }
+}
+
+impl fmt::Display for TradingRewardAggregationPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TradingRewardAggregationPeriod::Daily => write!(f, "DAILY"),
+            TradingRewardAggregationPeriod::Weekly => write!(f, "WEEKLY"),
+            TradingRewardAggregationPeriod::Monthly => write!(f, "MONTHLY"),
+        }
+    }
+}
+
+/// Spacing of the PnL ticks of vaults.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum PnlTickInterval {
+    Hour,
+    Day,
+}
+
+impl From<PnlTickInterval> for String {
+    fn from(value: PnlTickInterval) -> Self {
+        match value {
+            PnlTickInterval::Hour => String::from("hour"),
+            PnlTickInterval::Day => String::from("day"),
+        }
+    }
+}
+
+impl fmt::Display for PnlTickInterval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PnlTickInterval::Hour => write!(f, "hour"),
+            PnlTickInterval::Day => write!(f, "day"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OrderFlags {
+    ShortTerm,
+    Conditional,
+    LongTerm,
+}
+
+impl From<OrderFlags> for u32 {
+    fn from(value: OrderFlags) -> Self {
+        match value {
+            OrderFlags::ShortTerm => 0,
+            OrderFlags::Conditional => 32,
+            OrderFlags::LongTerm => 64,
+        }
+    }
+}
+
+// ========================================
+// Networks
+// ========================================
+
+pub const MAINNET_CHAIN_ID: &str = "dydx-mainnet-1";
+pub const MAINNET_INDEXER_REST_ENDPOINT: &str = "https://indexer.dydx.trade";
+pub const MAINNET_INDEXER_WEBSOCKET_ENDPOINT: &str = "wss://indexer.dydx.trade/v4/ws";
+pub const MAINNET_GRPC_ENDPOINT: &str = "https://dydx-ops-grpc.kingnodes.com:443";
+pub const MAINNET_CHAIN_TOKEN_DENOM: &str = "adydx";
+
+pub const TESTNET_CHAIN_ID: &str = "dydx-testnet-4";
+pub const TESTNET_INDEXER_REST_ENDPOINT: &str = "https://indexer.v4testnet.dydx.exchange";
+pub const TESTNET_INDEXER_WEBSOCKET_ENDPOINT: &str = "wss://indexer.v4testnet.dydx.exchange/v4/ws";
+pub const TESTNET_GRPC_ENDPOINT: &str = "https://test-dydx-grpc.kingnodes.com:443";
+pub const TESTNET_CHAIN_TOKEN_DENOM: &str = "adv4tnt";
+
+pub const LOCAL_CHAIN_ID: &str = "localdydxprotocol";
+pub const LOCAL_INDEXER_REST_ENDPOINT: &str = "http://localhost:3002";
+pub const LOCAL_INDEXER_WEBSOCKET_ENDPOINT: &str = "ws://localhost:3003/v4/ws";
+pub const LOCAL_GRPC_ENDPOINT: &str = "http://localhost:9090";
+pub const LOCAL_CHAIN_TOKEN_DENOM: &str = "adv4tnt";
+
+/// USDC bridged over IBC from Noble, on every network.
+pub const USDC_DENOM: &str = "ibc/8E27BA2D5493AF5636760E354E46004562C46AB7EC0CC4C1CA14E9E20E2545B5";
+pub const USDC_DECIMALS: u32 = 6;
+/// Id of USDC in the `x/assets` module, as used by asset positions.
+pub const USDC_ASSET_ID: u32 = 0;
+/// Gas price in USDC, in its smallest unit.
+pub const USDC_GAS_PRICE: Decimal = Decimal::from_parts(25, 0, 0, false, 3);
+pub const CHAIN_TOKEN_DECIMALS: u32 = 18;
@@ -1,2 +1,6 @@
 pub mod clients;
 pub mod constants;
+
+pub use chrono;
+pub use dydx_proto;
+pub use rust_decimal;
@@ -0,0 +1,492 @@
+//! Runs every `AccountsClient` and `MarketsClient` method against the
+//! sample responses under `tests/fixtures/indexer`, without a network.
+//!
+//! The samples are hand-written in the shape of the indexer's documented
+//! responses, in the file format of a `FixtureRecorder`, so they only check
+//! the client against that documentation. `test_record_testnet` makes the
+//! same requests against the testnet indexer through a `FixtureRecorder`,
+//! failing if any response does not decode; its output can replace the
+//! samples:
+//!
+//! ```text
+//! DYDX_FIXTURE_ADDRESS=dydx1... DYDX_FIXTURE_DIR=/tmp/fixtures \
+//!     cargo test --test indexer_replay -- --ignored
+//! ```
+
+use dydx_v4_client_rs::chrono::{DateTime, Utc};
+use dydx_v4_client_rs::clients::indexer_client::{AccountsClient, IndexerClient, MarketsClient};
+use dydx_v4_client_rs::clients::indexer_client_types::{
+    CandleResolution, CandlesRequest, FillsRequest, HistoricalBlockTradingRewardsRequest,
+    HistoricalFundingRequest, HistoricalPnLRequest, HistoricalTradingRewardAggregationsRequest,
+    OrdersRequest, ParentSubaccountFillsRequest, ParentSubaccountOrdersRequest,
+    ParentSubaccountTransfersRequest, PerpetualMarketsRequest, PositionDetailsRequest,
+    PositionSide, SparklinesRequest, SubaccountsRequest, TradesRequest, TransferType,
+    TransfersBetweenRequest, TransfersRequest,
+};
+use dydx_v4_client_rs::clients::indexer_fixtures::{FixtureRecorder, IndexerFixtures};
+use dydx_v4_client_rs::clients::network::Network;
+use dydx_v4_client_rs::constants::{
+    OrderSide, OrderStatus, PerpetualPositionStatus, TimePeriod, TradingRewardAggregationPeriod,
+};
+use dydx_v4_client_rs::rust_decimal::Decimal;
+
+const ADDRESS: &str = "dydx14zzueazeh0hj67cghhf9jypslcf9sh2n5k6art";
+const MARKET: &str = "BTC-USD";
+
+fn client() -> IndexerClient {
+    let fixtures = IndexerFixtures::load(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/indexer"
+    ))
+    .unwrap();
+    IndexerClient::from_fixtures(fixtures)
+}
+
+fn decimal(value: &str) -> Decimal {
+    value.parse().unwrap()
+}
+
+fn time(value: &str) -> DateTime<Utc> {
+    value.parse().unwrap()
+}
+
+// ========================================
+// AccountsClient
+// ========================================
+
+#[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+async fn test_get_sub_accounts() {
+    let response = client()
+        .get_sub_accounts(SubaccountsRequest::new(ADDRESS.to_string()).limit(2))
+        .await
+        .unwrap();
+    assert_eq!(response.len(), 2);
+    assert_eq!(response.subaccounts[0].subaccount_number, 0);
+    assert_eq!(response.subaccounts[1].equity, decimal("250"));
+    assert_eq!(response.total_trading_rewards, decimal("0.0123"));
+}
+
+#[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+async fn test_get_sub_account() {
+    let subaccount = client()
+        .get_sub_account(ADDRESS.to_string(), 0)
+        .await
+        .unwrap()
+        .subaccount;
+    assert_eq!(subaccount.equity, decimal("10512.34"));
+    let positions = subaccount.open_perpetual_positions.unwrap();
+    assert_eq!(positions[MARKET].side, Some(PositionSide::LONG));
+    assert_eq!(
+        subaccount.asset_positions.unwrap()["USDC"].size,
+        decimal("5512.34")
+    );
+}
+
+#[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+async fn test_get_sub_account_perpetual_positions() {
+    let positions = client()
+        .get_sub_account_perpetual_positions(
+            PositionDetailsRequest::new(ADDRESS.to_string(), 0)
+                .status(PerpetualPositionStatus::OPEN)
+                .limit(10),
+        )
+        .await
+        .unwrap();
+    assert_eq!(positions.len(), 1);
+    let position = positions.iter().next().unwrap();
+    assert_eq!(position.market, MARKET);
+    assert_eq!(position.entry_price, decimal("50000"));
+    assert!(position.closed_at.is_none());
+}
+
+#[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+async fn test_get_sub_account_asset_positions() {
+    let positions = client()
+        .get_sub_account_asset_positions(PositionDetailsRequest::new(ADDRESS.to_string(), 0))
+        .await
+        .unwrap();
+    assert_eq!(positions.len(), 1);
+    let position = positions.iter().next().unwrap();
+    assert_eq!(position.symbol, "USDC");
+    assert_eq!(position.asset_id.as_deref(), Some("0"));
+}
+
+#[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+async fn test_get_sub_account_transfers() {
+    let transfers = client()
+        .get_sub_account_transfers(TransfersRequest::new(ADDRESS.to_string(), 0).limit(2))
+        .await
+        .unwrap();
+    assert_eq!(transfers.len(), 2);
+    assert_eq!(transfers.transfers[0].transfer_type, TransferType::Deposit);
+    assert_eq!(transfers.transfers[0].sender.subaccount_number, None);
+    assert_eq!(
+        transfers.transfers[1].transfer_type,
+        TransferType::TransferOut
+    );
+    assert_eq!(transfers.transfers[1].recipient.subaccount_number, Some(1));
+}
+
+#[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+async fn test_get_sub_account_orders() {
+    let orders = client()
+        .get_sub_account_orders(
+            OrdersRequest::new(ADDRESS.to_string(), 0)
+                .ticker(MARKET.to_string())
+                .status(OrderStatus::Open)
+                .limit(10),
+        )
+        .await
+        .unwrap();
+    assert_eq!(orders.len(), 1);
+    assert_eq!(orders[0].side, OrderSide::BUY);
+    assert_eq!(orders[0].status, OrderStatus::Open);
+    assert_eq!(orders[0].good_til_block, Some(20_000_020));
+}
+
+#[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+async fn test_get_order() {
+    let order = client()
+        .get_order("7b1e3bb5-7a3b-5c4b-9c6f-2e4a2a0c1d11".to_string())
+        .await
+        .unwrap();
+    assert_eq!(order.status, OrderStatus::Filled);
+    assert_eq!(order.total_filled, order.size);
+    assert!(order.good_til_block_time.is_some());
+}
+
+#[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+async fn test_get_sub_account_fills() {
+    let fills = client()
+        .get_sub_account_fills(
+            FillsRequest::new(ADDRESS.to_string(), 0)
+                .market(MARKET.to_string())
+                .limit(2),
+        )
+        .await
+        .unwrap();
+    assert_eq!(fills.len(), 2);
+    let fees: Decimal = fills.iter().map(|fill| fill.fee).sum();
+    assert_eq!(fees, decimal("0.45"));
+}
+
+#[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+async fn test_get_sub_account_historical_pnls() {
+    let pnl = client()
+        .get_sub_account_historical_pnls(
+            HistoricalPnLRequest::new(ADDRESS.to_string(), 0)
+                .created_on_or_after(time("2024-01-01T00:00:00Z")),
+        )
+        .await
+        .unwrap();
+    assert_eq!(pnl.len(), 2);
+    assert!(pnl.historical_pnl[0].block_height > pnl.historical_pnl[1].block_height);
+    assert_eq!(pnl.historical_pnl[0].total_pnl, decimal("12.34"));
+}
+
+#[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+async fn test_get_parent_sub_account() {
+    let parent = client()
+        .get_parent_sub_account(ADDRESS.to_string(), 0)
+        .await
+        .unwrap();
+    assert_eq!(parent.subaccount.equity, decimal("10762.34"));
+    assert_eq!(parent.subaccount.child_subaccounts.len(), 2);
+    assert_eq!(
+        parent.subaccount.child_subaccounts[1].subaccount_number,
+        128
+    );
+}
+
+#[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+async fn test_get_parent_sub_account_transfers() {
+    let transfers = client()
+        .get_parent_sub_account_transfers(
+            ParentSubaccountTransfersRequest::new(ADDRESS.to_string(), 0).limit(1),
+        )
+        .await
+        .unwrap();
+    assert_eq!(transfers.len(), 1);
+    assert_eq!(
+        transfers.transfers[0].recipient.subaccount_number,
+        Some(128)
+    );
+}
+
+#[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+async fn test_get_parent_sub_account_orders() {
+    let orders = client()
+        .get_parent_sub_account_orders(
+            ParentSubaccountOrdersRequest::new(ADDRESS.to_string(), 0)
+                .status(OrderStatus::Untriggered),
+        )
+        .await
+        .unwrap();
+    assert_eq!(orders.len(), 1);
+    assert_eq!(orders[0].trigger_price, Some(decimal("45000")));
+    assert!(orders[0].reduce_only);
+}
+
+#[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+async fn test_get_parent_sub_account_fills() {
+    let fills = client()
+        .get_parent_sub_account_fills(
+            ParentSubaccountFillsRequest::new(ADDRESS.to_string(), 0).limit(1),
+        )
+        .await
+        .unwrap();
+    assert_eq!(fills.len(), 1);
+    assert_eq!(fills.fills[0].market, "ETH-USD");
+}
+
+#[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+async fn test_get_transfers_between() {
+    let transfers = client()
+        .get_transfers_between(TransfersBetweenRequest::new(
+            ADDRESS.to_string(),
+            0,
+            ADDRESS.to_string(),
+            1,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(transfers.transfers_subset.len(), 1);
+    assert_eq!(transfers.total_net_transfers, decimal("-250"));
+}
+
+#[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+async fn test_get_historical_block_trading_rewards() {
+    let rewards = client()
+        .get_historical_block_trading_rewards(
+            HistoricalBlockTradingRewardsRequest::new(ADDRESS.to_string()).limit(2),
+        )
+        .await
+        .unwrap();
+    assert_eq!(rewards.len(), 2);
+    let total: Decimal = rewards.iter().map(|reward| reward.trading_reward).sum();
+    assert_eq!(total, decimal("0.003"));
+}
+
+#[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+async fn test_get_historical_trading_reward_aggregations() {
+    let rewards = client()
+        .get_historical_trading_reward_aggregations(
+            HistoricalTradingRewardAggregationsRequest::new(
+                ADDRESS.to_string(),
+                TradingRewardAggregationPeriod::Daily,
+            )
+            .limit(2),
+        )
+        .await
+        .unwrap();
+    assert_eq!(rewards.len(), 2);
+    let running = &rewards.rewards[0];
+    assert!(running.ended_at.is_none() && running.ended_at_height.is_none());
+    assert_eq!(rewards.rewards[1].ended_at_height, Some(20_000_000));
+}
+
+// ========================================
+// MarketsClient
+// ========================================
+
+#[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+async fn test_get_perpetual_markets() {
+    let client = client();
+    let markets = client
+        .get_perpetual_markets(PerpetualMarketsRequest::new())
+        .await
+        .unwrap();
+    assert_eq!(markets.markets.len(), 2);
+    assert_eq!(markets.get("ETH-USD").unwrap().tick_size, decimal("0.1"));
+
+    let markets = client
+        .get_perpetual_markets(PerpetualMarketsRequest::new().ticker(MARKET.to_string()))
+        .await
+        .unwrap();
+    assert_eq!(markets.markets.len(), 1);
+    assert_eq!(markets.get(MARKET).unwrap().step_base_quantums, 1_000_000);
+}
+
+#[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+async fn test_get_perpetual_market_orderbook() {
+    let orderbook = client()
+        .get_perpetual_market_orderbook(MARKET.to_string())
+        .await
+        .unwrap();
+    assert_eq!(orderbook.bids.len(), 2);
+    assert_eq!(orderbook.asks.len(), 2);
+    assert!(orderbook.bids[0].price < orderbook.asks[0].price);
+}
+
+#[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+async fn test_get_perpetual_market_trades() {
+    let trades = client()
+        .get_perpetual_market_trades(TradesRequest::new(MARKET.to_string()).limit(2))
+        .await
+        .unwrap();
+    assert_eq!(trades.len(), 2);
+    assert_eq!(trades.trades[0].side, OrderSide::SELL);
+    assert_eq!(trades.trades[1].created_at_height, 20_000_001);
+}
+
+#[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+async fn test_get_perpetual_market_candles() {
+    let candles = client()
+        .get_perpetual_market_candles(
+            CandlesRequest::new(MARKET.to_string(), CandleResolution::OneHour).limit(2),
+        )
+        .await
+        .unwrap();
+    assert_eq!(candles.len(), 2);
+    let candle = &candles.candles[0];
+    assert_eq!(candle.resolution, CandleResolution::OneHour);
+    assert!(candle.low <= candle.open && candle.open <= candle.high);
+}
+
+#[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+async fn test_get_perpetual_market_historical_funding() {
+    let funding = client()
+        .get_perpetual_market_historical_funding(
+            HistoricalFundingRequest::new(MARKET.to_string()).limit(2),
+        )
+        .await
+        .unwrap();
+    assert_eq!(funding.len(), 2);
+    assert_eq!(funding.historical_funding[1].rate, decimal("-0.000005"));
+}
+
+#[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+async fn test_get_perpetual_market_sparklines() {
+    let sparklines = client()
+        .get_perpetual_market_sparklines(SparklinesRequest::new(TimePeriod::OneDay))
+        .await
+        .unwrap();
+    assert_eq!(sparklines.len(), 2);
+    assert_eq!(sparklines[MARKET][0], decimal("50050"));
+}
+
+// ========================================
+// Recording
+// ========================================
+
+#[maybe_async::test(feature = "blocking", async(not(feature = "blocking"), tokio::test))]
+#[ignore = "needs the testnet indexer, DYDX_FIXTURE_ADDRESS and DYDX_FIXTURE_DIR"]
+async fn test_record_testnet() {
+    let address = std::env::var("DYDX_FIXTURE_ADDRESS").unwrap();
+    let recorder = FixtureRecorder::new(std::env::var("DYDX_FIXTURE_DIR").unwrap()).unwrap();
+    let client = IndexerClient::new(Network::testnet().indexer_config().clone(), None)
+        .unwrap()
+        .with_recorder(recorder);
+    let market = MARKET.to_string();
+
+    client
+        .get_sub_accounts(SubaccountsRequest::new(address.clone()).limit(2))
+        .await
+        .unwrap();
+    client.get_sub_account(address.clone(), 0).await.unwrap();
+    client
+        .get_sub_account_perpetual_positions(PositionDetailsRequest::new(address.clone(), 0))
+        .await
+        .unwrap();
+    client
+        .get_sub_account_asset_positions(PositionDetailsRequest::new(address.clone(), 0))
+        .await
+        .unwrap();
+    client
+        .get_sub_account_transfers(TransfersRequest::new(address.clone(), 0).limit(2))
+        .await
+        .unwrap();
+    let orders = client
+        .get_sub_account_orders(OrdersRequest::new(address.clone(), 0).limit(10))
+        .await
+        .unwrap();
+    if let Some(order) = orders.first() {
+        client.get_order(order.id.clone()).await.unwrap();
+    }
+    client
+        .get_sub_account_fills(FillsRequest::new(address.clone(), 0).limit(2))
+        .await
+        .unwrap();
+    client
+        .get_sub_account_historical_pnls(HistoricalPnLRequest::new(address.clone(), 0))
+        .await
+        .unwrap();
+    client
+        .get_parent_sub_account(address.clone(), 0)
+        .await
+        .unwrap();
+    client
+        .get_parent_sub_account_transfers(
+            ParentSubaccountTransfersRequest::new(address.clone(), 0).limit(1),
+        )
+        .await
+        .unwrap();
+    client
+        .get_parent_sub_account_orders(ParentSubaccountOrdersRequest::new(address.clone(), 0))
+        .await
+        .unwrap();
+    client
+        .get_parent_sub_account_fills(
+            ParentSubaccountFillsRequest::new(address.clone(), 0).limit(1),
+        )
+        .await
+        .unwrap();
+    client
+        .get_transfers_between(TransfersBetweenRequest::new(
+            address.clone(),
+            0,
+            address.clone(),
+            1,
+        ))
+        .await
+        .unwrap();
+    client
+        .get_historical_block_trading_rewards(
+            HistoricalBlockTradingRewardsRequest::new(address.clone()).limit(2),
+        )
+        .await
+        .unwrap();
+    client
+        .get_historical_trading_reward_aggregations(
+            HistoricalTradingRewardAggregationsRequest::new(
+                address.clone(),
+                TradingRewardAggregationPeriod::Daily,
+            )
+            .limit(2),
+        )
+        .await
+        .unwrap();
+
+    client
+        .get_perpetual_markets(PerpetualMarketsRequest::new())
+        .await
+        .unwrap();
+    client
+        .get_perpetual_markets(PerpetualMarketsRequest::new().ticker(market.clone()))
+        .await
+        .unwrap();
+    client
+        .get_perpetual_market_orderbook(market.clone())
+        .await
+        .unwrap();
+    client
+        .get_perpetual_market_trades(TradesRequest::new(market.clone()).limit(2))
+        .await
+        .unwrap();
+    client
+        .get_perpetual_market_candles(
+            CandlesRequest::new(market.clone(), CandleResolution::OneHour).limit(2),
+        )
+        .await
+        .unwrap();
+    client
+        .get_perpetual_market_historical_funding(
+            HistoricalFundingRequest::new(market.clone()).limit(2),
+        )
+        .await
+        .unwrap();
+    client
+        .get_perpetual_market_sparklines(SparklinesRequest::new(TimePeriod::OneDay))
+        .await
+        .unwrap();
+}